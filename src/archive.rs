@@ -0,0 +1,317 @@
+use crate::i18n::tr;
+
+// Guitar Pro 7 (.gp) and compressed MusicXML (.mxl) are zip files
+const ZIP_LOCAL: u32 = 0x04034b50;
+const ZIP_CENTRAL: u32 = 0x02014b50;
+const ZIP_END: u32 = 0x06054b50;
+// Guitar Pro 6 (.gpx) packs its files in 4 KiB sectors (BCFS), usually compressed (BCFZ)
+const GPX_SECTOR: usize = 0x1000;
+// the most a file inside either may unpack to; sizes come from the file itself, so a damaged one could ask for anything
+const MAX_UNPACKED: usize = 64 << 20;
+
+pub type Files = Vec<(String, Vec<u8>)>;
+
+pub fn is_zip(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"PK")
+}
+pub fn is_gpx(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"BCFZ") || bytes.starts_with(b"BCFS")
+}
+fn u16_at(bytes: &[u8], at: usize) -> Option<usize> {
+    bytes.get(at..at.checked_add(2)?).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+}
+fn u32_at(bytes: &[u8], at: usize) -> Option<usize> {
+    bytes.get(at..at.checked_add(4)?).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+}
+pub fn unzip(bytes: &[u8]) -> Result<Files, String> {
+    read_zip(bytes).ok_or_else(|| tr("song.error.damaged"))
+}
+// the central directory at the end of the file lists every entry and where it starts
+fn read_zip(bytes: &[u8]) -> Option<Files> {
+    let end = (0..bytes.len().saturating_sub(21)).rev().find(|at| u32_at(bytes, *at) == Some(ZIP_END as usize))?;
+    let count = u16_at(bytes, end + 10)?;
+    let mut entry = u32_at(bytes, end + 16)?;
+    let mut files = vec![];
+    for _ in 0..count {
+        if u32_at(bytes, entry)? != ZIP_CENTRAL as usize {
+            return None;
+        }
+        let method = u16_at(bytes, entry + 10)?;
+        let size = u32_at(bytes, entry + 20)?;
+        let name_length = u16_at(bytes, entry + 28)?;
+        let skip = name_length + u16_at(bytes, entry + 30)? + u16_at(bytes, entry + 32)?;
+        let local = u32_at(bytes, entry + 42)?;
+        let name = String::from_utf8_lossy(bytes.get(entry + 46..entry + 46 + name_length)?).to_string();
+        if u32_at(bytes, local)? != ZIP_LOCAL as usize {
+            return None;
+        }
+        let start = local.checked_add(30 + u16_at(bytes, local.checked_add(26)?)? + u16_at(bytes, local.checked_add(28)?)?)?;
+        let data = bytes.get(start..start.checked_add(size)?)?;
+        let data = match method {
+            0 => data.to_vec(),
+            8 => miniz_oxide::inflate::decompress_to_vec_with_limit(data, MAX_UNPACKED).ok()?,
+            _ => return None,
+        };
+        files.push((name, data));
+        entry += 46 + skip;
+    }
+    Some(files)
+}
+pub fn unpack_gpx(bytes: &[u8]) -> Result<Files, String> {
+    let sectors = match bytes.starts_with(b"BCFZ") {
+        true => decompress_bcfz(bytes),
+        false => bytes.get(4..).map(|b| b.to_vec()),
+    };
+    sectors.and_then(|s| read_bcfs(&s)).ok_or_else(|| tr("song.error.damaged"))
+}
+// BCFZ is a bit stream of literal bytes and copies of earlier output, read most significant bit first
+fn decompress_bcfz(bytes: &[u8]) -> Option<Vec<u8>> {
+    let expected = u32_at(bytes, 4).filter(|size| *size <= MAX_UNPACKED)?;
+    let mut bits = Bits { bytes: bytes.get(8..)?, at: 0 };
+    // each literal byte takes at least 8 bits, so only copies can make the output longer than the input's bits
+    let mut out: Vec<u8> = Vec::with_capacity(expected.min(bytes.len().saturating_mul(8)));
+    while out.len() < expected {
+        match bits.read(1)? {
+            1 => {
+                let width = bits.read(4)?;
+                let offset = bits.read_reversed(width)?;
+                let size = bits.read_reversed(width)?;
+                let from = out.len().checked_sub(offset)?;
+                for i in from..from + size.min(offset) {
+                    out.push(*out.get(i)?);
+                }
+            },
+            _ => {
+                for _ in 0..bits.read_reversed(2)? {
+                    out.push(bits.read(8)? as u8);
+                }
+            },
+        }
+    }
+    // the decompressed data starts with its own BCFS header
+    out.get(4..).map(|b| b.to_vec())
+}
+struct Bits<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+impl Bits<'_> {
+    fn bit(&mut self) -> Option<usize> {
+        let byte = self.bytes.get(self.at / 8)?;
+        let bit = (byte >> (7 - self.at % 8)) & 1;
+        self.at += 1;
+        Some(bit as usize)
+    }
+    fn read(&mut self, count: usize) -> Option<usize> {
+        (0..count).try_fold(0, |value, _| Some((value << 1) | self.bit()?))
+    }
+    fn read_reversed(&mut self, count: usize) -> Option<usize> {
+        (0..count).try_fold(0, |value, i| Some(value | (self.bit()? << i)))
+    }
+}
+// each file entry is a sector holding its name, its size and the list of sectors its data is in
+fn read_bcfs(bytes: &[u8]) -> Option<Files> {
+    let mut files = vec![];
+    let mut offset = GPX_SECTOR;
+    while offset + 4 <= bytes.len() {
+        if u32_at(bytes, offset) == Some(2) {
+            let name = bytes.get(offset + 0x04..offset + 0x04 + 127)?;
+            let name = String::from_utf8_lossy(name.split(|b| *b == 0).next().unwrap_or_default()).to_string();
+            let size = u32_at(bytes, offset + 0x8c).filter(|size| *size <= MAX_UNPACKED)?;
+            let mut data = vec![];
+            let mut pointer = offset + 0x94;
+            // a sector list can name the same sector over and over, so stop once there's enough
+            while let Some(sector) = u32_at(bytes, pointer).filter(|s| *s != 0 && data.len() < size) {
+                let start = sector.checked_mul(GPX_SECTOR)?;
+                data.extend_from_slice(bytes.get(start..start.saturating_add(GPX_SECTOR).min(bytes.len()))?);
+                pointer += 4;
+            }
+            data.truncate(size);
+            files.push((name, data));
+        }
+        offset += GPX_SECTOR;
+    }
+    Some(files)
+}
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // a zip of (name, data, deflated) entries, as the central directory describes them
+    pub fn zip(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut central = vec![];
+        for (name, data, deflate) in files {
+            let (method, stored) = match deflate {
+                true => (8u16, miniz_oxide::deflate::compress_to_vec(data, 6)),
+                false => (0u16, data.to_vec()),
+            };
+            let local = bytes.len() as u32;
+            // version and flags, then time, date and CRC, which aren't read
+            bytes.extend(ZIP_LOCAL.to_le_bytes());
+            bytes.extend([20, 0, 0, 0]);
+            bytes.extend(method.to_le_bytes());
+            bytes.extend([0; 8]);
+            bytes.extend((stored.len() as u32).to_le_bytes());
+            bytes.extend((data.len() as u32).to_le_bytes());
+            bytes.extend((name.len() as u16).to_le_bytes());
+            bytes.extend([0, 0]);
+            bytes.extend(name.as_bytes());
+            bytes.extend(&stored);
+            central.extend(ZIP_CENTRAL.to_le_bytes());
+            central.extend([20, 0, 20, 0, 0, 0]);
+            central.extend(method.to_le_bytes());
+            central.extend([0; 8]);
+            central.extend((stored.len() as u32).to_le_bytes());
+            central.extend((data.len() as u32).to_le_bytes());
+            central.extend((name.len() as u16).to_le_bytes());
+            central.extend([0; 12]);
+            central.extend(local.to_le_bytes());
+            central.extend(name.as_bytes());
+        }
+        let start = bytes.len() as u32;
+        bytes.extend(&central);
+        bytes.extend(ZIP_END.to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.extend((files.len() as u16).to_le_bytes());
+        bytes.extend((files.len() as u16).to_le_bytes());
+        bytes.extend((central.len() as u32).to_le_bytes());
+        bytes.extend(start.to_le_bytes());
+        bytes.extend([0, 0]);
+        bytes
+    }
+    // a BCFS file system with an entry sector per file, followed by its data sectors
+    pub fn bcfs(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut sectors = vec![0u8; GPX_SECTOR];
+        for (name, data) in files {
+            let entry = sectors.len();
+            sectors.resize(entry + GPX_SECTOR, 0);
+            sectors[entry..entry + 4].copy_from_slice(&2u32.to_le_bytes());
+            sectors[entry + 4..entry + 4 + name.len()].copy_from_slice(name.as_bytes());
+            sectors[entry + 0x8c..entry + 0x90].copy_from_slice(&(data.len() as u32).to_le_bytes());
+            for (i, chunk) in data.chunks(GPX_SECTOR).enumerate() {
+                let sector = (sectors.len() / GPX_SECTOR) as u32;
+                sectors[entry + 0x94 + 4 * i..entry + 0x98 + 4 * i].copy_from_slice(&sector.to_le_bytes());
+                sectors.extend(chunk);
+                sectors.resize(sectors.len().div_ceil(GPX_SECTOR) * GPX_SECTOR, 0);
+            }
+        }
+        [b"BCFS".as_slice(), &sectors].concat()
+    }
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        at: usize,
+    }
+    impl BitWriter {
+        fn bit(&mut self, bit: usize) {
+            if self.at.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if bit == 1 {
+                *self.bytes.last_mut().unwrap() |= 1 << (7 - self.at % 8);
+            }
+            self.at += 1;
+        }
+        fn write(&mut self, value: usize, count: usize) {
+            (0..count).rev().for_each(|i| self.bit((value >> i) & 1));
+        }
+        fn write_reversed(&mut self, value: usize, count: usize) {
+            (0..count).for_each(|i| self.bit((value >> i) & 1));
+        }
+        fn literals(&mut self, bytes: &[u8]) {
+            for chunk in bytes.chunks(3) {
+                self.bit(0);
+                self.write_reversed(chunk.len(), 2);
+                chunk.iter().for_each(|b| self.write(*b as usize, 8));
+            }
+        }
+        fn copy(&mut self, offset: usize, size: usize) {
+            self.bit(1);
+            self.write(4, 4);
+            self.write_reversed(offset, 4);
+            self.write_reversed(size, 4);
+        }
+    }
+    fn bcfz(size: usize, bits: BitWriter) -> Vec<u8> {
+        [b"BCFZ".as_slice(), &(size as u32).to_le_bytes(), &bits.bytes].concat()
+    }
+    // BCFZ with only literals, for whole files
+    pub fn gpx(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let sectors = bcfs(files);
+        let mut bits = BitWriter::default();
+        bits.literals(&sectors);
+        bcfz(sectors.len(), bits)
+    }
+
+    #[test]
+    fn unzips_stored_and_deflated_entries() {
+        let bytes = zip(&[("a.txt", b"plain", false), ("dir/b.xml", &b"<x/>".repeat(100), true)]);
+        assert!(is_zip(&bytes));
+        let files = unzip(&bytes).unwrap();
+        assert_eq!(files, vec![("a.txt".to_string(), b"plain".to_vec()), ("dir/b.xml".to_string(), b"<x/>".repeat(100))]);
+    }
+    #[test]
+    fn damaged_zips_are_errors() {
+        let bytes = zip(&[("a.txt", b"plain", false), ("b.txt", &[7; 300], true)]);
+        for length in [0, 4, 30, bytes.len() / 2, bytes.len() - 1] {
+            assert!(unzip(&bytes[..length]).is_err(), "cut at {}", length);
+        }
+        // the central directory said to be past the end of the file
+        let mut far = bytes.clone();
+        let end = far.len() - 22;
+        far[end + 16..end + 20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(unzip(&far).is_err());
+        // a deflated entry that isn't deflate data
+        let mut garbled = zip(&[("b.txt", &[7; 300], true)]);
+        garbled[30 + 5..30 + 12].fill(0xff);
+        assert!(unzip(&garbled).is_err());
+    }
+    #[test]
+    fn reads_bcfs_up_to_the_last_sector() {
+        let long: Vec<u8> = (0..5000).map(|i| (i % 251) as u8).collect();
+        let bytes = bcfs(&[("Content/long.bin", &long), ("Content/empty", b"")]);
+        assert!(is_gpx(&bytes));
+        let files = unpack_gpx(&bytes).unwrap();
+        assert_eq!(files, vec![("Content/long.bin".to_string(), long), ("Content/empty".to_string(), vec![])]);
+    }
+    #[test]
+    fn damaged_bcfs_is_an_error() {
+        let mut bytes = bcfs(&[("a", b"data")]);
+        // a data sector number far past the end of the file
+        let pointer = 4 + GPX_SECTOR + 0x94;
+        bytes[pointer..pointer + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(unpack_gpx(&bytes).is_err());
+        // a size no file could need
+        let mut bytes = bcfs(&[("a", b"data")]);
+        let size = 4 + GPX_SECTOR + 0x8c;
+        bytes[size..size + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(unpack_gpx(&bytes).is_err());
+    }
+    #[test]
+    fn decompresses_bcfz_literals_and_copies() {
+        let files = [("score.gpif", b"<GPIF/>".as_slice())];
+        assert_eq!(unpack_gpx(&gpx(&files)).unwrap(), unpack_gpx(&bcfs(&files)).unwrap());
+        // "BCFSabc" then "abc" copied from 3 back, twice as far as the copy can reach in one go
+        let mut bits = BitWriter::default();
+        bits.literals(b"BCFSabc");
+        bits.copy(3, 5);
+        assert_eq!(decompress_bcfz(&bcfz(10, bits)), Some(b"abcabc".to_vec()));
+    }
+    #[test]
+    fn damaged_bcfz_is_an_error() {
+        let bytes = gpx(&[("score.gpif", b"<GPIF/>")]);
+        for length in [4, 8, 100, bytes.len() - 1] {
+            assert!(unpack_gpx(&bytes[..length]).is_err(), "cut at {}", length);
+        }
+        // a header asking for 4 GiB is refused before anything is read
+        let mut huge = bytes.clone();
+        huge[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(unpack_gpx(&huge).is_err());
+        // a copy from before the start of the output
+        let mut bits = BitWriter::default();
+        bits.literals(b"BC");
+        bits.copy(9, 3);
+        assert!(decompress_bcfz(&bcfz(10, bits)).is_none());
+    }
+}
@@ -0,0 +1,242 @@
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::instruments::FretPos;
+use crate::positions::strings_by_pitch;
+use crate::scales::{NoteType, TOTAL_TONES};
+
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+pub enum ChordQuality {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Major7,
+    Minor7,
+    Dominant7,
+    HalfDiminished7,
+    Diminished7,
+}
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+pub enum Picking {
+    Sweep,
+    Economy,
+}
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PickStroke {
+    Down,
+    Up,
+    Legato,
+}
+impl PickStroke {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PickStroke::Down => "↓",
+            PickStroke::Up => "↑",
+            PickStroke::Legato => "h",
+        }
+    }
+}
+impl ChordQuality {
+    pub fn intervals(&self) -> Vec<usize> {
+        match self {
+            ChordQuality::Major => vec![0, 4, 7],
+            ChordQuality::Minor => vec![0, 3, 7],
+            ChordQuality::Diminished => vec![0, 3, 6],
+            ChordQuality::Augmented => vec![0, 4, 8],
+            ChordQuality::Major7 => vec![0, 4, 7, 11],
+            ChordQuality::Minor7 => vec![0, 3, 7, 10],
+            ChordQuality::Dominant7 => vec![0, 4, 7, 10],
+            ChordQuality::HalfDiminished7 => vec![0, 3, 6, 10],
+            ChordQuality::Diminished7 => vec![0, 3, 6, 9],
+        }
+    }
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ChordQuality::Major => "",
+            ChordQuality::Minor => "m",
+            ChordQuality::Diminished => "dim",
+            ChordQuality::Augmented => "aug",
+            ChordQuality::Major7 => "maj7",
+            ChordQuality::Minor7 => "m7",
+            ChordQuality::Dominant7 => "7",
+            ChordQuality::HalfDiminished7 => "m7♭5",
+            ChordQuality::Diminished7 => "dim7",
+        }
+    }
+}
+// chord-tone label for an interval above the chord root
+pub fn chord_degree(interval: usize) -> &'static str {
+    match interval % TOTAL_TONES {
+        0 => "R",
+        3 => "♭3",
+        4 => "3",
+        6 => "♭5",
+        7 => "5",
+        8 => "♯5",
+        9 => "♭♭7",
+        10 => "♭7",
+        11 => "7",
+        _ => "",
+    }
+}
+// the chord of three or four notes stacked in thirds on a degree of a scale given as semitones above its tonic
+pub fn diatonic_chord(scale: &[usize], degree: usize, notes: usize) -> Option<ChordQuality> {
+    let root = *scale.get(degree)?;
+    let intervals: Vec<usize> = (0..notes).map(|i| (scale[(degree + 2 * i) % scale.len()] + TOTAL_TONES - root) % TOTAL_TONES).collect();
+    ChordQuality::iter().find(|q| q.intervals() == intervals)
+}
+pub fn chord_note_type(interval: usize) -> NoteType {
+    match interval % TOTAL_TONES {
+        0 => NoteType::Root,
+        3 | 4 | 6 | 7 | 8 => NoteType::Triad,
+        _ => NoteType::InPentatonic,
+    }
+}
+pub struct Arpeggio {
+    pub root: usize,
+    pub quality: ChordQuality,
+    pub octaves: usize,
+    pub picking: Picking,
+    pub start_fret: usize,
+}
+impl Default for Arpeggio {
+    fn default() -> Self {
+        Self {
+            root: 0,
+            quality: ChordQuality::Major,
+            octaves: 2,
+            picking: Picking::Sweep,
+            start_fret: 5,
+        }
+    }
+}
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ArpNote {
+    pub at: FretPos,
+    pub stroke: PickStroke,
+}
+impl Arpeggio {
+    pub fn name(&self, root_name: &str) -> String {
+        format!("{}{}", root_name, self.quality.symbol())
+    }
+    // the fret of the first root at or above start_fret on a string, an octave down when
+    // that's past the last fret
+    fn first_root(&self, open: usize, frets: usize) -> usize {
+        let mut first = (self.start_fret..self.start_fret + TOTAL_TONES)
+            .find(|fret| (open + fret) % TOTAL_TONES == self.root % TOTAL_TONES)
+            .unwrap_or(self.start_fret);
+        while first > frets && first >= TOTAL_TONES {
+            first -= TOTAL_TONES;
+        }
+        first
+    }
+    // the chord held as one shape: the root on the lowest string where the run starts, then on each
+    // higher string the chord tone within the hand's four frets, one not yet in the shape if there is one
+    pub fn chord(&self, strings: &[usize], frets: usize) -> Vec<FretPos> {
+        let order = strings_by_pitch(strings);
+        let Some(&lowest) = order.first() else {
+            return vec![];
+        };
+        let first = self.first_root(strings[lowest], frets);
+        let intervals = self.quality.intervals();
+        let tone = |note: usize| intervals.iter().position(|i| (self.root + i) % TOTAL_TONES == note % TOTAL_TONES);
+        let mut held = vec![0];
+        let mut chord = vec![FretPos { string: lowest, fret: first }];
+        for string in order.into_iter().skip(1) {
+            let best = (first..=(first + 3).min(frets))
+                .filter_map(|fret| tone(strings[string] + fret).map(|t| (fret, t)))
+                .min_by_key(|(fret, t)| (held.contains(t), *fret));
+            if let Some((fret, t)) = best {
+                held.push(t);
+                chord.push(FretPos { string, fret });
+            }
+        }
+        chord
+    }
+    // the ascending run: start on the lowest string at the first root at or above
+    // start_fret (within the frets), then keep each note on the current string until it is out of
+    // the hand's reach or the picking style wants to cross to the next string
+    pub fn notes(&self, strings: &[usize], frets: usize) -> Vec<ArpNote> {
+        let order = strings_by_pitch(strings);
+        let Some(&lowest) = order.first() else {
+            return vec![];
+        };
+        let open = strings[lowest];
+        let first = self.first_root(open, frets);
+        let start = open + first;
+        let mut pitches: Vec<usize> = (0..self.octaves.max(1))
+            .flat_map(|octave| self.quality.intervals().into_iter().map(move |i| start + octave * TOTAL_TONES + i))
+            .collect();
+        pitches.push(start + self.octaves.max(1) * TOTAL_TONES);
+        let per_string = match self.picking {
+            Picking::Sweep => 1,
+            Picking::Economy => 2,
+        };
+        let mut notes: Vec<ArpNote> = vec![];
+        let mut k = 0;
+        let mut on_string = 0;
+        let mut string_start = first;
+        for pitch in pitches {
+            let here = pitch.checked_sub(strings[order[k]]);
+            let next = order.get(k + 1).and_then(|s| pitch.checked_sub(strings[*s]));
+            // the hand may reach one fret back from where it started on this string
+            let out_of_reach = here.is_none_or(|fret| fret > string_start + 4);
+            let wants_to_cross = on_string >= per_string && next.is_some_and(|fret| fret + 1 >= string_start);
+            if let Some(fret) = next.filter(|_| !notes.is_empty() && (out_of_reach || wants_to_cross)) {
+                k += 1;
+                on_string = 0;
+                string_start = fret;
+            }
+            let string = order[k];
+            let Some(fret) = pitch.checked_sub(strings[string]) else {
+                continue;
+            };
+            // ascending: each new string starts with a downstroke (sweeping through),
+            // notes after it on the same string are hammered (sweep) or alternate (economy)
+            let stroke = match (on_string, self.picking) {
+                (0, _) => PickStroke::Down,
+                (_, Picking::Sweep) => PickStroke::Legato,
+                (n, Picking::Economy) if n % 2 == 1 => PickStroke::Up,
+                (_, Picking::Economy) => PickStroke::Down,
+            };
+            notes.push(ArpNote { at: FretPos { string, fret }, stroke });
+            on_string += 1;
+        }
+        notes
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scales::ScaleType;
+
+    // standard guitar tuning from the low E, C being 0
+    const GUITAR: [usize; 6] = [4, 9, 14, 19, 23, 28];
+
+    #[test]
+    fn major_scale_chords() {
+        use ChordQuality::*;
+        let chords = |notes| (0..7).map(|d| diatonic_chord(&ScaleType::Major.diatonic(), d, notes)).collect::<Vec<_>>();
+        assert_eq!(chords(3), [Some(Major), Some(Minor), Some(Minor), Some(Major), Some(Major), Some(Minor), Some(Diminished)]);
+        assert_eq!(chords(4), [Some(Major7), Some(Minor7), Some(Minor7), Some(Major7), Some(Dominant7), Some(Minor7), Some(HalfDiminished7)]);
+    }
+    #[test]
+    fn chords_are_held_as_barre_shapes() {
+        let frets = |quality, root, start_fret| {
+            let arpeggio = Arpeggio { root, quality, start_fret, ..Arpeggio::default() };
+            arpeggio.chord(&GUITAR, 12).iter().map(|at| (at.string, at.fret)).collect::<Vec<_>>()
+        };
+        assert_eq!(frets(ChordQuality::Major, 0, 5), [(0, 8), (1, 10), (2, 10), (3, 9), (4, 8), (5, 8)]);
+        assert_eq!(frets(ChordQuality::Minor, 0, 5), [(0, 8), (1, 10), (2, 10), (3, 8), (4, 8), (5, 8)]);
+        assert_eq!(frets(ChordQuality::Major, 4, 0), [(0, 0), (1, 2), (2, 2), (3, 1), (4, 0), (5, 0)]);
+    }
+    #[test]
+    fn runs_start_on_the_fretboard() {
+        // the first A at or above fret 10 on the low E is at 17, so a 12-fret board starts at 5
+        let arpeggio = Arpeggio { root: 9, start_fret: 10, ..Arpeggio::default() };
+        assert_eq!(arpeggio.notes(&GUITAR, 24)[0].at, FretPos { string: 0, fret: 17 });
+        assert_eq!(arpeggio.notes(&GUITAR, 12)[0].at, FretPos { string: 0, fret: 5 });
+        assert_eq!(arpeggio.chord(&GUITAR, 12)[0], FretPos { string: 0, fret: 5 });
+    }
+}
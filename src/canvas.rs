@@ -0,0 +1,221 @@
+use std::fmt::Write;
+use egui::{Align, Align2, Color32, CornerRadius, FontFamily, FontId, Painter, Pos2, Rect, Stroke, StrokeKind};
+
+// the shapes the fretboard diagram is made of, so it can be drawn on screen or written to a file
+pub trait Canvas {
+    fn line(&mut self, from: Pos2, to: Pos2, stroke: Stroke);
+    fn circle(&mut self, center: Pos2, radius: f32, fill: Color32, stroke: Stroke);
+    fn rect(&mut self, rect: Rect, radius: f32, fill: Color32, stroke: Stroke);
+    fn text(&mut self, pos: Pos2, anchor: Align2, text: &str, font: FontId, color: Color32);
+    // limit what follows to rect, or stop limiting with None
+    fn clip(&mut self, rect: Option<Rect>);
+}
+impl Canvas for Painter {
+    fn line(&mut self, from: Pos2, to: Pos2, stroke: Stroke) {
+        self.line_segment([from, to], stroke);
+    }
+    fn circle(&mut self, center: Pos2, radius: f32, fill: Color32, stroke: Stroke) {
+        Painter::circle(self, center, radius, fill, stroke);
+    }
+    fn rect(&mut self, rect: Rect, radius: f32, fill: Color32, stroke: Stroke) {
+        Painter::rect(self, rect, CornerRadius::same(radius as u8), fill, stroke, StrokeKind::Middle);
+    }
+    fn text(&mut self, pos: Pos2, anchor: Align2, text: &str, font: FontId, color: Color32) {
+        Painter::text(self, pos, anchor, text, font, color);
+    }
+    fn clip(&mut self, rect: Option<Rect>) {
+        self.set_clip_rect(rect.unwrap_or(Rect::EVERYTHING));
+    }
+}
+// an SVG document showing the viewport, in the same coordinates as the screen
+pub struct Svg {
+    viewport: Rect,
+    body: String,
+    clips: usize,
+    clipped: bool,
+}
+impl Svg {
+    pub fn new(viewport: Rect, background: Color32) -> Svg {
+        let mut svg = Svg { viewport, body: String::new(), clips: 0, clipped: false };
+        svg.rect(viewport, 0f32, background, Stroke::NONE);
+        svg
+    }
+    pub fn finish(mut self) -> String {
+        self.clip(None);
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" viewBox=\"{x:.0} {y:.0} {w:.0} {h:.0}\">\n{}</svg>\n",
+            self.body, x = self.viewport.left(), y = self.viewport.top(), w = self.viewport.width(), h = self.viewport.height(),
+        )
+    }
+}
+impl Canvas for Svg {
+    fn line(&mut self, from: Pos2, to: Pos2, stroke: Stroke) {
+        let _ = writeln!(self.body, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"{}/>", from.x, from.y, to.x, to.y, stroke_attributes(stroke));
+    }
+    fn circle(&mut self, center: Pos2, radius: f32, fill: Color32, stroke: Stroke) {
+        let _ = writeln!(self.body, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\"{}{}/>", center.x, center.y, radius, paint("fill", fill), stroke_attributes(stroke));
+    }
+    fn rect(&mut self, rect: Rect, radius: f32, fill: Color32, stroke: Stroke) {
+        let _ = writeln!(
+            self.body, "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"{:.1}\"{}{}/>",
+            rect.left(), rect.top(), rect.width(), rect.height(), radius, paint("fill", fill), stroke_attributes(stroke),
+        );
+    }
+    fn text(&mut self, pos: Pos2, anchor: Align2, text: &str, font: FontId, color: Color32) {
+        if text.is_empty() {
+            return;
+        }
+        let text_anchor = match anchor.x() {
+            Align::Min => "start",
+            Align::Center => "middle",
+            Align::Max => "end",
+        };
+        let baseline = match anchor.y() {
+            Align::Min => "hanging",
+            Align::Center => "central",
+            Align::Max => "text-after-edge",
+        };
+        // the app draws everything in Lucida, bundled with it
+        let family = match font.family {
+            FontFamily::Monospace => "'Lucida Sans Typewriter', 'Lucida Console', monospace",
+            _ => "'Lucida Sans Unicode', 'Lucida Grande', sans-serif",
+        };
+        let _ = writeln!(
+            self.body, "<text x=\"{:.1}\" y=\"{:.1}\" font-family=\"{}\" font-size=\"{:.1}\" text-anchor=\"{}\" dominant-baseline=\"{}\"{}>{}</text>",
+            pos.x, pos.y, family, font.size, text_anchor, baseline, paint("fill", color), escape(text),
+        );
+    }
+    fn clip(&mut self, rect: Option<Rect>) {
+        if self.clipped {
+            self.body.push_str("</g>\n");
+            self.clipped = false;
+        }
+        if let Some(rect) = rect {
+            self.clips += 1;
+            let _ = writeln!(
+                self.body, "<clipPath id=\"clip{}\"><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"/></clipPath><g clip-path=\"url(#clip{})\">",
+                self.clips, rect.left(), rect.top(), rect.width(), rect.height(), self.clips,
+            );
+            self.clipped = true;
+        }
+    }
+}
+// egui colours are premultiplied; SVG wants the plain colour and its opacity
+fn paint(attribute: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    match a {
+        0 => format!(" {}=\"none\"", attribute),
+        255 => format!(" {}=\"#{:02x}{:02x}{:02x}\"", attribute, r, g, b),
+        _ => format!(" {}=\"#{:02x}{:02x}{:02x}\" {}-opacity=\"{:.2}\"", attribute, r, g, b, attribute, a as f32 / 255f32),
+    }
+}
+fn stroke_attributes(stroke: Stroke) -> String {
+    match stroke.is_empty() {
+        true => String::new(),
+        false => format!("{} stroke-width=\"{:.1}\"", paint("stroke", stroke.color), stroke.width),
+    }
+}
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use quick_xml::events::{BytesStart, Event};
+    use quick_xml::Reader;
+
+    pub struct Tag {
+        pub name: String,
+        pub attributes: Vec<(String, String)>,
+        pub text: String,
+    }
+    impl Tag {
+        pub fn attr(&self, name: &str) -> Option<&str> {
+            self.attributes.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+        }
+    }
+    fn tag(start: &BytesStart) -> Tag {
+        Tag {
+            name: String::from_utf8_lossy(start.name().as_ref()).to_string(),
+            attributes: start.attributes()
+                .map(|a| a.expect("well-formed attribute"))
+                .map(|a| (String::from_utf8_lossy(a.key.as_ref()).to_string(), a.unescape_value().unwrap().to_string()))
+                .collect(),
+            text: String::new(),
+        }
+    }
+    // every element in document order, failing on anything that isn't well-formed XML with one <svg> root
+    pub fn parse(svg: &str) -> Vec<Tag> {
+        let mut reader = Reader::from_str(svg);
+        reader.config_mut().check_end_names = true;
+        let mut tags = vec![];
+        let mut open: Vec<usize> = vec![];
+        let mut roots = 0;
+        loop {
+            let event = reader.read_event().expect("well-formed XML");
+            if let (Event::Start(_) | Event::Empty(_), true) = (&event, open.is_empty()) {
+                roots += 1;
+            }
+            match event {
+                Event::Start(start) => {
+                    open.push(tags.len());
+                    tags.push(tag(&start));
+                },
+                Event::Empty(start) => tags.push(tag(&start)),
+                Event::Text(text) => match open.last() {
+                    Some(i) => tags[*i].text.push_str(&text.unescape().unwrap()),
+                    None => assert!(text.unescape().unwrap().trim().is_empty(), "text outside the root"),
+                },
+                Event::End(_) => {
+                    open.pop();
+                },
+                Event::Eof => break,
+                _ => {},
+            }
+        }
+        assert!(open.is_empty(), "unclosed elements");
+        assert_eq!(roots, 1);
+        assert_eq!(tags[0].name, "svg");
+        tags
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        let mut svg = Svg::new(Rect::from_min_size(Pos2::ZERO, egui::vec2(100f32, 50f32)), Color32::WHITE);
+        svg.text(Pos2::new(50f32, 25f32), Align2::CENTER_CENTER, "<A & B>", FontId::proportional(12f32), Color32::BLACK);
+        let svg = svg.finish();
+        assert!(svg.contains(">&lt;A &amp; B&gt;</text>"));
+        let tags = parse(&svg);
+        let text = tags.iter().find(|t| t.name == "text").unwrap();
+        assert_eq!(text.text, "<A & B>");
+        assert_eq!(text.attr("text-anchor"), Some("middle"));
+    }
+    #[test]
+    fn the_background_fills_the_viewport() {
+        let viewport = Rect::from_min_size(Pos2::new(-10f32, -5f32), egui::vec2(200f32, 80f32));
+        let tags = parse(&Svg::new(viewport, Color32::from_rgb(0x1b, 0x1b, 0x1b)).finish());
+        let root = &tags[0];
+        assert_eq!((root.attr("width"), root.attr("height")), (Some("200"), Some("80")));
+        assert_eq!(root.attr("viewBox"), Some("-10 -5 200 80"));
+        let background = &tags[1];
+        assert_eq!(background.name, "rect");
+        assert_eq!(background.attr("fill"), Some("#1b1b1b"));
+        assert_eq!((background.attr("width"), background.attr("height")), (Some("200.0"), Some("80.0")));
+    }
+    #[test]
+    fn clips_and_translucent_paint_stay_well_formed() {
+        let viewport = Rect::from_min_size(Pos2::ZERO, egui::vec2(100f32, 100f32));
+        let mut svg = Svg::new(viewport, Color32::TRANSPARENT);
+        svg.clip(Some(viewport.shrink(10f32)));
+        svg.circle(Pos2::new(50f32, 50f32), 10f32, Color32::from_black_alpha(128), Stroke::new(1f32, Color32::RED));
+        svg.clip(Some(viewport.shrink(20f32)));
+        svg.line(Pos2::ZERO, Pos2::new(100f32, 100f32), Stroke::new(2f32, Color32::BLUE));
+        let tags = parse(&svg.finish());
+        assert_eq!(tags[1].attr("fill"), Some("none"));
+        assert_eq!(tags.iter().filter(|t| t.name == "clipPath").count(), 2);
+        assert_eq!(tags.iter().filter(|t| t.name == "g").count(), 2);
+        let circle = tags.iter().find(|t| t.name == "circle").unwrap();
+        assert_eq!(circle.attr("fill-opacity"), Some("0.50"));
+        assert_eq!(circle.attr("stroke"), Some("#ff0000"));
+    }
+}
@@ -0,0 +1,363 @@
+use std::fmt::{Debug, Write as _};
+use std::io::Write as _;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use crate::fretboard::{full_name, FretboardApp, NoteMarker};
+use crate::i18n::trf;
+use crate::instruments::Instrument;
+use crate::scales::{key_letter, keys, Naming, Numbering, Scale, ScaleSize, ScaleType, LETTERS, LETTER_TONES, TOTAL_TONES};
+use crate::tab;
+
+const USAGE: &str = "\
+usage: fretboard_scales [COMMAND [OPTIONS]]
+
+Without a command the app opens its window.
+
+commands:
+  scale          show a scale on an instrument
+  instruments    list the instruments and their tunings
+  help           show this message
+
+scale options:
+  --key NOTE         the tonic, e.g. A, F#, Bb (default G)
+  --type TYPE        major, minor, dorian, phrygian, lydian, mixolydian or locrian (default minor)
+  --size SIZE        diatonic, pentatonic, blues, triads or root (default pentatonic)
+  --instrument NAME  guitar, violin, mandolin, cello, ukulele or banjo (default guitar)
+  --tuning NAME      one of the instrument's tunings, e.g. \"DROP D\" (default its first)
+  --frets N          frets shown, 4 to 25 (default 12)
+  --numbers          mark notes by scale degree instead of by name
+  --format FORMAT    text, notes, json, svg, png or pdf (default text)
+  --horizontal       draw SVG, PNG and PDF with the neck across rather than down
+  --per-page N       PDF diagrams per page, 1 to 6 (default 4); the PDF has all twelve keys
+  --output FILE      write to FILE instead of standard output
+";
+
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+enum Format {
+    Text,
+    Notes,
+    Json,
+    Svg,
+    Png,
+    Pdf,
+}
+struct Options {
+    scale: Scale,
+    instrument: Instrument,
+    // where the instrument is in the app's list, for drawing
+    instrument_index: usize,
+    frets: usize,
+    numbers: bool,
+    format: Format,
+    vertical: bool,
+    per_page: usize,
+    output: Option<String>,
+}
+// run the command in args and return the exit code
+pub fn run(args: &[String]) -> i32 {
+    let result = match args[0].as_str() {
+        "scale" => parse_scale(&args[1..]).and_then(|options| {
+            let bytes = scale(&options)?;
+            write_output(options.output.as_deref(), &bytes)
+        }),
+        "instruments" => write_output(None, instruments().as_bytes()),
+        "help" | "--help" | "-h" => write_output(None, USAGE.as_bytes()),
+        command => Err(format!("unknown command '{}'", command)),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("fretboard_scales: {}", e);
+            eprintln!("try 'fretboard_scales help'");
+            1
+        },
+    }
+}
+fn parse_scale(args: &[String]) -> Result<Options, String> {
+    let mut key = String::from("G");
+    let mut typ = ScaleType::Minor;
+    let mut siz = ScaleSize::Pentatonic;
+    let mut instrument = String::from("guitar");
+    let mut tuning = None;
+    let mut frets = String::from("12");
+    let mut numbers = false;
+    let mut format = Format::Text;
+    let mut vertical = true;
+    let mut per_page = String::from("4");
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        // both `--key A` and `--key=A`
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || inline.clone().or_else(|| args.next().cloned()).ok_or_else(|| format!("{} needs a value", name));
+        match name {
+            "--key" => key = value()?,
+            "--type" => typ = parse_named(&value()?, "scale type")?,
+            "--size" => siz = parse_named(&value()?, "scale size")?,
+            "--instrument" => instrument = value()?,
+            "--tuning" => tuning = Some(value()?),
+            "--frets" => frets = value()?,
+            "--numbers" => numbers = true,
+            "--format" => format = parse_named(&value()?, "format")?,
+            "--horizontal" => vertical = false,
+            "--per-page" => per_page = value()?,
+            "--output" | "-o" => output = Some(value()?),
+            _ => return Err(format!("unknown option '{}'", arg)),
+        }
+    }
+    let (key, key_letter) = parse_key(&key, typ)?;
+    let instruments = Instrument::all();
+    let instrument_index = instruments.iter().position(|i| i.name.eq_ignore_ascii_case(&instrument))
+        .ok_or_else(|| format!("unknown instrument '{}'", instrument))?;
+    let mut instrument = instruments.into_iter().nth(instrument_index).unwrap_or_else(Instrument::none);
+    if let Some(tuning) = tuning {
+        instrument.tune_index = instrument.tunings.iter().position(|t| t.name.eq_ignore_ascii_case(&tuning))
+            .ok_or_else(|| format!("{} has no tuning '{}'", instrument.name, tuning))?;
+    }
+    let frets = frets.parse().ok().filter(|f| (4..=25).contains(f))
+        .ok_or_else(|| format!("frets must be a number from 4 to 25, not '{}'", frets))?;
+    let per_page = per_page.parse().ok().filter(|n| (1..=6).contains(n))
+        .ok_or_else(|| format!("per-page must be a number from 1 to 6, not '{}'", per_page))?;
+    Ok(Options {
+        scale: Scale { typ, siz, key, key_letter, numbering: Numbering::Simple, naming: Naming::English },
+        instrument,
+        instrument_index,
+        frets,
+        numbers,
+        format,
+        vertical,
+        per_page,
+        output,
+    })
+}
+// an enum value by its name in any case, where a trailing "only" can be left off
+fn parse_named<T: IntoEnumIterator + Debug>(value: &str, what: &str) -> Result<T, String> {
+    let value = value.to_lowercase();
+    T::iter()
+        .find(|v| {
+            let name = format!("{:?}", v).to_lowercase();
+            name == value || name.strip_suffix("only") == Some(value.as_str())
+        })
+        .ok_or_else(|| format!("unknown {} '{}'", what, value))
+}
+// a letter and any sharps (# or ♯) or flats (b or ♭), keeping the spelling unless the key can't be written with it
+fn parse_key(name: &str, typ: ScaleType) -> Result<(usize, usize), String> {
+    let mut chars = name.chars();
+    let letter = chars.next().and_then(|c| LETTERS.iter().position(|l| l.eq_ignore_ascii_case(&c.to_string())));
+    let accidental: Option<i32> = chars.map(|c| match c {
+        '#' | '♯' => Some(1),
+        'b' | '♭' => Some(-1),
+        _ => None,
+    }).sum();
+    match (letter, accidental) {
+        (Some(letter), Some(accidental)) => {
+            let key = (LETTER_TONES[letter] as i32 + accidental).rem_euclid(TOTAL_TONES as i32) as usize;
+            match keys(typ).contains(&(key, letter)) {
+                true => Ok((key, letter)),
+                false => Ok((key, key_letter(typ, key))),
+            }
+        },
+        _ => Err(format!("unknown key '{}'", name)),
+    }
+}
+fn scale(options: &Options) -> Result<Vec<u8>, String> {
+    let marks = match options.numbers {
+        true => NoteMarker::Numbers,
+        false => NoteMarker::Letters,
+    };
+    let drawing = || FretboardApp::headless(Scale { ..options.scale }, options.instrument_index, options.instrument.tune_index, options.frets, options.vertical, marks);
+    match options.format {
+        Format::Text => Ok(text_fretboard(options).into_bytes()),
+        Format::Notes => Ok(format!("{}\n", scale_notes(options).iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(" ")).into_bytes()),
+        Format::Json => Ok(json(options).into_bytes()),
+        Format::Svg => Ok(drawing().export_svg().into_bytes()),
+        Format::Png => drawing().export_png(),
+        Format::Pdf => Ok(drawing().export_worksheet(options.per_page)),
+    }
+}
+fn title(options: &Options) -> String {
+    format!("{} · {} · {}", full_name(&options.scale), options.instrument.name, options.instrument.tuning().name())
+}
+// a note by name, or by degree with --numbers
+fn mark(options: &Options, note: usize) -> String {
+    match options.numbers {
+        true => options.scale.get_note_number((note % TOTAL_TONES + TOTAL_TONES - options.scale.key) % TOTAL_TONES),
+        false => options.scale.get_note_letter(note),
+    }
+}
+// the scale's notes from the tonic up, as marked and as pitch classes
+fn scale_notes(options: &Options) -> Vec<(String, usize)> {
+    options.scale.notes().iter().map(|n| {
+        let note = (options.scale.key + n) % TOTAL_TONES;
+        (mark(options, note), note)
+    }).collect()
+}
+// the frets of a string that hold notes of the scale
+fn string_notes(options: &Options, string: usize) -> Vec<usize> {
+    (0..=options.frets).filter(|fret| options.scale.is_note_in_scale((options.instrument.strings()[string] + fret) as i16)).collect()
+}
+// strings across with the highest on top as in tab, open strings before the nut and fret numbers above
+fn text_fretboard(options: &Options) -> String {
+    let strings = options.instrument.strings();
+    let names = tab::string_names(strings);
+    let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0) + 1;
+    let mut order: Vec<usize> = (0..strings.len()).collect();
+    order.sort_by_key(|s| std::cmp::Reverse(strings[*s]));
+    let mut text = format!("{}\n\n{:width$}{:^3} ", title(options), "", 0);
+    for fret in 1..=options.frets {
+        let _ = write!(text, "{:^5} ", fret);
+    }
+    text.push('\n');
+    for string in order {
+        let frets = string_notes(options, string);
+        let at = |fret: usize| match frets.contains(&fret) {
+            true => mark(options, strings[string] + fret),
+            false => String::new(),
+        };
+        let _ = write!(text, "{:width$}{:^3}‖", names[string], at(0));
+        for fret in 1..=options.frets {
+            let _ = write!(text, "{:-^5}|", at(fret));
+        }
+        text.push('\n');
+    }
+    text
+}
+// the scale and where its notes are on each string, strings in the instrument's order
+fn json(options: &Options) -> String {
+    let strings = options.instrument.strings();
+    let names = tab::string_names(strings);
+    let mut json = String::from("{\n");
+    let _ = writeln!(json, "  \"name\": {},", json_string(&full_name(&options.scale)));
+    let _ = writeln!(json, "  \"key\": {},", json_string(&options.scale.key_name()));
+    let _ = writeln!(json, "  \"type\": {},", json_string(&format!("{:?}", options.scale.typ).to_lowercase()));
+    let _ = writeln!(json, "  \"size\": {},", json_string(&format!("{:?}", options.scale.siz).to_lowercase()));
+    let _ = writeln!(json, "  \"instrument\": {},", json_string(&options.instrument.name));
+    let _ = writeln!(json, "  \"tuning\": {},", json_string(options.instrument.tuning().name()));
+    let notes: Vec<String> = scale_notes(options).iter()
+        .map(|(name, pitch_class)| format!("{{\"name\": {}, \"pitch_class\": {}}}", json_string(name), pitch_class))
+        .collect();
+    let _ = writeln!(json, "  \"notes\": [{}],", notes.join(", "));
+    json.push_str("  \"strings\": [\n");
+    for (string, name) in names.iter().enumerate() {
+        let frets: Vec<String> = string_notes(options, string).iter().map(|fret| {
+            let note = strings[string] + fret;
+            format!("{{\"fret\": {}, \"name\": {}, \"midi\": {}}}", fret, json_string(&mark(options, note)), options.instrument.midi_note(note))
+        }).collect();
+        let comma = match string + 1 < strings.len() {
+            true => ",",
+            false => "",
+        };
+        let _ = writeln!(
+            json, "    {{\"name\": {}, \"midi\": {}, \"notes\": [{}]}}{}",
+            json_string(name), options.instrument.midi_note(strings[string]), frets.join(", "), comma,
+        );
+    }
+    json.push_str("  ]\n}\n");
+    json
+}
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            },
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+fn instruments() -> String {
+    Instrument::all().iter().map(|instrument| {
+        let tunings: Vec<&str> = instrument.tunings.iter().map(|t| t.name.as_str()).collect();
+        format!("{}: {}\n", instrument.name, tunings.join(", "))
+    }).collect()
+}
+fn write_output(path: Option<&str>, bytes: &[u8]) -> Result<(), String> {
+    match path {
+        Some(path) => std::fs::write(path, bytes).map_err(|e| trf("error.write", &[&path, &e])),
+        None => std::io::stdout().write_all(bytes).map_err(|e| e.to_string()),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Result<Options, String> {
+        parse_scale(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+    fn error(args: &[&str]) -> String {
+        options(args).err().unwrap_or_default()
+    }
+    fn notes(args: &[&str]) -> Vec<String> {
+        scale_notes(&options(args).unwrap()).into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn keys_keep_their_spelling() {
+        assert_eq!(notes(&["--key", "F#", "--type", "major", "--size", "diatonic"]), ["F♯", "G♯", "A♯", "B", "C♯", "D♯", "E♯"]);
+        assert_eq!(notes(&["--key", "Gb", "--type", "major", "--size", "diatonic"]), ["G♭", "A♭", "B♭", "C♭", "D♭", "E♭", "F"]);
+        assert_eq!(notes(&["--key=g♭", "--type=major", "--size=diatonic"]), notes(&["--key", "Gb", "--type", "major", "--size", "diatonic"]));
+        // A♯ major would need double sharps, so it is written B♭
+        assert_eq!(options(&["--key", "A#", "--type", "major"]).unwrap().scale.key_name(), "B♭");
+    }
+    #[test]
+    fn bad_keys_are_errors() {
+        assert_eq!(error(&["--key", "H"]), "unknown key 'H'");
+        assert_eq!(error(&["--key", "C+"]), "unknown key 'C+'");
+        assert_eq!(error(&["--key", ""]), "unknown key ''");
+        assert_eq!(error(&["--key"]), "--key needs a value");
+    }
+    #[test]
+    fn sizes_drop_their_only() {
+        assert_eq!(options(&["--size", "triads"]).unwrap().scale.siz, ScaleSize::TriadsOnly);
+        assert_eq!(options(&["--size", "ROOT"]).unwrap().scale.siz, ScaleSize::RootOnly);
+        assert_eq!(notes(&["--key", "A", "--size", "triads"]), ["A", "C", "E"]);
+        assert_eq!(notes(&["--key", "A", "--size", "root"]), ["A"]);
+        assert_eq!(error(&["--size", "huge"]), "unknown scale size 'huge'");
+    }
+    #[test]
+    fn tunings_by_name() {
+        let drop_d = options(&["--tuning", "DROP D"]).unwrap();
+        assert_eq!(drop_d.instrument.tuning().name, "DROP D");
+        assert_eq!(tab::string_names(drop_d.instrument.strings())[0], "D");
+        assert_eq!(options(&["--tuning=drop d"]).unwrap().instrument.tune_index, drop_d.instrument.tune_index);
+        assert_eq!(error(&["--tuning", "Nashville"]), "Guitar has no tuning 'Nashville'");
+        assert_eq!(error(&["--instrument", "lute"]), "unknown instrument 'lute'");
+    }
+    #[test]
+    fn text_fretboard_snapshot() {
+        let expected = [
+            "G Minor Pentatonic scale · Ukulele · Standard",
+            "",
+            "   0    1     2     3     4     5   ",
+            "A    ‖-B♭--|-----|--C--|-----|--D--|",
+            "E    ‖--F--|-----|--G--|-----|-----|",
+            "C  C ‖-----|--D--|-----|-----|--F--|",
+            "G  G ‖-----|-----|-B♭--|-----|--C--|",
+            "",
+        ];
+        assert_eq!(text_fretboard(&options(&["--instrument", "ukulele", "--frets", "5"]).unwrap()), expected.join("\n"));
+    }
+    #[test]
+    fn json_parses() {
+        let options = options(&["--key", "Eb", "--type", "major", "--tuning", "DROP D", "--frets", "5"]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json(&options)).unwrap();
+        assert_eq!(json["name"], full_name(&options.scale));
+        assert_eq!(json["key"], "E♭");
+        assert_eq!(json["tuning"], "DROP D");
+        assert_eq!(json["notes"][0], serde_json::json!({"name": "E♭", "pitch_class": 3}));
+        let low = &json["strings"][0];
+        assert_eq!(low["midi"], 38);
+        // E♭, F and G up to the fifth fret; the open D is not in the key
+        let frets: Vec<u64> = low["notes"].as_array().unwrap().iter().map(|n| n["fret"].as_u64().unwrap()).collect();
+        assert_eq!(frets, [1, 3, 5]);
+        assert_eq!(json["strings"].as_array().unwrap().len(), 6);
+    }
+}
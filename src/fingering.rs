@@ -0,0 +1,201 @@
+use crate::instruments::FretPos;
+use crate::positions::strings_by_pitch;
+
+// cost of moving the hand one fret, of reaching outside the hand's span, and of
+// opening or closing a cello hand's extension without moving it
+const SHIFT_COST: usize = 2;
+const STRETCH_COST: usize = 4;
+const EXTENSION_COST: usize = 1;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FingeringStyle {
+    // one finger per fret, index finger on the hand position
+    Fretted,
+    // first position spans a fourth: low/high 1, 2 and 3, then 4
+    Violin,
+    // neck positions: 1 to 4 on semitones spanning a minor third, opened to a major third by
+    // extending forward (a tone between 1 and 2) or back (1 reaching down a semitone)
+    Cello,
+}
+// how the hand is opened out from its closed position
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Extension {
+    Closed,
+    Forward,
+    Backward,
+}
+// where the hand sits: the fret under the first finger, and how it's opened
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Hand {
+    fret: usize,
+    extension: Extension,
+}
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Finger {
+    pub number: usize,
+    pub stretch: bool,
+}
+impl Finger {
+    pub fn open() -> Finger {
+        Finger { number: 0, stretch: false }
+    }
+    pub fn label(&self) -> String {
+        match (self.number, self.stretch) {
+            (0, _) => "0".to_string(),
+            (n, false) => n.to_string(),
+            (1, true) => "1-".to_string(),
+            (n, true) => format!("{}+", n),
+        }
+    }
+}
+impl FingeringStyle {
+    // the finger used for a fret with the hand at `hand`, if it can reach at all
+    fn finger(&self, hand: Hand, fret: usize) -> Option<Finger> {
+        if fret == 0 {
+            return Some(Finger::open());
+        }
+        let offset = fret as i32 - hand.fret as i32;
+        let (number, stretch) = match self {
+            FingeringStyle::Fretted => match offset {
+                -1 => (1, true),
+                0..=3 => (offset as usize + 1, false),
+                4 => (4, true),
+                _ => return None,
+            },
+            FingeringStyle::Cello => match (hand.extension, offset) {
+                (Extension::Closed, 0..=3) => (offset as usize + 1, false),
+                (Extension::Forward, 0) => (1, false),
+                (Extension::Forward, 2) => (2, true),
+                (Extension::Forward, 3 | 4) => (offset as usize, false),
+                (Extension::Backward, -1) => (1, true),
+                (Extension::Backward, 1..=3) => (offset as usize + 1, false),
+                _ => return None,
+            },
+            FingeringStyle::Violin => match offset {
+                1 | 2 => (1, false),
+                3 | 4 => (2, false),
+                5 | 6 => (3, false),
+                7 => (4, false),
+                8 => (4, true),
+                _ => return None,
+            },
+        };
+        Some(Finger { number, stretch })
+    }
+    // every hand that could play some fret from lo to hi
+    fn hands(&self, lo: usize, hi: usize) -> Vec<Hand> {
+        let (frets, extensions) = match self {
+            FingeringStyle::Fretted => (lo.saturating_sub(4).max(1)..=hi + 1, vec![Extension::Closed]),
+            FingeringStyle::Cello => (lo.saturating_sub(4).max(1)..=hi + 1, vec![Extension::Closed, Extension::Forward, Extension::Backward]),
+            FingeringStyle::Violin => (lo.saturating_sub(8)..=hi.saturating_sub(1), vec![Extension::Closed]),
+        };
+        frets.flat_map(|fret| extensions.iter().map(move |extension| Hand { fret, extension: *extension })).collect()
+    }
+}
+// assign a finger to every note, played low to high, choosing hand positions that
+// minimise shifts and stretches (a small dynamic program over hand positions)
+pub fn assign(notes: &[FretPos], strings: &[usize], style: FingeringStyle) -> Vec<(FretPos, Finger)> {
+    let order = strings_by_pitch(strings);
+    let mut sorted: Vec<FretPos> = notes.to_vec();
+    sorted.sort_by_key(|at| (order.iter().position(|s| *s == at.string), at.fret));
+    let fretted: Vec<usize> = sorted.iter().filter(|at| at.fret > 0).map(|at| at.fret).collect();
+    let (Some(lo), Some(hi)) = (fretted.iter().min(), fretted.iter().max()) else {
+        return sorted.into_iter().map(|at| (at, Finger::open())).collect();
+    };
+    let hands = style.hands(*lo, *hi);
+    // extensions are part of cello technique, where on a fretted neck a stretch is a strain
+    let cost_of = |hand: Hand, at: &FretPos| {
+        style.finger(hand, at.fret).map(|f| match (f.stretch, style) {
+            (true, FingeringStyle::Fretted | FingeringStyle::Violin) => STRETCH_COST,
+            _ => 0,
+        })
+    };
+    let extending = |prev: &Hand, hand: &Hand| match prev.extension == hand.extension {
+        true => 0,
+        false => EXTENSION_COST,
+    };
+    // a finger moving straight on to another fret costs as much as the hand moving there
+    let sliding = |prev: &Hand, hand: &Hand, i: usize| {
+        let (before, now) = (style.finger(*prev, sorted[i - 1].fret), style.finger(*hand, sorted[i].fret));
+        match before.zip(now) {
+            Some((a, b)) if a.number > 0 && a.number == b.number && sorted[i - 1].fret != sorted[i].fret => SHIFT_COST,
+            _ => 0,
+        }
+    };
+    // best[i][h]: cheapest cost of playing notes 0..=i ending with the hand at hands[h]
+    let mut best: Vec<Vec<Option<usize>>> = vec![vec![None; hands.len()]; sorted.len()];
+    let mut from: Vec<Vec<usize>> = vec![vec![0; hands.len()]; sorted.len()];
+    for (i, at) in sorted.iter().enumerate() {
+        for (h, hand) in hands.iter().enumerate() {
+            let Some(cost) = cost_of(*hand, at) else {
+                continue;
+            };
+            if i == 0 {
+                best[i][h] = Some(cost);
+                continue;
+            }
+            let previous = hands
+                .iter()
+                .enumerate()
+                .filter_map(|(p, prev)| best[i - 1][p].map(|c| (c + prev.fret.abs_diff(hand.fret) * SHIFT_COST + extending(prev, hand) + sliding(prev, hand, i), p)))
+                .min();
+            if let Some((c, p)) = previous {
+                best[i][h] = Some(c + cost);
+                from[i][h] = p;
+            }
+        }
+    }
+    let last = sorted.len() - 1;
+    let Some((_, mut h)) = best[last].iter().enumerate().filter_map(|(h, c)| c.map(|c| (c, h))).min() else {
+        return vec![];
+    };
+    let mut fingers = vec![Finger::open(); sorted.len()];
+    for i in (0..sorted.len()).rev() {
+        fingers[i] = style.finger(hands[h], sorted[i].fret).unwrap_or(Finger::open());
+        h = from[i][h];
+    }
+    sorted.into_iter().zip(fingers).collect()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUITAR: [usize; 6] = [4, 9, 14, 19, 23, 28];
+    const VIOLIN: [usize; 4] = [7, 14, 21, 28];
+    const CELLO: [usize; 4] = [0, 7, 14, 21];
+
+    fn labels(notes: &[(usize, usize)], strings: &[usize], style: FingeringStyle) -> Vec<String> {
+        let notes: Vec<FretPos> = notes.iter().map(|(string, fret)| FretPos { string: *string, fret: *fret }).collect();
+        assert_eq!(assign(&notes, strings, style).iter().map(|(at, _)| *at).collect::<Vec<_>>(), notes);
+        assign(&notes, strings, style).iter().map(|(_, f)| f.label()).collect()
+    }
+
+    #[test]
+    fn fretted_hands_take_one_finger_per_fret() {
+        assert_eq!(labels(&[(0, 5), (0, 6), (0, 7), (0, 8), (1, 5), (1, 7)], &GUITAR, FingeringStyle::Fretted), ["1", "2", "3", "4", "1", "3"]);
+        assert_eq!(labels(&[(0, 0), (0, 3), (1, 0)], &GUITAR, FingeringStyle::Fretted), ["0", "3", "0"]);
+    }
+    #[test]
+    fn a_shift_beats_a_stretch() {
+        // the hand moves up a fret rather than the little finger reaching a fifth fret
+        assert_eq!(labels(&[(0, 5), (0, 9)], &GUITAR, FingeringStyle::Fretted), ["1", "4"]);
+        // but stays put for a shape it would have to keep moving back and forth for
+        assert_eq!(labels(&[(0, 5), (0, 9), (1, 5), (1, 9), (2, 5), (2, 9)], &GUITAR, FingeringStyle::Fretted), ["1", "4+", "1", "4+", "1", "4"]);
+    }
+    #[test]
+    fn violin_first_position() {
+        // G major up the G and D strings: low 1 a tone above the open string, 3 a semitone above 2
+        let scale = [(0, 0), (0, 2), (0, 4), (0, 5), (1, 0), (1, 2), (1, 4), (1, 5)];
+        assert_eq!(labels(&scale, &VIOLIN, FingeringStyle::Violin), ["0", "1", "2", "3", "0", "1", "2", "3"]);
+        assert_eq!(labels(&[(2, 2), (2, 4), (2, 5), (2, 7)], &VIOLIN, FingeringStyle::Violin), ["1", "2", "3", "4"]);
+    }
+    #[test]
+    fn cello_hands_span_a_minor_third_and_extend() {
+        assert_eq!(labels(&[(0, 1), (0, 2), (0, 3), (0, 4)], &CELLO, FingeringStyle::Cello), ["1", "2", "3", "4"]);
+        // a major third: a forward extension between 1 and 2, where a guitarist shifts
+        assert_eq!(labels(&[(0, 1), (0, 3), (0, 5)], &CELLO, FingeringStyle::Cello), ["1", "2+", "4"]);
+        assert_eq!(labels(&[(0, 1), (0, 3), (0, 5)], &CELLO, FingeringStyle::Fretted), ["1", "3", "4"]);
+        // and 1 reaching back from a closed position
+        assert_eq!(labels(&[(0, 2), (0, 3), (0, 4), (0, 5), (1, 1)], &CELLO, FingeringStyle::Cello), ["1", "2", "3", "4", "1-"]);
+    }
+}
@@ -4,8 +4,26 @@ use epaint::text::{FontInsert, InsertFontFamily};
 use strum_macros::EnumIter;
 use strum::IntoEnumIterator;
 use std::ops::Div;
-use crate::scales::{note_letter, prefers_flats, scale_name, NoteType, Scale, ScaleSize, ScaleType};
-use crate::instruments::Instrument;
+use crate::scales::{best_fit, chromatic_spelling, compound_interval_name, interval_name, interval_type, key_letter, keys, scale_name, spell, Bubble, Naming, NoteType, Numbering, Scale, ScaleSize, ScaleType, TOTAL_TONES};
+use crate::instruments::{FretPos, Instrument};
+use crate::quiz::{ChordAsk, Difficulty, Question, Quiz, QuizKind};
+use crate::fingering;
+use crate::keyboard;
+use crate::staff::{self, Clef};
+use crate::tab::{self, TabNote};
+use crate::musicxml::{Pitch, Score};
+use crate::song::{Song, SongImport};
+use crate::canvas::{Canvas, Svg};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::raster::Raster;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::pdf::{Pdf, PAGE_HEIGHT, PAGE_WIDTH};
+use crate::i18n::{self, language, set_language, tr, trf, Language};
+use crate::arpeggios::{chord_degree, chord_note_type, diatonic_chord, ArpNote, Arpeggio, ChordQuality, Picking};
+use crate::positions::{shape, Position, Shape, BOXES, PATTERNS_3NPS};
+use crate::trainer::{string_number, Drill, Prompt, Trainer, STORAGE_KEY};
+use crate::pitch::{midi_to_frequency, Listener, Recording};
+use crate::tuner::{nearest_string, target_midi, TunerReading, IN_TUNE_CENTS};
 
 #[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
 pub enum FretMarker {
@@ -18,6 +36,7 @@ pub enum NoteMarker {
     AllNotes,
     Letters,
     Numbers,
+    Fingering,
     Debug,
 }
 #[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
@@ -36,11 +55,47 @@ enum Panel {
     None,
     Instrument,
     ViewSettings,
+    Listen,
+    Tuner,
+    Quiz,
+    Trainer,
+    Arpeggio,
+    Circle,
+    Compare,
+    Views,
+    Staff,
+    Tab,
+    Song,
+    Export,
 }
+// which notes go into the exported tab
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+enum TabSource {
+    Scale,
+    Arpeggio,
+    Chords,
+    Selected,
+}
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+enum ViewLayout {
+    Stacked,
+    Tiled,
+}
+// which neighbour on the circle of fifths to preview
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+enum Modulation {
+    Off,
+    Dominant,
+    Subdominant,
+}
+// ring colours for notes found in only one of two compared scales
+const ONLY_IN_A: Color32 = Color32::BLUE;
+const ONLY_IN_B: Color32 = Color32::ORANGE;
 struct DrawSettings {
     dark_mode: bool,
     vertical: bool,
     show_legend: bool,
+    show_keyboard: bool,
     frets: usize,
     fret_marks: FretMarker,
     note_marks: NoteMarker,
@@ -49,6 +104,31 @@ struct DrawSettings {
     space_string: f32,
     space_fret: f32,
     dot_size:f32,
+    position: Position,
+    layout: ViewLayout,
+}
+// an extra fretboard drawn next to the main one
+struct View {
+    instrument: usize,
+    tune_index: usize,
+    position: Position,
+    // None shares the main scale
+    scale: Option<Scale>,
+}
+// everything one fretboard is drawn from
+struct Board<'a> {
+    // with the tuning this board is drawn in
+    instrument: Instrument,
+    scale: &'a Scale,
+    position: Position,
+    // only the main fretboard takes clicks and shows the panels' overlays
+    main: bool,
+}
+// where a board drew a note, for finding the one under the pointer
+struct Dot {
+    at: FretPos,
+    pos: Pos2,
+    midi: usize,
 }
 pub struct FretboardApp {
     toasts: Toasts,
@@ -58,6 +138,28 @@ pub struct FretboardApp {
     open_panel: Panel,
     settings: DrawSettings,
     scale: Scale,
+    listener: Listener,
+    quiz: Quiz,
+    trainer: Trainer,
+    arpeggio: Arpeggio,
+    anchor: Option<FretPos>,
+    modulation: Modulation,
+    compare: Scale,
+    views: Vec<View>,
+    // the MIDI note under the pointer, on a fretboard or the keyboard
+    hovered: Option<usize>,
+    // notes picked on the fretboard for the staff
+    selected: Vec<FretPos>,
+    tab_source: TabSource,
+    // a pasted tab and the notes read from it onto the current instrument
+    tab_text: String,
+    imported: Vec<TabNote>,
+    musicxml_path: String,
+    song: SongImport,
+    export_path: String,
+    // pixels to a point in PNG files, and diagrams to a page of the worksheet
+    png_scale: f32,
+    sheet_per_page: usize,
 }
 impl Default for FretboardApp {
     fn default() -> Self {
@@ -67,17 +169,12 @@ impl Default for FretboardApp {
             open_panel:Panel::None,
             current_instrument: 0,
             empty_instrument: Instrument::none(),
-            instruments: vec![
-                Instrument::guitar(),
-                Instrument::violin(),
-                Instrument::cello(),
-                Instrument::ukulele(),
-                Instrument::banjo(),
-            ],
+            instruments: Instrument::all(),
             settings: DrawSettings {
                 dark_mode: false,
                 vertical: true,
                 show_legend: true,
+                show_keyboard: false,
                 frets: 12,
                 fret_marks: FretMarker::Dots,
                 note_marks: NoteMarker::Letters,
@@ -86,12 +183,45 @@ impl Default for FretboardApp {
                 space_string: 50.0,
                 space_fret: 50.0,
                 dot_size:16f32,
+                position: Position::All,
+                layout: ViewLayout::Stacked,
             },
             scale: Scale {
                 siz: ScaleSize::Pentatonic,
                 typ: ScaleType::Minor,
                 key: 7,
-            }
+                key_letter: 4,
+                numbering: Numbering::Simple,
+                naming: Naming::English,
+            },
+            listener: Listener::default(),
+            quiz: Quiz::default(),
+            trainer: Trainer::default(),
+            arpeggio: Arpeggio {
+                root: 7,
+                ..Default::default()
+            },
+            anchor: None,
+            modulation: Modulation::Off,
+            compare: Scale {
+                siz: ScaleSize::Pentatonic,
+                typ: ScaleType::Major,
+                key: 7,
+                key_letter: 4,
+                numbering: Numbering::Simple,
+                naming: Naming::English,
+            },
+            views: vec![],
+            hovered: None,
+            selected: vec![],
+            tab_source: TabSource::Scale,
+            tab_text: String::new(),
+            imported: vec![],
+            musicxml_path: String::new(),
+            song: SongImport::default(),
+            export_path: String::new(),
+            png_scale: 2f32,
+            sheet_per_page: 4,
         }
     }
 }
@@ -99,19 +229,56 @@ impl eframe::App for FretboardApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.settings.dark_mode = ctx.style().visuals.dark_mode;
         self.draw_top_bar(ctx);
+        self.receive_dropped_files(ctx);
+        if !ctx.wants_keyboard_input() {
+            if ctx.input(|i| i.key_pressed(Key::ArrowRight)) {
+                self.settings.position = self.settings.position.step(true, self.settings.frets);
+            }
+            if ctx.input(|i| i.key_pressed(Key::ArrowLeft)) {
+                self.settings.position = self.settings.position.step(false, self.settings.frets);
+            }
+            if ctx.input(|i| i.key_pressed(Key::Escape)) {
+                self.anchor = None;
+            }
+        }
+        if self.listener.is_active() {
+            self.listener.update(ctx.input(|i| i.stable_dt));
+            ctx.request_repaint();
+        }
         match self.open_panel {
             Panel::ViewSettings => self.draw_panel_settings(ctx),
+            Panel::Listen => self.draw_panel_listen(ctx),
+            Panel::Tuner => self.draw_panel_tuner(ctx),
+            Panel::Quiz => self.draw_panel_quiz(ctx),
+            Panel::Trainer => self.draw_panel_trainer(ctx),
+            Panel::Arpeggio => self.draw_panel_arpeggio(ctx),
+            Panel::Circle => self.draw_panel_circle(ctx),
+            Panel::Compare => self.draw_panel_compare(ctx),
+            Panel::Views => self.draw_panel_views(ctx),
+            Panel::Staff => self.draw_panel_staff(ctx),
+            Panel::Tab => self.draw_panel_tab(ctx),
+            Panel::Song => self.draw_panel_song(ctx),
+            Panel::Export => self.draw_panel_export(ctx),
             _ => {},
         }
-        self.draw_panel_fretboard(ctx);
+        let keyboard_hovered = match self.settings.show_keyboard {
+            true => self.draw_panel_keyboard(ctx),
+            false => None,
+        };
+        let fretboard_hovered = self.draw_panel_fretboard(ctx);
+        self.hovered = keyboard_hovered.or(fretboard_hovered);
         self.toasts.show(ctx);
     }
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, &self.trainer.history);
+        eframe::set_value(storage, i18n::STORAGE_KEY, &language());
+    }
 }
 
 fn setup_custom_fonts(ctx: &egui::Context) {
     let font = FontInsert::new(
         "Lucida",
-        egui::FontData::from_static(include_bytes!("../Lucida.ttf")),
+        egui::FontData::from_static(LUCIDA),
         vec![
         InsertFontFamily {
             family: egui::FontFamily::Proportional,
@@ -123,6 +290,119 @@ fn setup_custom_fonts(ctx: &egui::Context) {
         }
     ]);
     ctx.add_font(font);
+    // Lucida has no kana or kanji; borrow a system CJK font for Japanese if one is installed
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(bytes) = CJK_FONTS.iter().find_map(|path| std::fs::read(path).ok()) {
+        ctx.add_font(FontInsert::new(
+            "CJK",
+            egui::FontData::from_owned(bytes),
+            vec![
+            InsertFontFamily {
+                family: egui::FontFamily::Proportional,
+                priority: egui::epaint::text::FontPriority::Lowest,
+            },
+            InsertFontFamily {
+                family: egui::FontFamily::Monospace,
+                priority: egui::epaint::text::FontPriority::Lowest,
+            }
+        ]));
+    }
+}
+const LUCIDA: &[u8] = include_bytes!("../Lucida.ttf");
+#[cfg(not(target_arch = "wasm32"))]
+const CJK_FONTS: [&str; 7] = [
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/System/Library/Fonts/ヒラギノ角ゴシック W3.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "C:\\Windows\\Fonts\\msgothic.ttc",
+];
+// the same fonts for pictures drawn without egui
+#[cfg(not(target_arch = "wasm32"))]
+fn raster_fonts() -> Vec<ab_glyph::FontArc> {
+    let cjk = CJK_FONTS.iter().find_map(|path| std::fs::read(path).ok()).and_then(|bytes| ab_glyph::FontArc::try_from_vec(bytes).ok());
+    ab_glyph::FontArc::try_from_slice(LUCIDA).into_iter().chain(cjk).collect()
+}
+// red for weak positions through gold to green for mastered ones
+fn heat_color(mastery:f32) -> Color32 {
+    let m = mastery.clamp(0.0, 1.0);
+    match m < 0.5 {
+        true => Color32::RED.lerp_to_gamma(Color32::GOLD, m * 2.0),
+        false => Color32::GOLD.lerp_to_gamma(Color32::GREEN, (m - 0.5) * 2.0),
+    }
+}
+// a top bar button that opens its panel, or closes it if it's already open
+fn panel_toggle(ui: &mut Ui, open_panel: &mut Panel, panel: Panel, key: &str) {
+    let mut open = *open_panel == panel;
+    if ui.toggle_value(&mut open, tr(key)).clicked() {
+        *open_panel = match open {
+            true => panel,
+            false => Panel::None,
+        };
+    }
+}
+fn position_picker(ui: &mut Ui, id: &str, position: &mut Position, scale: &Scale, frets: usize) {
+    ComboBox::from_id_salt(id)
+        .selected_text(position.name())
+        .show_ui(ui, |inner_ui|{
+            inner_ui.selectable_value(position, Position::All, Position::All.name());
+            for i in 0..BOXES {
+                inner_ui.selectable_value(position, Position::Box(i), Position::Box(i).name());
+            }
+            let seven_notes = scale.notes().len() == PATTERNS_3NPS;
+            for i in 0..PATTERNS_3NPS {
+                inner_ui.add_enabled_ui(seven_notes, |inner_ui|{
+                    inner_ui.selectable_value(position, Position::ThreeNps(i), Position::ThreeNps(i).name());
+                }).response.on_disabled_hover_text(tr("position.needs_seven"));
+            }
+            let window = match *position {
+                Position::Window { .. } => *position,
+                _ => Position::Window { from: 0, to: 4 },
+            };
+            inner_ui.selectable_value(position, window, tr("position.window"));
+        });
+    if let Position::Window { from, to } = position {
+        ui.add(egui::DragValue::new(from).range(0..=frets));
+        ui.add(egui::DragValue::new(to).range(*from..=frets));
+    }
+}
+// key, mode and size combos for a scale other than the main one
+fn scale_picker(ui: &mut Ui, id: &str, scale: &mut Scale) {
+    let mut key = (scale.key, scale.key_letter);
+    ComboBox::from_id_salt((id, "key"))
+        .selected_text(scale.key_name())
+        .width(100.0)
+        .show_ui(ui, |inner_ui|{
+            for k in keys(scale.typ) {
+                let name = spell(k.1, k.0, scale.naming).unwrap_or_default();
+                inner_ui.selectable_value(&mut key, k, name);
+            }
+        });
+    (scale.key, scale.key_letter) = key;
+    ComboBox::from_id_salt((id, "type"))
+        .selected_text(i18n::label(&scale.typ))
+        .width(100.0)
+        .show_ui(ui, |inner_ui|{
+            for s in ScaleType::iter() {
+                inner_ui.selectable_value(&mut scale.typ, s, i18n::label(&s));
+            }
+        });
+    if !keys(scale.typ).contains(&(scale.key, scale.key_letter)) {
+        scale.key_letter = key_letter(scale.typ, scale.key);
+    }
+    ComboBox::from_id_salt((id, "size"))
+        .selected_text(scale.scale_name())
+        .width(100.0)
+        .show_ui(ui, |inner_ui|{
+            for s in ScaleSize::iter() {
+                inner_ui.selectable_value(&mut scale.siz, s, scale_name(s));
+            }
+        });
+}
+pub fn full_name(scale:&Scale) -> String {
+    trf("compare.scale", &[&scale.key_name(), &i18n::label(&scale.typ), &scale.scale_name()])
 }
 fn font_glyph() -> FontId {
     font(18f32, FontFamily::Proportional)
@@ -133,7 +413,28 @@ fn font(size:f32, family:FontFamily) -> FontId {
 impl FretboardApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         setup_custom_fonts(&cc.egui_ctx);
-        Self::default()
+        let mut app = Self::default();
+        if let Some(storage) = cc.storage {
+            app.trainer.history = eframe::get_value(storage, STORAGE_KEY).unwrap_or_default();
+            if let Some(lang) = eframe::get_value(storage, i18n::STORAGE_KEY) {
+                set_language(lang);
+            }
+        }
+        app
+    }
+    // the app as the command line asks for it, to draw without a window
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn headless(scale:Scale, instrument:usize, tune_index:usize, frets:usize, vertical:bool, note_marks:NoteMarker) -> Self {
+        let mut app = Self {
+            scale,
+            current_instrument: instrument,
+            ..Self::default()
+        };
+        app.instruments[instrument].tune_index = tune_index;
+        app.settings.frets = frets;
+        app.settings.vertical = vertical;
+        app.settings.note_marks = note_marks;
+        app
     }
     pub fn instrument(&self) -> &Instrument {
         if self.instruments.len() <= 0 {
@@ -149,21 +450,27 @@ impl FretboardApp {
             // render toolbar:
             ui.horizontal(|ui|{
                 ui.set_height(30f32);
+                let mut key = (self.scale.key, self.scale.key_letter);
                 ComboBox::from_id_salt("scale_key")
-                    .selected_text(format!("key of {}", self.scale.get_note_letter(self.scale.key)))
+                    .selected_text(trf("top.key", &[&self.scale.key_name()]))
                     .show_ui(ui, |inner_ui|{
-                        for i in 0..12 {
-                            let letter = note_letter(i, prefers_flats(self.scale.typ, i));
-                            inner_ui.selectable_value(&mut self.scale.key, i, letter);
+                        for k in keys(self.scale.typ) {
+                            let name = spell(k.1, k.0, self.scale.naming).unwrap_or_default();
+                            inner_ui.selectable_value(&mut key, k, name);
                         }
                     });
+                (self.scale.key, self.scale.key_letter) = key;
                 ComboBox::from_id_salt("scale_type")
-                    .selected_text(format!("{:?}", self.scale.typ))
+                    .selected_text(i18n::label(&self.scale.typ))
                     .show_ui(ui, |inner_ui|{
                         for s in ScaleType::iter() {
-                            inner_ui.selectable_value(&mut self.scale.typ, s, format!("{:?}", s));
+                            inner_ui.selectable_value(&mut self.scale.typ, s, i18n::label(&s));
                         }
                     });
+                // e.g. G♭ major becomes F♯ minor rather than G♭ minor with its B♭♭
+                if !keys(self.scale.typ).contains(&(self.scale.key, self.scale.key_letter)) {
+                    self.scale.key_letter = key_letter(self.scale.typ, self.scale.key);
+                }
                 ComboBox::from_id_salt("scale_size")
                     .selected_text(self.scale.scale_name())
                     .show_ui(ui, |inner_ui|{
@@ -171,6 +478,7 @@ impl FretboardApp {
                             inner_ui.selectable_value(&mut self.scale.siz, s, scale_name(s));
                         }
                     });
+                self.draw_position_picker(ui);
             });
             // render toolbar:
             ui.horizontal(|ui|{
@@ -195,309 +503,1962 @@ impl FretboardApp {
                         }
                     });
                 self.instruments[self.current_instrument].tune_index = tune_index;
-                let mut show_settings = self.open_panel == Panel::ViewSettings;
-                if ui.toggle_value(&mut show_settings, "👁 Settings").clicked(){
-                    self.open_panel = match self.open_panel {
-                        Panel::ViewSettings => Panel::None,
-                        _ => Panel::ViewSettings,
-                    };
-                }
-                let mut show_legend = self.settings.show_legend;
-                if ui.toggle_value(&mut show_legend, "🎵 Legend").clicked() {
-                    self.settings.show_legend = !self.settings.show_legend;
+                panel_toggle(ui, &mut self.open_panel, Panel::ViewSettings, "top.settings");
+                ui.toggle_value(&mut self.settings.show_legend, tr("top.legend"));
+                ui.toggle_value(&mut self.settings.show_keyboard, tr("top.keyboard"));
+                for (panel, key) in [
+                    (Panel::Listen, "top.listen"),
+                    (Panel::Tuner, "top.tuner"),
+                    (Panel::Quiz, "top.quiz"),
+                    (Panel::Trainer, "top.trainer"),
+                    (Panel::Arpeggio, "top.arpeggio"),
+                    (Panel::Circle, "top.circle"),
+                    (Panel::Compare, "top.compare"),
+                    (Panel::Views, "top.views"),
+                    (Panel::Staff, "top.staff"),
+                    (Panel::Tab, "top.tab"),
+                    (Panel::Song, "top.song"),
+                    (Panel::Export, "top.export"),
+                ] {
+                    panel_toggle(ui, &mut self.open_panel, panel, key);
                 }
             });
             ui.add_space(3.0);
         });
     }
+    fn draw_position_picker(&mut self, ui: &mut Ui) {
+        if ui.small_button("◀").on_hover_text(tr("position.previous")).clicked() {
+            self.settings.position = self.settings.position.step(false, self.settings.frets);
+        }
+        position_picker(ui, "position", &mut self.settings.position, &self.scale, self.settings.frets);
+        if ui.small_button("▶").on_hover_text(tr("position.next")).clicked() {
+            self.settings.position = self.settings.position.step(true, self.settings.frets);
+        }
+    }
     fn draw_panel_settings(&mut self, ctx: &egui::Context) {
         egui::SidePanel::left("View Options")
         .resizable(false)
         .default_width(240.0)
         .show(ctx, |ui|{
             ui.add_space(5.0);
-            ui.heading("View Options");
+            ui.heading(tr("settings.title"));
             ui.add_space(14f32);
             // render toolbar:
             egui::Grid::new("settings")
             .show(ui, |ui|{
-                ui.label("theme");
+                ui.label(tr("settings.theme"));
                 egui::widgets::global_theme_preference_switch(ui);
                 ui.end_row();
-                ui.label("vertical fretboard");
+                ui.label(tr("settings.language"));
+                let mut lang = language();
+                ComboBox::from_id_salt("language")
+                    .selected_text(lang.native_name())
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for l in Language::iter() {
+                            inner_ui.selectable_value(&mut lang, l, l.native_name());
+                        }
+                    });
+                set_language(lang);
+                ui.end_row();
+                ui.label(tr("settings.vertical"));
                 let label = match self.settings.vertical {
-                    true => "On",
-                    false => "Off",
+                    true => tr("common.on"),
+                    false => tr("common.off"),
                 };
                 ui.toggle_value(&mut self.settings.vertical, label);
                 ui.end_row();
-                ui.label("show legend");
+                ui.label(tr("settings.show_legend"));
                 let label = match self.settings.show_legend {
-                    true => "On",
-                    false => "Off",
+                    true => tr("common.on"),
+                    false => tr("common.off"),
                 };
                 ui.toggle_value(&mut self.settings.show_legend, label);
                 ui.end_row();
-                ui.label(format!("{} frets", self.settings.frets));
+                ui.label(trf("settings.frets", &[&self.settings.frets]));
                 ui.add(egui::Slider::new(&mut self.settings.frets, 4..= 25).show_value(false));
                 ui.end_row();
-                ui.label("fret marks");
+                ui.label(tr("settings.fret_marks"));
                 ComboBox::from_id_salt("fret_marks")
-                    .selected_text(format!("{:?}", self.settings.fret_marks))
+                    .selected_text(i18n::label(&self.settings.fret_marks))
                     .width(100.0)
                     .show_ui(ui, |inner_ui|{
                         for fm in FretMarker::iter() {
-                            inner_ui.selectable_value(&mut self.settings.fret_marks, fm, format!("{:?}", fm));
+                            inner_ui.selectable_value(&mut self.settings.fret_marks, fm, i18n::label(&fm));
                         }
                     });
                 ui.end_row();
-                ui.label("note marks");
+                ui.label(tr("settings.note_marks"));
                 ComboBox::from_id_salt("note_marks")
-                    .selected_text(format!("{:?}", self.settings.note_marks))
+                    .selected_text(i18n::label(&self.settings.note_marks))
                     .width(100.0)
                     .show_ui(ui, |inner_ui|{
                         for nm in NoteMarker::iter() {
-                            inner_ui.selectable_value(&mut self.settings.note_marks, nm, format!("{:?}", nm));
+                            inner_ui.selectable_value(&mut self.settings.note_marks, nm, i18n::label(&nm));
+                        }
+                    });
+                ui.end_row();
+                ui.label(tr("settings.note_names"));
+                ComboBox::from_id_salt("naming")
+                    .selected_text(i18n::label(&self.scale.naming))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for n in Naming::iter() {
+                            inner_ui.selectable_value(&mut self.scale.naming, n, i18n::label(&n));
+                        }
+                    });
+                ui.end_row();
+                ui.label(tr("settings.degree_numbers"));
+                ComboBox::from_id_salt("numbering")
+                    .selected_text(i18n::label(&self.scale.numbering))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for n in Numbering::iter() {
+                            inner_ui.selectable_value(&mut self.scale.numbering, n, i18n::label(&n));
                         }
                     });
                 ui.end_row();
-                ui.label("note colors");
+                ui.label(tr("settings.note_colors"));
                 ComboBox::from_id_salt("note_colors")
-                    .selected_text(format!("{:?}", self.settings.note_colors))
+                    .selected_text(i18n::label(&self.settings.note_colors))
                     .width(100.0)
                     .show_ui(ui, |inner_ui|{
                         for nc in NoteColors::iter() {
-                            inner_ui.selectable_value(&mut self.settings.note_colors, nc, format!("{:?}", nc));
+                            inner_ui.selectable_value(&mut self.settings.note_colors, nc, i18n::label(&nc));
                         }
                     });
                 ui.end_row();
-                ui.label("string style");
+                ui.label(tr("settings.string_style"));
                 ComboBox::from_id_salt("string_style")
-                    .selected_text(format!("{:?}", self.settings.string_style))
+                    .selected_text(i18n::label(&self.settings.string_style))
                     .width(100.0)
                     .show_ui(ui, |inner_ui|{
                         for s in StringStyle::iter() {
-                            inner_ui.selectable_value(&mut self.settings.string_style, s, format!("{:?}", s));
+                            inner_ui.selectable_value(&mut self.settings.string_style, s, i18n::label(&s));
                         }
                     });
                 ui.end_row();
-                ui.label("string spacing");
+                ui.label(tr("settings.string_spacing"));
                 ui.add(egui::Slider::new(&mut self.settings.space_string, 40.0..=100.0).show_value(false));
                 ui.end_row();
-                ui.label("fret spacing");
+                ui.label(tr("settings.fret_spacing"));
                 ui.add(egui::Slider::new(&mut self.settings.space_fret, 40.0..=100.0).show_value(false));
             });
             ui.add_space(10.0);
 
         });
     }
-    fn draw_panel_fretboard(&mut self, ctx: &egui::Context){
-        let painter = Painter::new(
-            ctx.clone(),
-            LayerId {
-                id: Id::new("shapes_layer"),
-                order: Order::Background,
-            },
-            ctx.available_rect(),
-        );
-        egui::CentralPanel::default().show(ctx, |ui|{
-
-            let num_frets = self.settings.frets + 1;
-            
-            // the "width" of the fret board
-            let fretboard_size = (self.strings().len() as f32) * self.settings.space_string;
-            let half_size = fretboard_size/2f32;
-
-            // rect position
-            let rect = ui.available_rect_before_wrap();
-            let offset:f32 = 10f32 + match self.settings.vertical {
-                true => rect.top(),
-                false => rect.left(),
+    fn receive_dropped_files(&mut self, ctx: &egui::Context) {
+        for file in ctx.input(|i| i.raw.dropped_files.clone()) {
+            let bytes = match (&file.bytes, &file.path) {
+                (Some(bytes), _) => Ok(bytes.to_vec()),
+                (None, Some(path)) => std::fs::read(path).map_err(|e| e.to_string()),
+                (None, None) => Err(tr("error.empty_file")),
             };
-            let center = match self.settings.vertical {
-                false => rect.center().y,
-                true => rect.center().x,
+            let name = match &file.path {
+                Some(path) => path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+                None => file.name.clone(),
             };
-            // fret marker positions
-            let gap_to_fret_markers = 10f32;
-            let d_fret_marker1 = center - half_size as f32 - gap_to_fret_markers;
-            let d_fret_marker2 = center + half_size as f32 + gap_to_fret_markers;
-            
-            // paint frets and fret-markers
-            for fret in 0..num_frets {
-                let mut pos_down_neck = fret as f32 * self.settings.space_fret + offset;
-                let pos_fret_marker1 = match self.settings.vertical {
-                    true => Pos2 { x: d_fret_marker1, y: pos_down_neck },
-                    false => Pos2 { x: pos_down_neck, y: d_fret_marker1 },
-                };
-                let pos_fret_marker2 = match self.settings.vertical {
-                    true => Pos2 { x: d_fret_marker2, y: pos_down_neck },
-                    false => Pos2 { x: pos_down_neck, y: d_fret_marker2 },
-                };
-                self.draw_fret_marker(fret, painter.to_owned(), pos_fret_marker1);
-                self.draw_fret_marker(fret, painter.to_owned(), pos_fret_marker2);
-                pos_down_neck += self.settings.space_fret.div(2f32);
-                self.draw_fret(painter.to_owned(), fret, pos_down_neck, center, half_size);
-            }
-            // paint strings and notes
-            for i in 0..*&self.strings().len() {
-                let string = self.strings()[ match self.settings.vertical {
-                    true => i,
-                    false => self.strings().len() - i - 1,
-                }];
-
-                let cell_pree :f32= center - half_size + (i as f32 * self.settings.space_string);
-                let cell_post :f32= cell_pree + self.settings.space_string;
-                let cell_middle = (cell_pree + cell_post)/2f32;
-
-                // draw horizontal line (string):
-                match self.settings.string_style {
-                    StringStyle::String => {
-                        self.draw_line(painter.to_owned(), rect, cell_middle);
-                    },
-                    StringStyle::Cells => {
-                        self.draw_line(painter.to_owned(), rect, cell_pree);
-                        self.draw_line(painter.to_owned(), rect, cell_post);
+            match name.to_lowercase().ends_with(".wav") {
+                true => self.load_recording(&name, bytes),
+                false => self.load_song(&name, bytes),
+            }
+        }
+    }
+    fn load_recording(&mut self, name:&str, bytes:Result<Vec<u8>, String>) {
+        match bytes.and_then(|b| Recording::from_wav(name, &b)) {
+            Ok(recording) => {
+                self.listener.load(recording);
+                if self.open_panel != Panel::Tuner {
+                    self.open_panel = Panel::Listen;
+                }
+            },
+            Err(e) => {
+                self.toasts.error(trf("error.read", &[&name, &e]));
+            },
+        }
+    }
+    fn load_song(&mut self, name:&str, bytes:Result<Vec<u8>, String>) {
+        match bytes.and_then(|b| Song::from_bytes(name, &b)) {
+            Ok(song) => {
+                self.song.load(song);
+                self.open_panel = Panel::Song;
+            },
+            Err(e) => {
+                self.toasts.error(trf("error.read", &[&name, &e]));
+            },
+        }
+    }
+    fn draw_audio_source(&mut self, ui: &mut Ui) {
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.horizontal(|ui|{
+            ui.add(egui::TextEdit::singleline(&mut self.listener.wav_path).hint_text("path/to/file.wav").desired_width(150.0));
+            if ui.button(tr("audio.load")).clicked() {
+                let path = self.listener.wav_path.clone();
+                let name = std::path::Path::new(&path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or(path.clone());
+                self.load_recording(&name, std::fs::read(&path).map_err(|e| e.to_string()));
+            }
+        });
+        ui.label(tr("audio.drop"));
+        #[cfg(feature = "microphone")]
+        {
+            let mut listening = self.listener.microphone.is_some();
+            if ui.toggle_value(&mut listening, tr("audio.microphone")).clicked() {
+                self.listener.microphone = match self.listener.microphone {
+                    Some(_) => None,
+                    None => match crate::pitch::Microphone::open() {
+                        Ok(mic) => Some(mic),
+                        Err(e) => {
+                            self.toasts.error(trf("error.microphone", &[&e]));
+                            None
+                        },
                     },
                 };
-
-                // paint notes:
-                for fret in 0..(num_frets) {
-                    let b = self.scale.get_bubble(self.settings.dark_mode, self.settings.note_colors, string + fret, self.settings.note_marks);
-                    let pos = match self.settings.vertical {
-                        false => Pos2 { x: offset + fret as f32 * self.settings.space_fret, y: cell_middle },
-                        true => Pos2 { x: cell_middle, y: offset + fret as f32 * self.settings.space_fret },
-                    };
-                    painter.circle_filled(pos, self.settings.dot_size, b.color);
-                    painter.text(pos, Align2::CENTER_CENTER, b.text, font_glyph(), b.text_color);
+                self.listener.heard = None;
+            }
+        }
+        ui.add_space(10.0);
+        if let Some(recording) = &self.listener.recording {
+            let duration = recording.duration();
+            ui.label(&recording.name);
+            ui.horizontal(|ui|{
+                let label = match self.listener.playing {
+                    true => tr("audio.pause"),
+                    false => tr("audio.play"),
+                };
+                if ui.button(label).clicked() {
+                    self.listener.playing = !self.listener.playing;
+                }
+                if ui.add(egui::Slider::new(&mut self.listener.position, 0.0..=duration).suffix("s")).changed() {
+                    self.listener.update(0.0);
                 }
+            });
+        }
+        ui.add_space(10.0);
+    }
+    fn note_name(&self, midi:usize) -> String {
+        format!("{}{}", self.scale.get_note_letter(midi % TOTAL_TONES), (midi / TOTAL_TONES) as i32 - 1)
+    }
+    fn draw_panel_listen(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("Listen")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("listen.title"));
+            ui.add_space(14f32);
+            self.draw_audio_source(ui);
+            match self.listener.heard {
+                None => {
+                    ui.label(tr("common.no_pitch"));
+                },
+                Some(heard) => {
+                    let pc = heard.pitch_class();
+                    ui.heading(self.note_name(heard.midi));
+                    ui.label(trf("listen.reading", &[&format!("{:.1}", heard.frequency), &format!("{:+.0}", heard.cents)]));
+                    match self.scale.is_note_in_scale(pc as i16) {
+                        true => ui.colored_label(Color32::DARK_GREEN, trf("listen.in_scale", &[&self.scale.scale_name()])),
+                        false => ui.colored_label(Color32::RED, trf("listen.not_in_scale", &[&self.scale.scale_name()])),
+                    };
+                },
             }
-        
-            // paint legend
-            if self.settings.show_legend {
-                self.draw_legend(rect, painter);
+        });
+    }
+    fn tuner_reading(&self) -> Option<TunerReading> {
+        self.listener.heard.and_then(|heard| nearest_string(self.instrument(), heard.frequency))
+    }
+    fn draw_panel_tuner(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("Tuner")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("tuner.title"));
+            ui.label(format!("{}, {}", self.instrument().name, self.instrument().tuning().name));
+            ui.add_space(14f32);
+            self.draw_audio_source(ui);
+            let reading = self.tuner_reading();
+            self.draw_tuner_needle(ui, reading);
+            match reading {
+                None => {
+                    ui.label(tr("common.no_pitch"));
+                },
+                Some(reading) => {
+                    ui.heading(self.note_name(reading.target_midi));
+                    let (text, color) = match reading {
+                        r if r.in_tune() => (tr("tuner.in_tune"), Color32::DARK_GREEN),
+                        r if r.cents < 0.0 => (trf("tuner.flat", &[&format!("{:.0}", -r.cents)]), Color32::RED),
+                        r => (trf("tuner.sharp", &[&format!("{:.0}", r.cents)]), Color32::RED),
+                    };
+                    ui.colored_label(color, text);
+                    ui.label(trf("tuner.target", &[&format!("{:.1}", reading.target_frequency)]));
+                },
             }
+            ui.add_space(10.0);
+            egui::Grid::new("tuner_strings")
+            .show(ui, |ui|{
+                for (i, target) in target_midi(self.instrument()).into_iter().enumerate() {
+                    let text = RichText::new(self.note_name(target));
+                    ui.label(trf("tuner.string", &[&(i + 1)]));
+                    match reading.map(|r| r.string) == Some(i) {
+                        true => ui.label(text.strong()),
+                        false => ui.label(text),
+                    };
+                    ui.label(format!("{:.1} Hz", midi_to_frequency(target as f32)));
+                    ui.end_row();
+                }
+            });
         });
     }
-    fn draw_line(&self, painter:Painter, rect:Rect, pos:f32){
-        painter.line_segment(
-            match self.settings.vertical {
-                false => [
-                    Pos2::new(rect.left(), pos),
-                    Pos2::new(rect.right(), pos),
-                ],
-                true => [
-                    Pos2::new(pos, rect.top()),
-                    Pos2::new(pos, rect.bottom()),
-                ],
-            },
-            self.stroke(1f32),
-        );
+    fn draw_tuner_needle(&self, ui: &mut Ui, reading: Option<TunerReading>) {
+        let (response, painter) = ui.allocate_painter(vec2(220f32, 120f32), Sense::hover());
+        let rect = response.rect;
+        let pivot = Pos2 { x: rect.center().x, y: rect.bottom() - 10f32 };
+        let radius = 100f32;
+        let range = 50f32;
+        let max_angle = 60f32.to_radians();
+        let point_at = |cents:f32, r:f32| {
+            let angle = cents.clamp(-range, range) / range * max_angle;
+            Pos2 { x: pivot.x + r * angle.sin(), y: pivot.y - r * angle.cos() }
+        };
+        let in_tune = Stroke::new(6f32, Color32::DARK_GREEN);
+        painter.line_segment([point_at(-IN_TUNE_CENTS, radius), point_at(IN_TUNE_CENTS, radius)], in_tune);
+        for tick in (-50..=50).step_by(10) {
+            let length = match tick {
+                0 => 14f32,
+                _ => 8f32,
+            };
+            painter.line_segment([point_at(tick as f32, radius), point_at(tick as f32, radius - length)], self.stroke(1f32));
+        }
+        painter.text(point_at(-range, radius + 8f32), Align2::CENTER_BOTTOM, "♭", font(14f32, FontFamily::Proportional), self.stroke(1f32).color);
+        painter.text(point_at(range, radius + 8f32), Align2::CENTER_BOTTOM, "♯", font(14f32, FontFamily::Proportional), self.stroke(1f32).color);
+        if let Some(reading) = reading {
+            let color = match reading.in_tune() {
+                true => Color32::DARK_GREEN,
+                false => Color32::RED,
+            };
+            painter.line_segment([pivot, point_at(reading.cents, radius - 4f32)], Stroke::new(2f32, color));
+        }
+        painter.circle_filled(pivot, 4f32, self.stroke(1f32).color);
     }
-    fn draw_fret(&self, painter:Painter, fret:usize, offset:f32, center:f32, half_width:f32) {
-        painter.line_segment(
-            match self.settings.vertical {
-                true => [
-                    Pos2 {
-                        x: center - half_width,
-                        y: offset,
-                    },
-                    Pos2 {
-                        x: center + half_width,
-                        y: offset,
-                    },
-                ],
-                false => [
-                    Pos2 {
-                        x: offset,
-                        y: center - half_width,
-                    },
-                    Pos2 {
-                        x: offset,
-                        y: center + half_width,
-                    },
-                ],
-            },
-            self.stroke(match fret {
-                0 => 3f32,
-                _ => 1f32,
-            }),
-        );
+    fn quiz_label(&self, question:&Question, value:usize) -> String {
+        match question {
+            Question::Interval { .. } => interval_name(value),
+            Question::NoteName { .. } | Question::FindAll { .. } | Question::Chord { ask: ChordAsk::Root, .. } => self.scale.get_note_letter(value),
+            Question::ScaleDegree { .. } => self.scale.get_note_number(value),
+            Question::Chord { ask: ChordAsk::Quality, .. } => ChordQuality::iter().nth(value).map_or(String::new(), |q| i18n::label(&q)),
+        }
     }
-    fn draw_fret_marker(&self, fret:usize, painter:Painter, mut pos:Pos2){
-        let is_octave = fret % 12 == 0;
-        let draw_dot = |p|{
-            painter.circle_filled(p, 3f32, match self.settings.dark_mode {
-                false => Color32::BLACK,
-                true => Color32::WHITE,
+    fn draw_panel_quiz(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("Quiz")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("quiz.title"));
+            ui.add_space(14f32);
+            egui::Grid::new("quiz_settings")
+            .show(ui, |ui|{
+                ui.label(tr("quiz.kind"));
+                ComboBox::from_id_salt("quiz_kind")
+                    .selected_text(i18n::label(&self.quiz.kind))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for k in QuizKind::iter() {
+                            inner_ui.selectable_value(&mut self.quiz.kind, k, i18n::label(&k));
+                        }
+                    });
+                ui.end_row();
+                ui.label(tr("quiz.difficulty"));
+                ComboBox::from_id_salt("quiz_difficulty")
+                    .selected_text(i18n::label(&self.quiz.difficulty))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for d in Difficulty::iter() {
+                            inner_ui.selectable_value(&mut self.quiz.difficulty, d, i18n::label(&d));
+                        }
+                    });
+                ui.end_row();
             });
-        };
-        match match fret.try_into().unwrap_or(0) {
-            3|5|7|9|12|15|17|19 => self.settings.fret_marks,
-            _ => FretMarker::None,
-        } {
-            FretMarker::None => {},
-            FretMarker::Dots => {
-                match is_octave {
-                    false => draw_dot(pos),
+            ui.add_space(10.0);
+            let strings = self.strings().clone();
+            ui.horizontal(|ui|{
+                match self.quiz.is_running() {
+                    false => if ui.button(tr("common.start")).clicked() {
+                        let seed = ctx.input(|i| i.time).to_bits();
+                        self.quiz.start(seed, &strings, self.settings.frets, &self.scale);
+                    },
                     true => {
-                        pos.x += 4.0;
-                        draw_dot(pos);
-                        pos.x -= 8.0;
-                        draw_dot(pos);
-                    }
+                        if ui.button(tr("quiz.skip")).clicked() {
+                            self.quiz.next(&strings, self.settings.frets, &self.scale);
+                        }
+                        if ui.button(tr("common.stop")).clicked() {
+                            self.quiz.stop();
+                        }
+                    },
                 }
-            },
-            FretMarker::Numbers => {
-                painter.text(
-                    pos,
-                    Align2::CENTER_CENTER,
-                    (fret).to_string(),
-                    font(12f32, FontFamily::Monospace),
-                    match is_octave {
-                        false => match self.settings.dark_mode { true => Color32::WHITE, false => Color32::BLACK },
-                        true => match self.settings.dark_mode { true => Color32::GOLD, false => Color32::BLUE },
+            });
+            ui.add_space(10.0);
+            let prompt = match &self.quiz.question {
+                None => None,
+                Some(Question::Interval { .. }) => Some(tr("quiz.interval")),
+                Some(Question::NoteName { .. }) => Some(tr("quiz.note")),
+                Some(Question::ScaleDegree { .. }) => Some(trf("quiz.degree", &[&self.scale.key_name(), &self.scale.scale_name()])),
+                Some(Question::FindAll { note, found, total }) => Some(trf("quiz.find_all", &[&self.scale.get_note_letter(*note), &found.len(), total])),
+                Some(Question::Chord { ask: ChordAsk::Quality, .. }) => Some(tr("quiz.chord_quality")),
+                Some(Question::Chord { ask: ChordAsk::Root, .. }) => Some(tr("quiz.chord_root")),
+            };
+            if let (Some(prompt), Some(question)) = (prompt, self.quiz.question.clone()) {
+                ui.label(prompt);
+                ui.add_space(5.0);
+                let mut chosen = None;
+                ui.horizontal_wrapped(|ui|{
+                    for option in self.quiz.options.iter() {
+                        if ui.button(self.quiz_label(&question, *option)).clicked() {
+                            chosen = Some(*option);
+                        }
                     }
-                );
+                });
+                if let Some(option) = chosen {
+                    self.quiz.answer(option, &strings, self.settings.frets, &self.scale);
+                }
             }
-        }
+            ui.add_space(10.0);
+            if let Some(feedback) = &self.quiz.feedback {
+                match feedback.correct {
+                    true => ui.colored_label(Color32::DARK_GREEN, format!("✔ {}", self.quiz_label(&feedback.question, feedback.answer))),
+                    false => ui.colored_label(Color32::RED, trf("quiz.wrong", &[&self.quiz_label(&feedback.question, feedback.answer)])),
+                };
+            }
+            ui.label(trf("quiz.score", &[&self.quiz.score, &self.quiz.asked]));
+            ui.label(trf("quiz.streak", &[&self.quiz.streak, &self.quiz.best_streak]));
+        });
     }
-    fn draw_legend(&self, rect:Rect, painter:Painter){
-        let m = 10f32;
-        let w = 250f32;
-        let h = 5f32 * 35f32 + 15f32;
-        let bg = Rect {
-            min: Pos2 { x: rect.right() - m - w, y: rect.bottom() - m - h },
-            max: Pos2 { x: rect.right() - m, y: rect.bottom() - m },
-        };
-        painter.rect(bg, CornerRadius::same(4u8), match self.settings.dark_mode {
-            true => Color32::DARK_GRAY,
-            false => Color32::LIGHT_GRAY,
-        }, self.stroke(1f32), StrokeKind::Middle);
-        let draw_dot = |x:f32, y:f32, n, str|{
-            let pos = Pos2 { x: bg.min.x + x, y: bg.min.y + y };
-            let b = self.scale.get_bubble_from(self.settings.note_colors, self.settings.dark_mode, n, self.settings.note_marks);
-            painter.circle_filled(pos, self.settings.dot_size, b.color);
-            painter.text(pos, Align2::CENTER_CENTER, b.text, font_glyph(), b.text_color);
-            painter.text(
-                Pos2 { x: pos.x + 30f32, y: pos.y}, 
-                Align2::LEFT_CENTER, 
-                str,
-                font(14f32, FontFamily::Monospace),
-                match self.settings.dark_mode { true => Color32::WHITE, false => Color32::BLACK }
-            );
-        };
-        draw_dot(25f32, 25f32, NoteType::Root, "Root notes");
-        draw_dot(25f32, 60f32, NoteType::Triad, "Triad notes");
-        draw_dot(25f32, 95f32, NoteType::InPentatonic, "Notes in scale (penta)");
-        draw_dot(25f32, 130f32, NoteType::Blue, "Blue notes");
-        draw_dot(25f32, 165f32, NoteType::InDiatonic, "Notes in scale (natural)");
-
+    fn draw_panel_trainer(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        egui::SidePanel::left("Trainer")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("trainer.title"));
+            ui.add_space(14f32);
+            let instrument = match self.instruments.get(self.current_instrument) {
+                Some(instrument) => instrument,
+                None => &self.empty_instrument,
+            };
+            egui::Grid::new("trainer_settings")
+            .show(ui, |ui|{
+                ui.label(tr("trainer.drill"));
+                ComboBox::from_id_salt("trainer_drill")
+                    .selected_text(i18n::label(&self.trainer.drill))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for d in Drill::iter() {
+                            inner_ui.selectable_value(&mut self.trainer.drill, d, i18n::label(&d));
+                        }
+                    });
+                ui.end_row();
+                ui.label(tr("trainer.heat_map"));
+                let label = match self.trainer.heat_map {
+                    true => tr("common.on"),
+                    false => tr("common.off"),
+                };
+                ui.toggle_value(&mut self.trainer.heat_map, label);
+                ui.end_row();
+            });
+            ui.add_space(10.0);
+            ui.horizontal(|ui|{
+                match self.trainer.prompt {
+                    None => if ui.button(tr("common.start")).clicked() {
+                        self.trainer.start(now.to_bits(), instrument, self.settings.frets, now);
+                    },
+                    Some(_) => if ui.button(tr("common.stop")).clicked() {
+                        self.trainer.stop();
+                    },
+                }
+            });
+            ui.add_space(10.0);
+            let strings = instrument.strings();
+            match self.trainer.prompt {
+                None => {},
+                Some(Prompt::NameNote { at }) => {
+                    ui.label(trf("trainer.name_note", &[&string_number(strings, at.string), &at.fret]));
+                    let mut chosen = None;
+                    egui::Grid::new("trainer_notes")
+                    .show(ui, |ui|{
+                        for pc in 0..TOTAL_TONES {
+                            if ui.button(self.scale.get_note_letter(pc)).clicked() {
+                                chosen = Some(pc);
+                            }
+                            if pc % 4 == 3 {
+                                ui.end_row();
+                            }
+                        }
+                    });
+                    if let Some(pc) = chosen {
+                        self.trainer.answer_note(pc, instrument, self.settings.frets, now);
+                    }
+                },
+                Some(Prompt::FindOnString { at }) => {
+                    let note = self.scale.get_note_letter(at.note(strings));
+                    ui.label(trf("trainer.find", &[&note, &string_number(strings, at.string)]));
+                },
+            }
+            ui.add_space(10.0);
+            if let Some(feedback) = self.trainer.feedback {
+                let text = trf("trainer.feedback", &[
+                    &string_number(strings, feedback.at.string),
+                    &feedback.at.fret,
+                    &self.scale.get_note_letter(feedback.at.note(strings)),
+                    &format!("{:.1}", feedback.seconds)]);
+                match feedback.correct {
+                    true => ui.colored_label(Color32::DARK_GREEN, format!("✔ {}", text)),
+                    false => ui.colored_label(Color32::RED, format!("✘ {}", text)),
+                };
+            }
+            let (practiced, accuracy, seconds) = self.trainer.summary(instrument);
+            ui.label(trf("trainer.practiced", &[&practiced]));
+            ui.label(trf("trainer.summary", &[&format!("{:.0}", accuracy * 100.0), &format!("{:.1}", seconds)]));
+            ui.add_space(10.0);
+            if ui.button(tr("trainer.reset")).clicked() {
+                self.trainer.history = Default::default();
+            }
+        });
     }
-    fn stroke(&self, weight:f32) -> Stroke {
-        Stroke::new(weight, match self.settings.dark_mode {
-            false => Color32::BLACK,
+    fn draw_panel_arpeggio(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("Arpeggio")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("arpeggio.title"));
+            ui.add_space(14f32);
+            egui::Grid::new("arpeggio_settings")
+            .show(ui, |ui|{
+                ui.label(tr("arpeggio.root"));
+                ComboBox::from_id_salt("arpeggio_root")
+                    .selected_text(self.scale.get_note_letter(self.arpeggio.root))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for pc in 0..TOTAL_TONES {
+                            inner_ui.selectable_value(&mut self.arpeggio.root, pc, self.scale.get_note_letter(pc));
+                        }
+                    });
+                ui.end_row();
+                ui.label(tr("arpeggio.chord"));
+                ComboBox::from_id_salt("arpeggio_quality")
+                    .selected_text(i18n::label(&self.arpeggio.quality))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for q in ChordQuality::iter() {
+                            inner_ui.selectable_value(&mut self.arpeggio.quality, q, i18n::label(&q));
+                        }
+                    });
+                ui.end_row();
+                ui.label(tr("arpeggio.octaves"));
+                ui.add(egui::Slider::new(&mut self.arpeggio.octaves, 1..=3));
+                ui.end_row();
+                ui.label(tr("arpeggio.start_fret"));
+                ui.add(egui::Slider::new(&mut self.arpeggio.start_fret, 0..=self.settings.frets));
+                ui.end_row();
+                ui.label(tr("arpeggio.picking"));
+                ComboBox::from_id_salt("arpeggio_picking")
+                    .selected_text(i18n::label(&self.arpeggio.picking))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for p in Picking::iter() {
+                            inner_ui.selectable_value(&mut self.arpeggio.picking, p, i18n::label(&p));
+                        }
+                    });
+                ui.end_row();
+            });
+            ui.add_space(10.0);
+            let notes = self.arpeggio_notes();
+            let tones: Vec<&str> = self.arpeggio.quality.intervals().into_iter().map(chord_degree).collect();
+            ui.label(format!("{} ({})", self.arpeggio.name(&self.scale.get_note_letter(self.arpeggio.root)), tones.join(" ")));
+            ui.label(trf("arpeggio.count", &[&notes.len()]));
+            ui.add_space(10.0);
+            let hint = match self.arpeggio.picking {
+                Picking::Sweep => tr("arpeggio.sweep_hint"),
+                Picking::Economy => tr("arpeggio.economy_hint"),
+            };
+            ui.label(hint);
+        });
+    }
+    fn draw_panel_circle(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("Circle")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("circle.title"));
+            ui.add_space(14f32);
+            let (response, painter) = ui.allocate_painter(Vec2::splat(230f32), Sense::click());
+            let center = response.rect.center();
+            let (outer, inner) = (92f32, 55f32);
+            let relative = self.scale.relative_major();
+            // majors round the outside, clockwise from C at the top, their relative minors inside
+            for i in 0..TOTAL_TONES {
+                let major = i * 7 % TOTAL_TONES;
+                let minor = (major + 9) % TOTAL_TONES;
+                let angle = i as f32 * std::f32::consts::TAU / TOTAL_TONES as f32;
+                let direction = Vec2::new(angle.sin(), -angle.cos());
+                let names = |typ:ScaleType, pc:usize| -> String {
+                    keys(typ).iter()
+                        .filter(|k| k.0 == pc)
+                        .filter_map(|k| spell(k.1, k.0, self.scale.naming))
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                };
+                let segments = [
+                    (center + direction * outer, 20f32, names(ScaleType::Major, major), self.scale.typ != ScaleType::Minor),
+                    (center + direction * inner, 16f32, format!("{}m", names(ScaleType::Minor, minor).replace('\n', "m\n")), self.scale.typ == ScaleType::Minor),
+                ];
+                for (pos, size, text, ring) in segments {
+                    let typ = match (major == relative.key, ring) {
+                        (true, true) => NoteType::Root,
+                        (true, false) => NoteType::Triad,
+                        (false, _) if (major + 7) % TOTAL_TONES == relative.key || (major + 5) % TOTAL_TONES == relative.key => NoteType::InDiatonic,
+                        (false, _) => NoteType::NotInScale,
+                    };
+                    let (color, text_color) = Scale::color_lookup(self.settings.note_colors, self.settings.dark_mode, typ, 0);
+                    painter.circle(pos, size, color, self.stroke(1f32));
+                    painter.text(pos, Align2::CENTER_CENTER, text, font(11f32, FontFamily::Proportional), text_color);
+                }
+            }
+            if let Some(click) = response.interact_pointer_pos().filter(|_| response.clicked()) {
+                let offset = click - center;
+                let turn = offset.x.atan2(-offset.y).rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU;
+                let major = (turn * TOTAL_TONES as f32).round() as usize % TOTAL_TONES * 7 % TOTAL_TONES;
+                let distance = offset.length();
+                let picked = match distance {
+                    d if d > outer + 20f32 || d < inner - 16f32 => None,
+                    d if d > (outer + inner) / 2f32 => Some((ScaleType::Major, major)),
+                    _ => Some((ScaleType::Minor, (major + 9) % TOTAL_TONES)),
+                };
+                if let Some((typ, key)) = picked {
+                    self.scale.typ = typ;
+                    self.scale.key = key;
+                    self.scale.key_letter = key_letter(typ, key);
+                }
+            }
+            ui.add_space(10.0);
+            let signature = self.scale.key_signature();
+            match self.scale.typ {
+                ScaleType::Major => ui.label(trf("circle.relative_minor", &[&self.scale.relative_minor().key_name()])),
+                _ => ui.label(trf("circle.relative_major", &[&relative.key_name()])),
+            };
+            ui.label(match signature.is_empty() {
+                true => tr("circle.no_accidentals"),
+                false => trf("circle.signature", &[&signature.join(" ")]),
+            });
+            ui.horizontal(|ui|{
+                ui.label(tr("circle.neighbours"));
+                let down = self.scale.neighbour(false);
+                let up = self.scale.neighbour(true);
+                if ui.button(format!("◀ {}", down.key_name())).clicked() {
+                    (self.scale.key, self.scale.key_letter) = (down.key, down.key_letter);
+                }
+                if ui.button(format!("{} ▶", up.key_name())).clicked() {
+                    (self.scale.key, self.scale.key_letter) = (up.key, up.key_letter);
+                }
+            });
+            ui.add_space(10.0);
+            egui::Grid::new("circle_settings")
+            .show(ui, |ui|{
+                ui.label(tr("circle.modulation"));
+                ComboBox::from_id_salt("modulation")
+                    .selected_text(i18n::label(&self.modulation))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for m in Modulation::iter() {
+                            inner_ui.selectable_value(&mut self.modulation, m, i18n::label(&m));
+                        }
+                    });
+                ui.end_row();
+            });
+            if let Some((neighbour, leaving, arriving)) = self.modulation_notes() {
+                ui.label(trf("circle.change", &[&self.scale.get_note_letter(leaving), &neighbour.get_note_letter(arriving)]));
+            }
+        });
+    }
+    fn draw_panel_compare(&mut self, ctx: &egui::Context) {
+        // the second scale is always named and numbered like the first
+        self.compare.naming = self.scale.naming;
+        self.compare.numbering = self.scale.numbering;
+        egui::SidePanel::left("Compare")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("compare.title"));
+            ui.add_space(14f32);
+            egui::Grid::new("compare_settings")
+            .show(ui, |ui|{
+                ui.colored_label(ONLY_IN_A, "A");
+                ui.label(full_name(&self.scale));
+                ui.end_row();
+                ui.colored_label(ONLY_IN_B, "B");
+                ui.vertical(|ui| scale_picker(ui, "compare", &mut self.compare));
+                ui.end_row();
+            });
+            if ui.button(tr("compare.swap")).clicked() {
+                std::mem::swap(&mut self.scale, &mut self.compare);
+            }
+            ui.add_space(10.0);
+            let (only_a, only_b) = self.scale.differences(&self.compare);
+            if only_a.is_empty() && only_b.is_empty() {
+                ui.label(tr("compare.same"));
+                return;
+            }
+            // each note is spelled and numbered by the scale it belongs to
+            let describe = |scale:&Scale, notes:&Vec<usize>| -> String {
+                notes.iter()
+                    .map(|n| format!("{} ({})", scale.get_note_letter(*n), scale.get_note_number((n + TOTAL_TONES - scale.key) % TOTAL_TONES)))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
+            let none = tr("compare.none");
+            ui.label(trf("compare.only_in", &[&"A", &match only_a.is_empty() {
+                true => none.clone(),
+                false => describe(&self.scale, &only_a),
+            }]));
+            ui.label(trf("compare.only_in", &[&"B", &match only_b.is_empty() {
+                true => none,
+                false => describe(&self.compare, &only_b),
+            }]));
+        });
+    }
+    fn draw_panel_views(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("Views")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("views.title"));
+            ui.add_space(14f32);
+            egui::Grid::new("views_settings")
+            .show(ui, |ui|{
+                ui.label(tr("views.layout"));
+                ComboBox::from_id_salt("view_layout")
+                    .selected_text(i18n::label(&self.settings.layout))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for l in ViewLayout::iter() {
+                            inner_ui.selectable_value(&mut self.settings.layout, l, i18n::label(&l));
+                        }
+                    });
+                ui.end_row();
+            });
+            ui.add_space(10.0);
+            let mut remove = None;
+            for (index, view) in self.views.iter_mut().enumerate() {
+                ui.group(|ui|{
+                    ui.horizontal(|ui|{
+                        ui.strong(trf("views.fretboard", &[&(index + 2)]));
+                        if ui.small_button("🗑").on_hover_text(tr("views.remove")).clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                    let before = view.instrument;
+                    ComboBox::from_id_salt(("view_instrument", index))
+                        .selected_text(&self.instruments[view.instrument].name)
+                        .width(100.0)
+                        .show_ui(ui, |inner_ui|{
+                            for i in 0..self.instruments.len() {
+                                inner_ui.selectable_value(&mut view.instrument, i, &self.instruments[i].name);
+                            }
+                        });
+                    if view.instrument != before {
+                        view.tune_index = 0;
+                    }
+                    let instrument = &self.instruments[view.instrument];
+                    ComboBox::from_id_salt(("view_tuning", index))
+                        .selected_text(instrument.tunings[view.tune_index].name())
+                        .width(100.0)
+                        .show_ui(ui, |inner_ui|{
+                            for i in 0..instrument.tunings.len() {
+                                inner_ui.selectable_value(&mut view.tune_index, i, instrument.tunings[i].name());
+                            }
+                        });
+                    let scale = view.scale.as_ref().unwrap_or(&self.scale);
+                    ui.horizontal(|ui|{
+                        position_picker(ui, &format!("view_position_{}", index), &mut view.position, scale, self.settings.frets);
+                    });
+                    let mut shared = view.scale.is_none();
+                    if ui.checkbox(&mut shared, tr("views.share_scale")).changed() {
+                        view.scale = match shared {
+                            true => None,
+                            false => Some(Scale { ..self.scale }),
+                        };
+                    }
+                    if let Some(scale) = view.scale.as_mut() {
+                        scale.naming = self.scale.naming;
+                        scale.numbering = self.scale.numbering;
+                        scale_picker(ui, &format!("view_scale_{}", index), scale);
+                    }
+                });
+            }
+            if let Some(index) = remove {
+                self.views.remove(index);
+            }
+            if ui.button(tr("views.add")).clicked() {
+                self.views.push(View {
+                    instrument: self.current_instrument,
+                    tune_index: 0,
+                    position: Position::All,
+                    scale: None,
+                });
+            }
+        });
+    }
+    fn draw_panel_staff(&mut self, ctx: &egui::Context) {
+        let strings = self.strings().clone();
+        self.selected.retain(|at| at.string < strings.len() && at.fret <= self.settings.frets);
+        let mut clear = false;
+        egui::SidePanel::left("Staff")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("staff.title"));
+            ui.add_space(14f32);
+            let instrument = self.instrument();
+            let clef = instrument.clef;
+            ui.label(trf("staff.clef", &[&i18n::label(&clef)]));
+            ui.add_space(10.0);
+            // the scale climbs an octave from the tonic, closing on the tonic above
+            let tonic = clef.tonic_midi(self.scale.key, self.scale.key_letter);
+            let mut intervals = self.scale.notes();
+            intervals.push(TOTAL_TONES);
+            let scale_notes: Vec<usize> = intervals.iter().map(|i| tonic + i).collect();
+            ui.label(full_name(&self.scale));
+            self.draw_staff(ui, clef, &scale_notes);
+            ui.add_space(10.0);
+            ui.label(tr("staff.selected"));
+            if self.selected.is_empty() {
+                ui.label(tr("staff.hint"));
+                return;
+            }
+            let selected: Vec<usize> = self.selected.iter().map(|at| instrument.midi_note(at.note(&strings))).collect();
+            self.draw_staff(ui, clef, &selected);
+            clear = ui.button(tr("staff.clear")).clicked();
+        });
+        if clear {
+            self.selected.clear();
+        }
+    }
+    // whole notes on a five line staff, spelled in the current key after its key signature
+    fn draw_staff(&self, ui:&mut Ui, clef:Clef, notes:&[usize]) {
+        let gap = 8f32;
+        let (response, painter) = ui.allocate_painter(Vec2::new(ui.available_width(), 16f32 * gap), Sense::hover());
+        let rect = response.rect;
+        let color = self.stroke(1f32).color;
+        // room above the staff for four ledger lines
+        let top = rect.top() + 5f32 * gap;
+        let y = |position:i32| top + (staff::TOP_LINE - position) as f32 * gap / 2f32;
+        for line in (0..=staff::TOP_LINE).step_by(2) {
+            painter.line_segment([Pos2::new(rect.left(), y(line)), Pos2::new(rect.right(), y(line))], self.stroke(1f32));
+        }
+        // the treble clef curls round the G line, the bass clef starts on the F line
+        let (outline, line, x) = match clef {
+            Clef::Bass => (&staff::BASS_CLEF[..], 6, rect.left() + 6f32),
+            _ => (&staff::TREBLE_CLEF[..], 2, rect.left() + 16f32),
+        };
+        let points: Vec<Pos2> = outline.iter().map(|(dx, dy)| Pos2::new(x + dx * gap, y(line) - dy * gap)).collect();
+        painter.add(egui::Shape::line(points, Stroke::new(2f32, color)));
+        match clef {
+            Clef::Bass => {
+                painter.circle_filled(Pos2::new(x + 0.15 * gap, y(line)), 0.22 * gap, color);
+                painter.circle_filled(Pos2::new(x + 1.75 * gap, y(line) - 0.5 * gap), 0.12 * gap, color);
+                painter.circle_filled(Pos2::new(x + 1.75 * gap, y(line) + 0.5 * gap), 0.12 * gap, color);
+            },
+            _ => {
+                painter.circle_filled(Pos2::new(x - 0.45 * gap, y(line) + 2.25 * gap), 0.2 * gap, color);
+            },
+        }
+        if clef == Clef::Treble8vb {
+            painter.text(Pos2::new(x - 0.3 * gap, y(line) + 3.4 * gap), Align2::CENTER_CENTER, "8", font(10f32, FontFamily::Proportional), color);
+        }
+        let signature = self.scale.signature();
+        let mut x = rect.left() + 36f32;
+        for (letter, accidental) in signature.iter() {
+            let pos = Pos2::new(x, y(clef.signature_position(*letter, *accidental)));
+            painter.text(pos, Align2::CENTER_CENTER, staff::accidental_symbol(*accidental), font(16f32, FontFamily::Proportional), color);
+            x += 7f32;
+        }
+        let start = x + 14f32;
+        let step = ((rect.right() - 10f32 - start) / notes.len().max(1) as f32).min(30f32);
+        let base = self.instrument().midi_note(0);
+        for (i, midi) in notes.iter().enumerate() {
+            let (letter, accidental) = self.scale.spelling(*midi);
+            let position = clef.position(*midi, letter, accidental);
+            let pos = Pos2::new(start + step * (i as f32 + 0.5), y(position));
+            for ledger in staff::ledger_lines(position) {
+                painter.line_segment([Pos2::new(pos.x - gap, y(ledger)), Pos2::new(pos.x + gap, y(ledger))], self.stroke(1f32));
+            }
+            // only accidentals the key signature doesn't already give
+            let in_signature = signature.iter().find(|(l, _)| *l == letter).map_or(0, |(_, a)| *a);
+            if accidental != in_signature {
+                let symbol = staff::accidental_symbol(accidental);
+                painter.text(Pos2::new(pos.x - gap, pos.y), Align2::RIGHT_CENTER, symbol, font(14f32, FontFamily::Proportional), color);
+            }
+            let bubble = self.scale.get_bubble(self.settings.dark_mode, self.settings.note_colors, midi.saturating_sub(base), NoteMarker::AllNotes);
+            painter.add(egui::Shape::ellipse_filled(pos, Vec2::new(0.65 * gap, 0.48 * gap), bubble.color));
+            painter.add(egui::Shape::ellipse_stroke(pos, Vec2::new(0.65 * gap, 0.48 * gap), self.stroke(1.5f32)));
+        }
+    }
+    fn draw_panel_tab(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("Tab")
+        .resizable(true)
+        .default_width(360.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("tab.title"));
+            ui.add_space(14f32);
+            egui::Grid::new("tab_settings")
+            .show(ui, |ui|{
+                ui.label(tr("tab.source"));
+                ComboBox::from_id_salt("tab_source")
+                    .selected_text(i18n::label(&self.tab_source))
+                    .width(100.0)
+                    .show_ui(ui, |inner_ui|{
+                        for s in TabSource::iter() {
+                            inner_ui.selectable_value(&mut self.tab_source, s, i18n::label(&s));
+                        }
+                    });
+                ui.end_row();
+            });
+            match self.tab_source {
+                TabSource::Chords => {
+                    ui.label(tr("tab.chords_hint"));
+                },
+                TabSource::Selected => {
+                    ui.label(tr("tab.select_hint"));
+                },
+                _ => {},
+            }
+            ui.add_space(10.0);
+            let text = self.export_tab();
+            egui::ScrollArea::horizontal().show(ui, |ui|{
+                ui.add(egui::TextEdit::multiline(&mut text.as_str()).code_editor().desired_width(f32::INFINITY));
+            });
+            ui.horizontal(|ui|{
+                if ui.button(tr("tab.copy")).clicked() {
+                    ctx.copy_text(text);
+                    self.toasts.info(tr("tab.copied"));
+                }
+                if ui.button(tr("tab.copy_musicxml")).clicked() {
+                    ctx.copy_text(self.export_musicxml());
+                    self.toasts.info(tr("tab.copied_musicxml"));
+                }
+            });
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.horizontal(|ui|{
+                ui.add(egui::TextEdit::singleline(&mut self.musicxml_path).hint_text("path/to/file.musicxml").desired_width(150.0));
+                if ui.button(tr("tab.save_musicxml")).clicked() {
+                    match std::fs::write(&self.musicxml_path, self.export_musicxml()) {
+                        Ok(()) => self.toasts.info(trf("tab.saved", &[&self.musicxml_path])),
+                        Err(e) => self.toasts.error(trf("error.write", &[&self.musicxml_path, &e])),
+                    };
+                }
+            });
+            ui.add_space(10.0);
+            ui.separator();
+            ui.label(tr("tab.import"));
+            egui::ScrollArea::horizontal().id_salt("tab_import").show(ui, |ui|{
+                ui.add(egui::TextEdit::multiline(&mut self.tab_text).code_editor().hint_text(tr("tab.import_hint")).desired_width(f32::INFINITY));
+            });
+            self.imported = match self.tab_text.trim().is_empty() {
+                true => vec![],
+                false => match tab::import(&self.tab_text, self.strings()) {
+                    Ok(notes) => notes,
+                    Err(e) => {
+                        ui.colored_label(Color32::RED, e);
+                        vec![]
+                    },
+                },
+            };
+            let notes: Vec<usize> = self.imported.iter().map(|n| n.note).collect();
+            if !notes.is_empty() {
+                ui.label(trf("tab.imported", &[&notes.len()]));
+                self.draw_best_fit(ui, &notes);
+            }
+            if !self.tab_text.is_empty() && ui.button(tr("tab.clear")).clicked() {
+                self.tab_text.clear();
+            }
+        });
+    }
+    // the diatonic scale the notes fit best, with a button to switch to it
+    fn draw_best_fit(&mut self, ui:&mut Ui, notes:&[usize]) {
+        let Some((typ, key)) = best_fit(notes) else {
+            return;
+        };
+        let letter = key_letter(typ, key);
+        let name = spell(letter, key, self.scale.naming).unwrap_or_default();
+        ui.horizontal(|ui|{
+            ui.label(trf("best_fit.label", &[&name, &i18n::label(&typ)]));
+            if ui.button(tr("best_fit.use")).clicked() {
+                self.scale.typ = typ;
+                self.scale.key = key;
+                self.scale.key_letter = letter;
+                self.scale.siz = ScaleSize::Diatonic;
+            }
+        });
+    }
+    fn draw_panel_song(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("Song")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("song.title"));
+            ui.add_space(14f32);
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.horizontal(|ui|{
+                ui.add(egui::TextEdit::singleline(&mut self.song.path).hint_text("path/to/song.gp").desired_width(150.0));
+                if ui.button(tr("song.load")).clicked() {
+                    let path = self.song.path.clone();
+                    let name = std::path::Path::new(&path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or(path.clone());
+                    self.load_song(&name, std::fs::read(&path).map_err(|e| e.to_string()));
+                }
+            });
+            ui.label(tr("song.drop"));
+            ui.add_space(10.0);
+            let Some(song) = &self.song.song else {
+                return;
+            };
+            ui.label(&song.name);
+            let tracks: Vec<String> = song.tracks.iter().map(|t| t.name.clone()).collect();
+            egui::Grid::new("song_settings")
+            .show(ui, |ui|{
+                ui.label(tr("song.track"));
+                ComboBox::from_id_salt("song_track")
+                    .selected_text(tracks.get(self.song.track).cloned().unwrap_or_default())
+                    .width(140.0)
+                    .show_ui(ui, |inner_ui|{
+                        for (i, name) in tracks.iter().enumerate() {
+                            inner_ui.selectable_value(&mut self.song.track, i, name);
+                        }
+                    });
+                ui.end_row();
+                let bars = self.song.bars().max(1);
+                ui.label(tr("song.bars"));
+                ui.horizontal(|ui|{
+                    ui.add(egui::DragValue::new(&mut self.song.from).range(1..=bars));
+                    ui.label("–");
+                    ui.add(egui::DragValue::new(&mut self.song.to).range(1..=bars));
+                });
+                ui.end_row();
+            });
+            self.song.refresh();
+            let notes = self.song.notes();
+            ui.add_space(10.0);
+            ui.label(trf("song.notes", &[&notes.len()]));
+            self.draw_best_fit(ui, &notes);
+        });
+    }
+    // the notes the Tab panel exports, in playing order, one beat at a time
+    fn tab_run(&self) -> Vec<Vec<FretPos>> {
+        let strings = self.strings();
+        let single = |notes: Vec<FretPos>| notes.into_iter().map(|at| vec![at]).collect();
+        match self.tab_source {
+            TabSource::Scale => single(tab::ascending(&self.displayed_notes(), strings)),
+            TabSource::Arpeggio => single(self.arpeggio_notes().iter().map(|n| n.at).collect()),
+            TabSource::Chords => self.scale_chords().iter().map(|chord| chord.chord(strings, self.settings.frets)).collect(),
+            TabSource::Selected => single(self.selected.clone()),
+        }
+    }
+    // the triads on each degree of the scale's mode, from the Arpeggio panel's start fret
+    fn scale_chords(&self) -> Vec<Arpeggio> {
+        let diatonic = self.scale.typ.diatonic();
+        (0..diatonic.len()).filter_map(|degree| {
+            diatonic_chord(&diatonic, degree, 3).map(|quality| Arpeggio {
+                root: (self.scale.key + diatonic[degree]) % TOTAL_TONES,
+                quality,
+                start_fret: self.arpeggio.start_fret,
+                ..Arpeggio::default()
+            })
+        }).collect()
+    }
+    fn export_tab(&self) -> String {
+        let strings = self.strings();
+        let names = tab::string_names(strings);
+        tab::export(&self.tab_run(), strings, &names)
+    }
+    fn export_musicxml(&self) -> String {
+        let instrument = self.instrument();
+        let strings = self.strings();
+        let title = match self.tab_source {
+            TabSource::Arpeggio => format!("{} {}", self.scale.get_note_letter(self.arpeggio.root), i18n::label(&self.arpeggio.quality)),
+            _ => full_name(&self.scale),
+        };
+        // open strings are named like the tab's: naturals, or flats for lowered tunings
+        let tuning = strings.iter().map(|s| {
+            let (letter, accidental) = chromatic_spelling(s % TOTAL_TONES, true);
+            Pitch { midi: instrument.midi_note(*s), letter, accidental }
+        }).collect();
+        let beats = self.tab_run().into_iter().map(|beat| {
+            beat.into_iter().filter(|at| at.string < strings.len()).map(|at| {
+                let (letter, accidental) = self.scale.spelling(at.note(strings));
+                (Pitch { midi: instrument.midi_note(at.note(strings)), letter, accidental }, at)
+            }).collect::<Vec<_>>()
+        }).filter(|beat| !beat.is_empty()).collect();
+        Score {
+            title,
+            part: instrument.name.clone(),
+            clef: instrument.clef,
+            fifths: self.scale.signature().iter().map(|(_, a)| a).sum(),
+            mode: self.scale.typ,
+            tuning,
+            beats,
+        }.to_xml()
+    }
+    // the scale notes the main fretboard shows for the current position
+    fn displayed_notes(&self) -> Vec<FretPos> {
+        let strings = self.strings();
+        match shape(self.settings.position, &self.scale, strings, self.settings.frets) {
+            Some(shape) => shape.notes.into_iter().filter(|at| at.fret <= self.settings.frets).collect(),
+            None => (0..strings.len())
+                .flat_map(|string| (0..=self.settings.frets).map(move |fret| FretPos { string, fret }))
+                .filter(|at| self.scale.is_note_in_scale(at.note(strings) as i16))
+                .collect(),
+        }
+    }
+    fn compare_bubble(&self, note:usize) -> Bubble {
+        let in_a = self.scale.is_note_in_scale(note as i16);
+        let in_b = self.compare.is_note_in_scale(note as i16);
+        let bubble = self.compare.get_bubble(self.settings.dark_mode, self.settings.note_colors, note, self.settings.note_marks);
+        match (in_a, in_b) {
+            // notes only in B are drawn hollow, spelled as B spells them
+            (false, true) => Bubble::new((Color32::TRANSPARENT, self.stroke(1f32).color), bubble.text),
+            _ => self.scale.get_bubble(self.settings.dark_mode, self.settings.note_colors, note, self.settings.note_marks),
+        }
+    }
+    fn compare_ring(&self, note:usize) -> Option<Color32> {
+        if self.open_panel != Panel::Compare {
+            return None;
+        }
+        match (self.scale.is_note_in_scale(note as i16), self.compare.is_note_in_scale(note as i16)) {
+            (true, false) => Some(ONLY_IN_A),
+            (false, true) => Some(ONLY_IN_B),
+            _ => None,
+        }
+    }
+    // the neighbouring key being previewed, with the note that leaves and the note that arrives
+    fn modulation_notes(&self) -> Option<(Scale, usize, usize)> {
+        let clockwise = match (self.open_panel, self.modulation) {
+            (Panel::Circle, Modulation::Dominant) => true,
+            (Panel::Circle, Modulation::Subdominant) => false,
+            _ => return None,
+        };
+        let (leaving, arriving) = self.scale.modulation(clockwise)?;
+        Some((self.scale.neighbour(clockwise), leaving, arriving))
+    }
+    fn fretboard_clicked(&mut self, at:FretPos, now:f64) {
+        let strings = self.strings().clone();
+        let instrument = match self.instruments.get(self.current_instrument) {
+            Some(instrument) => instrument,
+            None => &self.empty_instrument,
+        };
+        match self.open_panel {
+            Panel::Quiz => self.quiz.click(at, &strings, self.settings.frets, &self.scale),
+            Panel::Trainer => self.trainer.click(at, instrument, self.settings.frets, now),
+            Panel::Staff | Panel::Tab => match self.selected.iter().position(|s| *s == at) {
+                Some(index) => { self.selected.remove(index); },
+                None => self.selected.push(at),
+            },
+            // anywhere else a click anchors the interval overlay, and a second click clears it
+            _ => self.anchor = match self.anchor == Some(at) {
+                true => None,
+                false => Some(at),
+            },
+        }
+    }
+    // while the Tab panel holds a pasted tab, the board shows every place its notes can be played
+    fn shows_import(&self) -> bool {
+        self.open_panel == Panel::Tab && !self.imported.is_empty()
+    }
+    fn import_bubble(&self, note:usize) -> Bubble {
+        match self.imported.iter().any(|n| n.note == note) {
+            true => self.scale.get_bubble(self.settings.dark_mode, self.settings.note_colors, note, self.settings.note_marks),
+            false => Bubble::blank(),
+        }
+    }
+    fn shows_song(&self) -> bool {
+        self.open_panel == Panel::Song && self.song.song.is_some()
+    }
+    fn song_bubble(&self, note:usize) -> Bubble {
+        match self.song.counts[note % TOTAL_TONES] > 0 {
+            true => self.scale.get_bubble(self.settings.dark_mode, self.settings.note_colors, note, self.settings.note_marks),
+            false => Bubble::blank(),
+        }
+    }
+    // song notes are sized by how often the passage plays them, the most played at full size
+    fn dot_size(&self, board:&Board, note:usize) -> f32 {
+        let most = self.song.counts.iter().max().copied().unwrap_or(0);
+        match board.main && self.shows_song() && most > 0 {
+            true => self.settings.dot_size * (0.45 + 0.55 * self.song.counts[note % TOTAL_TONES] as f32 / most as f32),
+            false => self.settings.dot_size,
+        }
+    }
+    fn shows_arpeggio(&self) -> bool {
+        self.open_panel == Panel::Arpeggio || (self.open_panel == Panel::Tab && self.tab_source == TabSource::Arpeggio && self.imported.is_empty())
+    }
+    // panels where clicking the fretboard picks a run of notes
+    fn picks_notes(&self) -> bool {
+        self.open_panel == Panel::Staff || self.open_panel == Panel::Tab
+    }
+    fn shows_fingers(&self, position:Position) -> bool {
+        self.settings.note_marks == NoteMarker::Fingering || matches!(position, Position::ThreeNps(_))
+    }
+    fn bubble_at(&self, board:&Board, at:FretPos, note:usize, shape:Option<&Shape>) -> Bubble {
+        if board.main {
+            if let Some(bubble) = self.overlay_bubble(at, note, shape) {
+                return bubble;
+            }
+        }
+        let bubble = board.scale.get_bubble(self.settings.dark_mode, self.settings.note_colors, note, self.settings.note_marks);
+        match shape {
+            Some(shape) if shape.is_ghost(at) => Bubble::new((bubble.color.gamma_multiply(0.3), bubble.text_color.gamma_multiply(0.3)), bubble.text),
+            Some(shape) if !shape.contains(at) => Bubble::blank(),
+            Some(shape) => match shape.finger(at).filter(|_| self.shows_fingers(board.position)) {
+                Some(finger) => Bubble::new((bubble.color, bubble.text_color), finger.label()),
+                None => bubble,
+            },
+            None => bubble,
+        }
+    }
+    // what the open panel draws over the main fretboard in place of the scale
+    fn overlay_bubble(&self, at:FretPos, note:usize, shape:Option<&Shape>) -> Option<Bubble> {
+        if self.open_panel == Panel::Quiz && self.quiz.is_running() {
+            return Some(self.quiz_bubble(at));
+        }
+        if self.open_panel == Panel::Trainer && (self.trainer.heat_map || self.trainer.prompt.is_some()) {
+            return Some(self.trainer_bubble(at));
+        }
+        if let Some(anchor) = self.anchor.filter(|a| a.string < self.strings().len()) {
+            return Some(self.interval_bubble(anchor, note));
+        }
+        if self.shows_import() {
+            return Some(self.import_bubble(note));
+        }
+        if self.shows_song() {
+            return Some(self.song_bubble(note));
+        }
+        if self.shows_arpeggio() {
+            return Some(self.arpeggio_bubble(at, note, shape));
+        }
+        if self.open_panel == Panel::Compare {
+            return Some(self.compare_bubble(note));
+        }
+        let (neighbour, _, arriving) = self.modulation_notes().filter(|m| note % TOTAL_TONES == m.2)?;
+        let colors = Scale::color_lookup(self.settings.note_colors, self.settings.dark_mode, NoteType::NotInScale, note / TOTAL_TONES);
+        Some(Bubble::new(colors, neighbour.get_note_letter(arriving)))
+    }
+    fn interval_bubble(&self, anchor:FretPos, note:usize) -> Bubble {
+        let from = anchor.note(self.strings());
+        let semitones = note.abs_diff(from);
+        let colors = Scale::color_lookup(self.settings.note_colors, self.settings.dark_mode, interval_type(semitones), note / TOTAL_TONES);
+        let text = match note.cmp(&from) {
+            std::cmp::Ordering::Equal => self.scale.get_note_letter(note),
+            std::cmp::Ordering::Greater => compound_interval_name(semitones),
+            std::cmp::Ordering::Less => format!("↓{}", compound_interval_name(semitones)),
+        };
+        Bubble::new(colors, text)
+    }
+    // the arpeggio's run, leaving out any notes past the last fret shown
+    fn arpeggio_notes(&self) -> Vec<ArpNote> {
+        self.arpeggio.notes(self.strings(), self.settings.frets).into_iter()
+            .filter(|n| n.at.fret <= self.settings.frets)
+            .collect()
+    }
+    fn arpeggio_bubble(&self, at:FretPos, note:usize, shape:Option<&Shape>) -> Bubble {
+        let Some(shape) = shape.filter(|s| s.contains(at)) else {
+            return Bubble::blank();
+        };
+        let interval = (note + TOTAL_TONES - self.arpeggio.root % TOTAL_TONES) % TOTAL_TONES;
+        let colors = Scale::color_lookup(self.settings.note_colors, self.settings.dark_mode, chord_note_type(interval), note / TOTAL_TONES);
+        let text = match self.settings.note_marks {
+            NoteMarker::Numbers => chord_degree(interval).to_string(),
+            NoteMarker::Debug => note.to_string(),
+            NoteMarker::Fingering => shape.finger(at).map(|f| f.label()).unwrap_or_default(),
+            NoteMarker::AllNotes | NoteMarker::Letters => self.scale.get_note_letter(note),
+        };
+        Bubble::new(colors, text)
+    }
+    fn trainer_bubble(&self, at:FretPos) -> Bubble {
+        if self.trainer.heat_map {
+            return match self.trainer.card(self.instrument(), at) {
+                Some(card) => Bubble::new((heat_color(card.mastery()), Color32::BLACK), String::new()),
+                None => Bubble::new(Scale::color_lookup(NoteColors::Monochrome, self.settings.dark_mode, NoteType::Root, 0), String::new()),
+            };
+        }
+        let colors = Scale::color_lookup(NoteColors::Monochrome, self.settings.dark_mode, NoteType::Root, 0);
+        match self.trainer.prompt {
+            Some(Prompt::NameNote { at: q }) if q == at => Bubble::new(colors, "?".to_string()),
+            Some(Prompt::FindOnString { at: q }) if q.string == at.string => Bubble::new(colors, String::new()),
+            _ => Bubble::blank(),
+        }
+    }
+    fn quiz_bubble(&self, at:FretPos) -> Bubble {
+        let colors = Scale::color_lookup(NoteColors::Monochrome, self.settings.dark_mode, NoteType::Root, 0);
+        let strings = self.strings();
+        let text = match &self.quiz.question {
+            Some(Question::NoteName { at: q }) | Some(Question::ScaleDegree { at: q }) if *q == at => "?".to_string(),
+            Some(Question::Interval { from, .. }) if *from == at => self.scale.get_note_letter(from.note(strings)),
+            Some(Question::Interval { to, .. }) if *to == at => "?".to_string(),
+            Some(Question::FindAll { found, .. }) if found.contains(&at) => self.scale.get_note_letter(at.note(strings)),
+            // the chord's shape, with its root on the lowest string marked when the quality is asked
+            Some(Question::Chord { notes, ask }) if notes.contains(&at) => match (ask, notes.first() == Some(&at)) {
+                (ChordAsk::Quality, true) => "R".to_string(),
+                _ => String::new(),
+            },
+            _ => return Bubble::blank(),
+        };
+        Bubble::new(colors, text)
+    }
+    fn draw_panel_fretboard(&mut self, ctx: &egui::Context) -> Option<usize> {
+        let mut painter = Painter::new(
+            ctx.clone(),
+            LayerId {
+                id: Id::new("shapes_layer"),
+                order: Order::Background,
+            },
+            ctx.available_rect(),
+        );
+        let mut clicked = None;
+        let mut hovered = None;
+        egui::CentralPanel::default().show(ctx, |ui|{
+            let area = ui.available_rect_before_wrap();
+            let highlight = ui.visuals().selection.bg_fill;
+            let boards = self.paint_fretboard(&mut painter, area, area, Some(highlight));
+            for (index, (rect, dots)) in boards.iter().enumerate() {
+                let response = ui.interact(*rect, Id::new(("fretboard_clicks", index)), Sense::click());
+                let near = |p:Pos2| dots.iter().find(|d| p.distance(d.pos) <= self.settings.dot_size);
+                // only the main fretboard takes clicks
+                if index == 0 && response.clicked() {
+                    clicked = response.interact_pointer_pos().and_then(near).map(|d| d.at);
+                }
+                hovered = hovered.or(response.hover_pos().and_then(near).map(|d| d.midi));
+            }
+        });
+        if let Some(at) = clicked {
+            self.fretboard_clicked(at, ctx.input(|i| i.time));
+        }
+        hovered
+    }
+    // every board, its name and the legend in the corner of legend_area, returning each board's rect
+    // and where it drew its notes; highlight rings the note under the pointer and is left out of exports
+    fn paint_fretboard(&self, canvas:&mut dyn Canvas, area:Rect, legend_area:Rect, highlight:Option<Color32>) -> Vec<(Rect, Vec<Dot>)> {
+        let boards = self.boards();
+        let rects = self.board_rects(area, boards.len());
+        let mut drawn = vec![];
+        for (board, rect) in boards.iter().zip(rects) {
+            // the open strings' notes sit on the board's edge, so they're let over it
+            canvas.clip(Some(rect.expand(self.settings.dot_size + 6f32)));
+            drawn.push((rect, self.paint_board(canvas, rect, board, highlight)));
+            canvas.clip(None);
+            if boards.len() > 1 {
+                let name = format!("{} · {}", board.instrument.name, board.instrument.tuning().name());
+                canvas.text(rect.left_top() + Vec2::new(4f32, 2f32), Align2::LEFT_TOP, &name, font(12f32, FontFamily::Proportional), self.stroke(1f32).color);
+            }
+        }
+
+        // paint legend
+        if self.settings.show_legend {
+            match self.open_panel {
+                Panel::Compare => self.draw_compare_legend(legend_area, canvas),
+                _ => self.draw_legend(legend_area, canvas),
+            }
+        }
+        drawn
+    }
+    // the diagram sized to fit its boards rather than the window, with the legend below a horizontal neck
+    // or beside a vertical one; returns the whole size and the boards' area
+    fn diagram_layout(&self) -> (Vec2, Rect) {
+        let boards = self.boards();
+        let strings = boards.iter().map(|b| b.instrument.strings().len()).max().unwrap_or(0);
+        // the neck with a margin, and the strings with room for the fret markers either side
+        let along = 20f32 + (self.settings.frets + 1) as f32 * self.settings.space_fret;
+        let across = strings as f32 * self.settings.space_string + 60f32;
+        let (columns, rows) = self.board_grid(boards.len());
+        let board = match self.settings.vertical {
+            true => Vec2::new(across, along),
+            false => Vec2::new(along, across),
+        };
+        let area = Rect::from_min_size(Pos2::ZERO, Vec2::new(board.x * columns as f32, board.y * rows as f32));
+        let legend = match self.settings.show_legend {
+            true => self.legend_size() + Vec2::splat(20f32),
+            false => Vec2::ZERO,
+        };
+        let size = match self.settings.vertical {
+            true => Vec2::new(area.width() + legend.x, area.height().max(legend.y)),
+            false => Vec2::new(area.width().max(legend.x), area.height() + legend.y),
+        };
+        (size, area)
+    }
+    fn background(&self) -> Color32 {
+        match self.settings.dark_mode {
+            true => Visuals::dark().panel_fill,
+            false => Visuals::light().panel_fill,
+        }
+    }
+    pub fn export_svg(&self) -> String {
+        let (size, area) = self.diagram_layout();
+        let diagram = Rect::from_min_size(Pos2::ZERO, size);
+        let mut svg = Svg::new(diagram.expand(self.settings.dot_size + 6f32), self.background());
+        self.paint_fretboard(&mut svg, area, diagram, None);
+        svg.finish()
+    }
+    // the same picture as the SVG, png_scale pixels to a point
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_png(&self) -> Result<Vec<u8>, String> {
+        let (size, area) = self.diagram_layout();
+        let diagram = Rect::from_min_size(Pos2::ZERO, size);
+        let viewport = diagram.expand(self.settings.dot_size + 6f32);
+        let pixels = (viewport.size() * self.png_scale).round();
+        let mut raster = Raster::new(pixels.x as usize, pixels.y as usize, self.background(), raster_fonts());
+        raster.fit(viewport, Rect::from_min_size(Pos2::ZERO, pixels));
+        self.paint_fretboard(&mut raster, area, diagram, None);
+        raster.png()
+    }
+    // the scale in all twelve keys, per_page to an A4 page with each key's name over its diagram,
+    // printed light whatever the theme
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_worksheet(&mut self, per_page:usize) -> Vec<u8> {
+        // 150 dots to the inch
+        let dots = 150f32 / 72f32;
+        let margin = 36f32;
+        let per_page = per_page.max(1);
+        let columns = match per_page >= 4 {
+            true => 2,
+            false => 1,
+        };
+        let cell = Vec2::new(PAGE_WIDTH - 2f32 * margin, PAGE_HEIGHT - 2f32 * margin) / Vec2::new(columns as f32, per_page.div_ceil(columns) as f32);
+        let page = Rect::from_min_size(Pos2::ZERO, Vec2::new(PAGE_WIDTH, PAGE_HEIGHT));
+        let (size, area) = self.diagram_layout();
+        let diagram = Rect::from_min_size(Pos2::ZERO, size);
+        let viewport = diagram.expand(self.settings.dot_size + 6f32);
+        let fonts = raster_fonts();
+        let (key, letter, dark_mode) = (self.scale.key, self.scale.key_letter, self.settings.dark_mode);
+        self.settings.dark_mode = false;
+        let mut pdf = Pdf::default();
+        let all_keys: Vec<usize> = (0..TOTAL_TONES).collect();
+        for page_keys in all_keys.chunks(per_page) {
+            let pixels = (page.size() * dots).round();
+            let mut raster = Raster::new(pixels.x as usize, pixels.y as usize, Color32::WHITE, fonts.clone());
+            for (i, k) in page_keys.iter().enumerate() {
+                self.scale.key = *k;
+                self.scale.key_letter = key_letter(self.scale.typ, *k);
+                let at = Pos2::new(margin + (i % columns) as f32 * cell.x, margin + (i / columns) as f32 * cell.y);
+                let cell = Rect::from_min_size(at, cell).shrink(6f32);
+                raster.fit(page, Rect::from_min_size(Pos2::ZERO, pixels));
+                raster.text(cell.center_top(), Align2::CENTER_TOP, &full_name(&self.scale), font(14f32, FontFamily::Proportional), Color32::BLACK);
+                let below_title = Rect::from_min_max(cell.min + Vec2::new(0f32, 24f32), cell.max);
+                raster.fit(viewport, Rect::from_min_max(below_title.min * dots, below_title.max * dots));
+                self.paint_fretboard(&mut raster, area, diagram, None);
+            }
+            pdf.add_page(&raster);
+        }
+        self.scale.key = key;
+        self.scale.key_letter = letter;
+        self.settings.dark_mode = dark_mode;
+        pdf.finish()
+    }
+    // write to the path typed in, with the extension of what's written
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_export(&mut self, extension:&str, contents:Result<Vec<u8>, String>) {
+        let path = std::path::Path::new(&self.export_path).with_extension(extension);
+        let name = path.display().to_string();
+        match contents.and_then(|c| std::fs::write(&path, c).map_err(|e| e.to_string())) {
+            Ok(()) => self.toasts.info(trf("export.saved", &[&name])),
+            Err(e) => self.toasts.error(trf("error.write", &[&name, &e])),
+        };
+    }
+    fn draw_panel_export(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("Export")
+        .resizable(false)
+        .default_width(240.0)
+        .show(ctx, |ui|{
+            ui.add_space(5.0);
+            ui.heading(tr("export.title"));
+            ui.add_space(14f32);
+            ui.label(tr("export.hint"));
+            ui.add_space(10.0);
+            if ui.button(tr("export.copy_svg")).clicked() {
+                ctx.copy_text(self.export_svg());
+                self.toasts.info(tr("export.copied"));
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.add_space(10.0);
+                ui.add(egui::TextEdit::singleline(&mut self.export_path).hint_text("path/to/fretboard").desired_width(150.0));
+                ui.horizontal(|ui|{
+                    if ui.button(tr("export.save_svg")).clicked() {
+                        let svg = self.export_svg();
+                        self.save_export("svg", Ok(svg.into_bytes()));
+                    }
+                    if ui.button(tr("export.save_png")).clicked() {
+                        let png = self.export_png();
+                        self.save_export("png", png);
+                    }
+                });
+                ui.horizontal(|ui|{
+                    ui.label(tr("export.resolution"));
+                    ui.add(egui::DragValue::new(&mut self.png_scale).range(1f32..=4f32).speed(0.1).suffix("×"));
+                });
+                ui.add_space(14f32);
+                ui.label(RichText::new(tr("export.worksheet")).strong());
+                ui.label(tr("export.worksheet_hint"));
+                ui.horizontal(|ui|{
+                    ui.label(tr("export.per_page"));
+                    ui.add(egui::DragValue::new(&mut self.sheet_per_page).range(1..=6));
+                });
+                if ui.button(tr("export.save_pdf")).clicked() {
+                    let pdf = self.export_worksheet(self.sheet_per_page);
+                    self.save_export("pdf", Ok(pdf));
+                }
+            }
+        });
+    }
+    // the main fretboard first, then one per extra view
+    fn boards(&self) -> Vec<Board<'_>> {
+        let main = Board {
+            instrument: self.instrument().clone(),
+            scale: &self.scale,
+            position: self.settings.position,
+            main: true,
+        };
+        let views = self.views.iter().filter_map(|view| {
+            let instrument = self.instruments.get(view.instrument).filter(|i| view.tune_index < i.tunings.len())?;
+            Some(Board {
+                instrument: Instrument { tune_index: view.tune_index, ..instrument.clone() },
+                scale: view.scale.as_ref().unwrap_or(&self.scale),
+                position: view.position,
+                main: false,
+            })
+        });
+        std::iter::once(main).chain(views).collect()
+    }
+    // columns and rows of boards
+    fn board_grid(&self, count:usize) -> (usize, usize) {
+        match (self.settings.layout, self.settings.vertical) {
+            (ViewLayout::Tiled, _) => {
+                let columns = (count as f32).sqrt().ceil() as usize;
+                (columns, count.div_ceil(columns))
+            },
+            (ViewLayout::Stacked, true) => (count, 1),
+            (ViewLayout::Stacked, false) => (1, count),
+        }
+    }
+    fn board_rects(&self, area:Rect, count:usize) -> Vec<Rect> {
+        let (columns, rows) = self.board_grid(count);
+        let size = Vec2::new(area.width() / columns as f32, area.height() / rows as f32);
+        (0..count)
+            .map(|i| Rect::from_min_size(area.min + Vec2::new((i % columns) as f32 * size.x, (i / columns) as f32 * size.y), size))
+            .collect()
+    }
+    fn paint_board(&self, canvas:&mut dyn Canvas, rect:Rect, board:&Board, highlight:Option<Color32>) -> Vec<Dot> {
+        let strings = board.instrument.strings();
+        let num_frets = self.settings.frets + 1;
+
+        // the "width" of the fret board
+        let fretboard_size = (strings.len() as f32) * self.settings.space_string;
+        let half_size = fretboard_size/2f32;
+
+        let offset:f32 = 10f32 + match self.settings.vertical {
+            true => rect.top(),
+            false => rect.left(),
+        };
+        let center = match self.settings.vertical {
+            false => rect.center().y,
+            true => rect.center().x,
+        };
+        // fret marker positions
+        let gap_to_fret_markers = 10f32;
+        let d_fret_marker1 = center - half_size as f32 - gap_to_fret_markers;
+        let d_fret_marker2 = center + half_size as f32 + gap_to_fret_markers;
+
+        // paint frets and fret-markers
+        for fret in 0..num_frets {
+            let mut pos_down_neck = fret as f32 * self.settings.space_fret + offset;
+            let pos_fret_marker1 = match self.settings.vertical {
+                true => Pos2 { x: d_fret_marker1, y: pos_down_neck },
+                false => Pos2 { x: pos_down_neck, y: d_fret_marker1 },
+            };
+            let pos_fret_marker2 = match self.settings.vertical {
+                true => Pos2 { x: d_fret_marker2, y: pos_down_neck },
+                false => Pos2 { x: pos_down_neck, y: d_fret_marker2 },
+            };
+            self.draw_fret_marker(fret, canvas, pos_fret_marker1);
+            self.draw_fret_marker(fret, canvas, pos_fret_marker2);
+            pos_down_neck += self.settings.space_fret.div(2f32);
+            self.draw_fret(canvas, fret, pos_down_neck, center, half_size);
+        }
+        // the panels' overlays only apply to the main fretboard
+        let heard = self.listener.heard.map(|h| h.midi).filter(|_| board.main);
+        let modulation = self.modulation_notes().filter(|_| board.main);
+        let tuning = match self.open_panel {
+            Panel::Tuner if board.main => self.tuner_reading(),
+            _ => None,
+        };
+        let arpeggio = match self.open_panel {
+            _ if board.main && self.shows_arpeggio() => self.arpeggio_notes(),
+            _ => vec![],
+        };
+        let mut shape = match self.open_panel {
+            _ if board.main && self.shows_arpeggio() => Some(Shape {
+                notes: arpeggio.iter().map(|n| n.at).collect(),
+                ghost: vec![],
+                fingers: vec![],
+            }),
+            _ => shape(board.position, board.scale, strings, self.settings.frets),
+        };
+        if let Some(shape) = shape.as_mut().filter(|s| s.fingers.is_empty() && self.shows_fingers(board.position)) {
+            shape.fingers = fingering::assign(&shape.notes, strings, board.instrument.fingering);
+        }
+        let vertical = self.settings.vertical;
+        let num_strings = strings.len();
+        let string_middle = |string_index:usize| {
+            let i = match vertical {
+                true => string_index,
+                false => num_strings - string_index - 1,
+            };
+            center - half_size + (i as f32 + 0.5) * self.settings.space_string
+        };
+        let pos_of = |at:FretPos| match vertical {
+            false => Pos2 { x: offset + at.fret as f32 * self.settings.space_fret, y: string_middle(at.string) },
+            true => Pos2 { x: string_middle(at.string), y: offset + at.fret as f32 * self.settings.space_fret },
+        };
+        // paint strings
+        for i in 0..num_strings {
+            let cell_pree :f32= center - half_size + (i as f32 * self.settings.space_string);
+            let cell_post :f32= cell_pree + self.settings.space_string;
+            let cell_middle = (cell_pree + cell_post)/2f32;
+
+            // draw horizontal line (string):
+            match self.settings.string_style {
+                StringStyle::String => {
+                    self.draw_line(canvas, rect, cell_middle);
+                },
+                StringStyle::Cells => {
+                    self.draw_line(canvas, rect, cell_pree);
+                    self.draw_line(canvas, rect, cell_post);
+                },
+            };
+        }
+        // paint the arpeggio path under the notes
+        for pair in arpeggio.windows(2) {
+            canvas.line(pos_of(pair[0].at), pos_of(pair[1].at), Stroke::new(4f32, self.stroke(1f32).color.gamma_multiply(0.6)));
+        }
+        // paint notes:
+        let mut dots = vec![];
+        for (string_index, &string) in strings.iter().enumerate() {
+            for fret in 0..(num_frets) {
+                let at = FretPos { string: string_index, fret };
+                let b = self.bubble_at(board, at, string + fret, shape.as_ref());
+                let pos = pos_of(at);
+                let midi = board.instrument.midi_note(string + fret);
+                dots.push(Dot { at, pos, midi });
+                canvas.circle(pos, self.dot_size(board, string + fret), b.color, Stroke::NONE);
+                canvas.text(pos, Align2::CENTER_CENTER, &b.text, font_glyph(), b.text_color);
+                if let Some(color) = highlight.filter(|_| self.hovered == Some(midi)) {
+                    canvas.circle(pos, self.settings.dot_size + 2f32, Color32::TRANSPARENT, Stroke::new(2f32, color));
+                }
+                let in_tab = self.shows_import() && self.imported.iter().any(|n| n.at == Some(at));
+                if board.main && (self.anchor == Some(at) || in_tab || (self.picks_notes() && self.selected.contains(&at))) {
+                    canvas.circle(pos, self.settings.dot_size + 4f32, Color32::TRANSPARENT, self.stroke(3f32));
+                }
+                if let Some(color) = self.compare_ring(string + fret).filter(|_| board.main) {
+                    canvas.circle(pos, self.settings.dot_size, Color32::TRANSPARENT, Stroke::new(3f32, color));
+                }
+                // the note the modulation drops is ringed red, the one it brings in green
+                if let Some((_, leaving, arriving)) = modulation {
+                    let note = (string + fret) % TOTAL_TONES;
+                    if note == leaving {
+                        canvas.circle(pos, self.settings.dot_size + 4f32, Color32::TRANSPARENT, Stroke::new(3f32, Color32::RED));
+                    } else if note == arriving {
+                        canvas.circle(pos, self.settings.dot_size + 4f32, Color32::TRANSPARENT, Stroke::new(3f32, Color32::GREEN));
+                    }
+                }
+                if let Some(reading) = tuning.filter(|r| fret == 0 && r.string == string_index) {
+                    let color = match reading.in_tune() {
+                        true => Color32::GREEN,
+                        false => Color32::RED,
+                    };
+                    canvas.circle(pos, self.settings.dot_size + 4f32, Color32::TRANSPARENT, Stroke::new(3f32, color));
+                } else if tuning.is_none() && heard == Some(board.instrument.midi_note(string + fret)) {
+                    let color = match board.scale.is_note_in_scale((string + fret) as i16) {
+                        true => Color32::GREEN,
+                        false => Color32::RED,
+                    };
+                    canvas.circle(pos, self.settings.dot_size + 4f32, Color32::TRANSPARENT, Stroke::new(3f32, color));
+                }
+            }
+        }
+        // picking hints next to each arpeggio note
+        for note in arpeggio.iter() {
+            let pos = pos_of(note.at) + Vec2::new(self.settings.dot_size, -self.settings.dot_size);
+            canvas.text(pos, Align2::CENTER_CENTER, note.stroke.symbol(), font(12f32, FontFamily::Proportional), self.stroke(1f32).color);
+        }
+        dots
+    }
+    // a keyboard across every pitch the main instrument can play, returning the MIDI note under the pointer
+    fn draw_panel_keyboard(&self, ctx: &egui::Context) -> Option<usize> {
+        let mut hovered = None;
+        egui::TopBottomPanel::bottom("keyboard")
+        .resizable(false)
+        .show(ctx, |ui|{
+            let (response, painter) = ui.allocate_painter(Vec2::new(ui.available_width(), 110f32), Sense::hover());
+            let strings = self.strings();
+            let (Some(low), Some(high)) = (strings.iter().min(), strings.iter().max()) else {
+                return;
+            };
+            let instrument = self.instrument();
+            let keys = keyboard::layout(response.rect.shrink(5f32), *low, high + self.settings.frets);
+            hovered = response.hover_pos().and_then(|p| keyboard::key_at(&keys, p)).map(|n| instrument.midi_note(n));
+            for key in keys.iter() {
+                let fill = match (self.hovered == Some(instrument.midi_note(key.note)), key.black) {
+                    (true, _) => ui.visuals().selection.bg_fill,
+                    (false, true) => Color32::BLACK,
+                    (false, false) => Color32::WHITE,
+                };
+                painter.rect(key.rect, CornerRadius::same(2u8), fill, Stroke::new(1f32, Color32::DARK_GRAY), StrokeKind::Inside);
+                // the same bubble as the fretboard, at the bottom of the key
+                let b = self.scale.get_bubble(self.settings.dark_mode, self.settings.note_colors, key.note, self.settings.note_marks);
+                let size = (key.rect.width() * 0.45).min(self.settings.dot_size);
+                let pos = Pos2::new(key.rect.center().x, key.rect.bottom() - size - 3f32);
+                if b.color != Color32::TRANSPARENT {
+                    painter.circle(pos, size, b.color, Stroke::new(1f32, Color32::DARK_GRAY));
+                    painter.text(pos, Align2::CENTER_CENTER, b.text, font(size, FontFamily::Proportional), b.text_color);
+                }
+            }
+        });
+        hovered
+    }
+    fn draw_line(&self, canvas:&mut dyn Canvas, rect:Rect, pos:f32){
+        let [from, to] = match self.settings.vertical {
+            false => [
+                Pos2::new(rect.left(), pos),
+                Pos2::new(rect.right(), pos),
+            ],
+            true => [
+                Pos2::new(pos, rect.top()),
+                Pos2::new(pos, rect.bottom()),
+            ],
+        };
+        canvas.line(from, to, self.stroke(1f32));
+    }
+    fn draw_fret(&self, canvas:&mut dyn Canvas, fret:usize, offset:f32, center:f32, half_width:f32) {
+        let [from, to] = match self.settings.vertical {
+            true => [
+                Pos2 {
+                    x: center - half_width,
+                    y: offset,
+                },
+                Pos2 {
+                    x: center + half_width,
+                    y: offset,
+                },
+            ],
+            false => [
+                Pos2 {
+                    x: offset,
+                    y: center - half_width,
+                },
+                Pos2 {
+                    x: offset,
+                    y: center + half_width,
+                },
+            ],
+        };
+        canvas.line(from, to, self.stroke(match fret {
+            0 => 3f32,
+            _ => 1f32,
+        }));
+    }
+    fn draw_fret_marker(&self, fret:usize, canvas:&mut dyn Canvas, mut pos:Pos2){
+        let is_octave = fret % 12 == 0;
+        let mut draw_dot = |p|{
+            canvas.circle(p, 3f32, match self.settings.dark_mode {
+                false => Color32::BLACK,
+                true => Color32::WHITE,
+            }, Stroke::NONE);
+        };
+        match match fret.try_into().unwrap_or(0) {
+            3|5|7|9|12|15|17|19 => self.settings.fret_marks,
+            _ => FretMarker::None,
+        } {
+            FretMarker::None => {},
+            FretMarker::Dots => {
+                match is_octave {
+                    false => draw_dot(pos),
+                    true => {
+                        pos.x += 4.0;
+                        draw_dot(pos);
+                        pos.x -= 8.0;
+                        draw_dot(pos);
+                    }
+                }
+            },
+            FretMarker::Numbers => {
+                canvas.text(
+                    pos,
+                    Align2::CENTER_CENTER,
+                    &(fret).to_string(),
+                    font(12f32, FontFamily::Monospace),
+                    match is_octave {
+                        false => match self.settings.dark_mode { true => Color32::WHITE, false => Color32::BLACK },
+                        true => match self.settings.dark_mode { true => Color32::GOLD, false => Color32::BLUE },
+                    }
+                );
+            }
+        }
+    }
+    fn legend_size(&self) -> Vec2 {
+        match self.open_panel {
+            Panel::Compare => Vec2::new(340f32, 3f32 * 35f32 + 15f32),
+            _ => Vec2::new(250f32, 5f32 * 35f32 + 15f32),
+        }
+    }
+    fn draw_legend(&self, rect:Rect, canvas:&mut dyn Canvas){
+        let m = 10f32;
+        let Vec2 { x: w, y: h } = self.legend_size();
+        let bg = Rect {
+            min: Pos2 { x: rect.right() - m - w, y: rect.bottom() - m - h },
+            max: Pos2 { x: rect.right() - m, y: rect.bottom() - m },
+        };
+        canvas.rect(bg, 4f32, match self.settings.dark_mode {
+            true => Color32::DARK_GRAY,
+            false => Color32::LIGHT_GRAY,
+        }, self.stroke(1f32));
+        let mut draw_dot = |x:f32, y:f32, n, str:String|{
+            let pos = Pos2 { x: bg.min.x + x, y: bg.min.y + y };
+            let b = self.scale.get_bubble_from(self.settings.note_colors, self.settings.dark_mode, n, self.settings.note_marks);
+            canvas.circle(pos, self.settings.dot_size, b.color, Stroke::NONE);
+            canvas.text(pos, Align2::CENTER_CENTER, &b.text, font_glyph(), b.text_color);
+            canvas.text(
+                Pos2 { x: pos.x + 30f32, y: pos.y}, 
+                Align2::LEFT_CENTER, 
+                &str,
+                font(14f32, FontFamily::Monospace),
+                match self.settings.dark_mode { true => Color32::WHITE, false => Color32::BLACK }
+            );
+        };
+        draw_dot(25f32, 25f32, NoteType::Root, tr("legend.root"));
+        draw_dot(25f32, 60f32, NoteType::Triad, tr("legend.triad"));
+        draw_dot(25f32, 95f32, NoteType::InPentatonic, tr("legend.pentatonic"));
+        draw_dot(25f32, 130f32, NoteType::Blue, tr("legend.blue"));
+        draw_dot(25f32, 165f32, NoteType::InDiatonic, tr("legend.natural"));
+
+    }
+    fn draw_compare_legend(&self, rect:Rect, canvas:&mut dyn Canvas){
+        let m = 10f32;
+        let Vec2 { x: w, y: h } = self.legend_size();
+        let bg = Rect {
+            min: Pos2 { x: rect.right() - m - w, y: rect.bottom() - m - h },
+            max: Pos2 { x: rect.right() - m, y: rect.bottom() - m },
+        };
+        canvas.rect(bg, 4f32, match self.settings.dark_mode {
+            true => Color32::DARK_GRAY,
+            false => Color32::LIGHT_GRAY,
+        }, self.stroke(1f32));
+        let root = self.scale.get_bubble_from(self.settings.note_colors, self.settings.dark_mode, NoteType::Root, NoteMarker::Letters);
+        let mut draw_dot = |y:f32, fill:Color32, ring:Option<Color32>, str:String|{
+            let pos = Pos2 { x: bg.min.x + 25f32, y: bg.min.y + y };
+            canvas.circle(pos, self.settings.dot_size, fill, Stroke::NONE);
+            if let Some(ring) = ring {
+                canvas.circle(pos, self.settings.dot_size, Color32::TRANSPARENT, Stroke::new(3f32, ring));
+            }
+            canvas.text(
+                Pos2 { x: pos.x + 30f32, y: pos.y},
+                Align2::LEFT_CENTER,
+                &str,
+                font(14f32, FontFamily::Monospace),
+                match self.settings.dark_mode { true => Color32::WHITE, false => Color32::BLACK }
+            );
+        };
+        draw_dot(25f32, root.color, None, tr("compare.in_both"));
+        draw_dot(60f32, root.color, Some(ONLY_IN_A), trf("compare.only", &[&full_name(&self.scale)]));
+        draw_dot(95f32, Color32::TRANSPARENT, Some(ONLY_IN_B), trf("compare.only", &[&full_name(&self.compare)]));
+    }
+    fn stroke(&self, weight:f32) -> Stroke {
+        Stroke::new(weight, match self.settings.dark_mode {
+            false => Color32::BLACK,
             true => Color32::WHITE,
         })
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::tests::parse;
+
+    fn app(vertical: bool) -> FretboardApp {
+        let scale = Scale { typ: ScaleType::Major, siz: ScaleSize::Diatonic, key: 3, key_letter: 2, numbering: Numbering::Simple, naming: Naming::English };
+        FretboardApp::headless(scale, 0, 0, 12, vertical, NoteMarker::Letters)
+    }
+    fn size(svg: &str) -> (String, String) {
+        let root = &parse(svg)[0];
+        (root.attr("width").unwrap().to_string(), root.attr("height").unwrap().to_string())
+    }
+
+    #[test]
+    fn png_is_drawn_at_png_scale() {
+        let app = app(true);
+        let (size, _) = app.diagram_layout();
+        let pixels = ((size + Vec2::splat(2f32 * (app.settings.dot_size + 6f32))) * app.png_scale).round();
+        let png = app.export_png().unwrap();
+        let reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (pixels.x as u32, pixels.y as u32));
+    }
+    #[test]
+    fn worksheets_have_every_key() {
+        let mut app = app(true);
+        for per_page in [1, 4, 5, 6] {
+            assert_eq!(crate::pdf::tests::pages(&app.export_worksheet(per_page)), TOTAL_TONES.div_ceil(per_page), "{} per page", per_page);
+        }
+    }
+    #[test]
+    fn worksheets_put_the_scale_and_theme_back() {
+        let mut app = app(false);
+        app.settings.dark_mode = true;
+        app.export_worksheet(6);
+        assert_eq!((app.scale.key, app.scale.key_letter), (3, 2));
+        assert!(app.settings.dark_mode);
+    }
+    #[test]
+    fn svg_is_well_formed() {
+        for vertical in [true, false] {
+            let tags = parse(&app(vertical).export_svg());
+            assert!(tags.iter().any(|t| t.name == "circle"));
+            // E♭ major spelled with flats, drawn as text
+            assert!(tags.iter().any(|t| t.name == "text" && t.text == "E♭"));
+        }
+    }
+    #[test]
+    fn svg_turns_with_the_neck() {
+        // the legend goes beside a vertical neck and under a horizontal one, so leave it out
+        let svg = |vertical| {
+            let mut app = app(vertical);
+            app.settings.show_legend = false;
+            app.export_svg()
+        };
+        let (width, height) = size(&svg(true));
+        assert!(width.parse::<f32>().unwrap() < height.parse::<f32>().unwrap());
+        assert_eq!(size(&svg(false)), (height, width));
+    }
+    #[test]
+    fn svg_background_follows_the_theme() {
+        let background = |dark_mode| {
+            let mut app = app(true);
+            app.settings.dark_mode = dark_mode;
+            parse(&app.export_svg())[1].attr("fill").unwrap().to_string()
+        };
+        let [r, g, b, _] = Visuals::dark().panel_fill.to_srgba_unmultiplied();
+        assert_eq!(background(true), format!("#{:02x}{:02x}{:02x}", r, g, b));
+        assert_ne!(background(true), background(false));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+pub const STORAGE_KEY: &str = "language";
+
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Language {
+    English,
+    Spanish,
+    German,
+    French,
+    Japanese,
+}
+// one catalog per language, in the same order as the enum
+const CATALOGS: [&str; 5] = [
+    include_str!("../locales/en.txt"),
+    include_str!("../locales/es.txt"),
+    include_str!("../locales/de.txt"),
+    include_str!("../locales/fr.txt"),
+    include_str!("../locales/ja.txt"),
+];
+static LANGUAGE: AtomicUsize = AtomicUsize::new(0);
+static MESSAGES: OnceLock<Vec<HashMap<&str, &str>>> = OnceLock::new();
+
+impl Language {
+    // languages are always listed in their own name
+    pub fn native_name(&self) -> &str {
+        match self {
+            Language::English => "English",
+            Language::Spanish => "Español",
+            Language::German => "Deutsch",
+            Language::French => "Français",
+            Language::Japanese => "日本語",
+        }
+    }
+}
+pub fn language() -> Language {
+    Language::iter().nth(LANGUAGE.load(Ordering::Relaxed)).unwrap_or(Language::English)
+}
+pub fn set_language(language: Language) {
+    let index = Language::iter().position(|l| l == language).unwrap_or(0);
+    LANGUAGE.store(index, Ordering::Relaxed);
+}
+// `key = value` lines; blank lines and lines starting with # are skipped
+fn parse(catalog: &str) -> HashMap<&str, &str> {
+    catalog
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect()
+}
+// the message in the current language, else the English one, else the key itself
+pub fn tr(key: &str) -> String {
+    let messages = MESSAGES.get_or_init(|| CATALOGS.iter().map(|c| parse(c)).collect());
+    let index = LANGUAGE.load(Ordering::Relaxed);
+    messages
+        .get(index)
+        .and_then(|m| m.get(key))
+        .or_else(|| messages[0].get(key))
+        .map(|m| m.to_string())
+        .unwrap_or_else(|| key.to_string())
+}
+// fill in {0}, {1}... so each language can put the arguments where its grammar wants them
+pub fn trf(key: &str, args: &[&dyn Display]) -> String {
+    args.iter()
+        .enumerate()
+        .fold(tr(key), |text, (i, arg)| text.replace(&format!("{{{}}}", i), &arg.to_string()))
+}
+// display name of an enum value, looked up as `TypeName.Variant`
+pub fn label<T: Debug>(value: &T) -> String {
+    let type_name = std::any::type_name::<T>().rsplit("::").next().unwrap_or_default();
+    tr(&format!("{}.{:?}", type_name, value))
+}
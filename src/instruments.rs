@@ -1,28 +1,60 @@
+use crate::fingering::FingeringStyle;
+use crate::staff::Clef;
+
+#[derive(Clone)]
 pub struct Instrument {
     pub name: String,
     pub tune_index: usize,
+    pub midi_offset: usize,
+    pub fingering: FingeringStyle,
+    pub clef: Clef,
     pub tunings: Vec<Tuning>,
 }
+#[derive(Clone)]
 pub struct Tuning {
     pub name: String,
     pub strings: Vec<usize>,
+    // semitones the strings were raised by to keep them unsigned
+    pub raised: usize,
 }
 impl Tuning {
     pub fn from(name: &str, strings: Vec<usize>) -> Tuning {
         Tuning {
             name: name.to_string(),
             strings,
+            raised: 0,
+        }
+    }
+    pub fn offset(name: &str, base: &[usize], shape: &[i32], more:i32, is_left:bool) -> Tuning {
+        let (strings, raised) = offset_strings(base, shape, more, is_left);
+        Tuning {
+            name: name.to_string(),
+            strings,
+            raised,
         }
     }
     pub fn name(&self) -> &String {
         &self.name
     }
 }
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FretPos {
+    pub string: usize,
+    pub fret: usize,
+}
+impl FretPos {
+    pub fn note(&self, strings: &[usize]) -> usize {
+        strings[self.string] + self.fret
+    }
+}
 impl Default for Instrument {
     fn default() -> Self {
         Self {
             name: "None".to_string(),
             tune_index: 0,
+            midi_offset: 36,
+            fingering: FingeringStyle::Fretted,
+            clef: Clef::Treble8vb,
             tunings: vec![],
         }
     }
@@ -32,15 +64,45 @@ impl Instrument {
         Instrument {
             name: name.to_string(),
             tune_index: 0,
+            midi_offset: 36,
+            fingering: FingeringStyle::Fretted,
+            clef: Clef::Treble8vb,
             tunings,
         }
     }
+    // string numbers are relative; this is the MIDI note of 0 on this instrument
+    pub fn with_midi_offset(mut self, midi_offset: usize) -> Instrument {
+        self.midi_offset = midi_offset;
+        self
+    }
+    pub fn with_fingering(mut self, fingering: FingeringStyle) -> Instrument {
+        self.fingering = fingering;
+        self
+    }
+    pub fn with_clef(mut self, clef: Clef) -> Instrument {
+        self.clef = clef;
+        self
+    }
+    pub fn midi_note(&self, note: usize) -> usize {
+        (note + self.midi_offset).saturating_sub(self.tuning().raised)
+    }
     pub fn strings(&self) -> &Vec<usize> {
         &self.tuning().strings
     }
     pub fn tuning(&self) -> &Tuning {
         &self.tunings[self.tune_index]
     }
+    // every instrument the app offers, in menu order
+    pub fn all() -> Vec<Instrument> {
+        vec![
+            Instrument::guitar(),
+            Instrument::violin(),
+            Instrument::mandolin(),
+            Instrument::cello(),
+            Instrument::ukulele(),
+            Instrument::banjo(),
+        ]
+    }
     pub fn none() -> Instrument {
         Instrument::from("none", vec![])
     }
@@ -50,36 +112,43 @@ impl Instrument {
         let shape_e = vec![0, 2, 2, 1, 0, 0];
         let shape_a = vec![0, 0, 2, 2, 2, 0];
         let shape_drop_d = vec![-2, 0, 0, 0, 0, 0];
-        let shape_d = vec![-1, 0, 0, 2, 3, 2];
+        let shape_d = vec![-2, 0, 0, 2, 3, 2];
+        let shape_dadgad = vec![-2, 0, 0, 0, -2, -2];
         Instrument::from("Guitar", vec![
-                Tuning::from("Standard", offset_strings(&standard, &shape_none, 0, false)),
-                Tuning::from("Standard (lefty)", offset_strings(&standard, &shape_none, 0, true)),
-                Tuning::from("Standard Eb", offset_strings(&standard, &shape_none, -1, false)),
-                Tuning::from("Standard D", offset_strings(&standard, &shape_none, -2, false)),
-                Tuning::from("DROP D", offset_strings(&standard, &shape_drop_d, 0, false)),
-                Tuning::from("OPEN D", offset_strings(&standard, &shape_e, -2, false)),
-                Tuning::from("OPEN E", offset_strings(&standard, &shape_e, 0, false)),
-                Tuning::from("OPEN F", offset_strings(&standard, &shape_a, -4, false)),
-                Tuning::from("OPEN G", offset_strings(&standard, &shape_a, -2, false)),
-                Tuning::from("OPEN A", offset_strings(&standard, &shape_a, 0, false)),
-                Tuning::from("OPEN C", offset_strings(&standard, &shape_d, -3, false)),
+                Tuning::offset("Standard", &standard, &shape_none, 0, false),
+                Tuning::offset("Standard (lefty)", &standard, &shape_none, 0, true),
+                Tuning::offset("Standard Eb", &standard, &shape_none, -1, false),
+                Tuning::offset("Standard D", &standard, &shape_none, -2, false),
+                Tuning::offset("DROP D", &standard, &shape_drop_d, 0, false),
+                Tuning::offset("OPEN D", &standard, &shape_e, -2, false),
+                Tuning::offset("OPEN E", &standard, &shape_e, 0, false),
+                Tuning::offset("OPEN F", &standard, &shape_a, -4, false),
+                Tuning::offset("OPEN G", &standard, &shape_a, -2, false),
+                Tuning::offset("OPEN A", &standard, &shape_a, 0, false),
+                Tuning::offset("OPEN C", &standard, &shape_d, -2, false),
+                Tuning::offset("DADGAD", &standard, &shape_dadgad, 0, false),
             ],
         )
     }
     pub fn violin() -> Instrument {
         Instrument::from("Violin", vec![
             Tuning::from("Standard", vec![7, 14, 21, 28]),
-        ])
+        ]).with_midi_offset(48).with_fingering(FingeringStyle::Violin).with_clef(Clef::Treble)
+    }
+    pub fn mandolin() -> Instrument {
+        Instrument::from("Mandolin", vec![
+            Tuning::from("Standard", vec![7, 14, 21, 28]),
+        ]).with_midi_offset(48).with_clef(Clef::Treble)
     }
     pub fn cello() -> Instrument {
         Instrument::from("Cello", vec![
             Tuning::from("Standard", vec![0, 7, 14, 21]),
-        ])
+        ]).with_fingering(FingeringStyle::Cello).with_clef(Clef::Bass)
     }
     pub fn ukulele() -> Instrument {
         Instrument::from("Ukulele", vec![
             Tuning::from("Standard", vec![7, 12, 16, 21]),
-        ])
+        ]).with_midi_offset(48).with_clef(Clef::Treble)
     }
     pub fn banjo() -> Instrument {
         Instrument::from("Banjo", vec![
@@ -87,7 +156,7 @@ impl Instrument {
         ])
     }
 }
-fn offset_strings(vec1: &[usize], vec2: &[i32], more:i32, is_left:bool) -> Vec<usize> {
+fn offset_strings(vec1: &[usize], vec2: &[i32], more:i32, is_left:bool) -> (Vec<usize>, usize) {
     let mut offset = more;
     for i in 0..vec2.len() {
         while vec2[i] + offset < 0 {
@@ -97,11 +166,10 @@ fn offset_strings(vec1: &[usize], vec2: &[i32], more:i32, is_left:bool) -> Vec<u
     let mut result = Vec::with_capacity(vec1.len());
     for i in 0..vec1.len() {
         let plus = vec2[i] + offset;
-        // println!("{:} + {:} - {:} = {:}", more, vec2[i], offset, plus);
         result.push(vec1[i] + plus as usize);
     }
     if is_left {
         result.reverse();
     }
-    return result;
+    return (result, (offset - more) as usize);
 }
@@ -0,0 +1,108 @@
+use egui::{Pos2, Rect, Vec2};
+use crate::scales::TOTAL_TONES;
+
+const BLACK_KEYS: [usize; 5] = [1, 3, 6, 8, 10];
+const MAX_KEY_WIDTH: f32 = 28.0;
+
+pub struct PianoKey {
+    pub note: usize,
+    pub rect: Rect,
+    pub black: bool,
+}
+pub fn is_black(note: usize) -> bool {
+    BLACK_KEYS.contains(&(note % TOTAL_TONES))
+}
+// one key per note from low to high, widened to start and end on a white key and centred in rect;
+// the white keys come first so the black ones paint over them
+pub fn layout(rect: Rect, low: usize, high: usize) -> Vec<PianoKey> {
+    let low = match is_black(low) {
+        true => low - 1,
+        false => low,
+    };
+    let high = match is_black(high) {
+        true => high + 1,
+        false => high,
+    };
+    let whites = (low..=high).filter(|n| !is_black(*n)).count();
+    let width = (rect.width() / whites as f32).min(MAX_KEY_WIDTH);
+    let mut x = rect.center().x - width * whites as f32 / 2f32;
+    let mut white_keys = vec![];
+    let mut black_keys = vec![];
+    for note in low..=high {
+        match is_black(note) {
+            false => {
+                let rect = Rect::from_min_size(Pos2::new(x, rect.top()), Vec2::new(width, rect.height()));
+                white_keys.push(PianoKey { note, rect, black: false });
+                x += width;
+            },
+            true => {
+                let rect = Rect::from_min_size(Pos2::new(x - width * 0.3, rect.top()), Vec2::new(width * 0.6, rect.height() * 0.6));
+                black_keys.push(PianoKey { note, rect, black: true });
+            },
+        }
+    }
+    white_keys.extend(black_keys);
+    white_keys
+}
+// black keys sit on top of the white ones
+pub fn key_at(keys: &[PianoKey], pos: Pos2) -> Option<usize> {
+    keys.iter().rev().find(|k| k.rect.contains(pos)).map(|k| k.note)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<PianoKey> {
+        layout(Rect::from_min_size(Pos2::ZERO, Vec2::new(700f32, 100f32)), 12, 23)
+    }
+    fn key(keys: &[PianoKey], note: usize) -> &PianoKey {
+        keys.iter().find(|k| k.note == note).unwrap()
+    }
+
+    #[test]
+    fn black_keys_are_the_sharps() {
+        let octave: Vec<bool> = (0..TOTAL_TONES).map(is_black).collect();
+        assert_eq!(octave, [false, true, false, true, false, false, true, false, true, false, true, false]);
+        assert!(is_black(13) && is_black(46) && !is_black(40) && !is_black(47));
+    }
+    #[test]
+    fn white_keys_come_first_and_side_by_side() {
+        let keys = keys();
+        let notes: Vec<usize> = keys.iter().map(|k| k.note).collect();
+        assert_eq!(notes, [12, 14, 16, 17, 19, 21, 23, 13, 15, 18, 20, 22]);
+        assert!(keys.iter().all(|k| k.black == is_black(k.note)));
+        // seven white keys at most 28 wide, centred in the 700 wide rect
+        let whites: Vec<&PianoKey> = keys.iter().filter(|k| !k.black).collect();
+        assert!(whites.iter().all(|k| k.rect.width() == MAX_KEY_WIDTH && k.rect.height() == 100f32));
+        assert_eq!(whites[0].rect.left(), 350f32 - 3.5 * MAX_KEY_WIDTH);
+        assert!(whites.windows(2).all(|w| w[0].rect.right() == w[1].rect.left()));
+    }
+    #[test]
+    fn black_keys_straddle_their_neighbours() {
+        let keys = keys();
+        for black in keys.iter().filter(|k| k.black) {
+            let below = key(&keys, black.note - 1);
+            assert_eq!(black.rect.center().x, below.rect.right(), "{}", black.note);
+            assert!(black.rect.height() < below.rect.height());
+        }
+    }
+    #[test]
+    fn ranges_widen_to_white_keys() {
+        let keys = layout(Rect::from_min_size(Pos2::ZERO, Vec2::new(100f32, 50f32)), 1, 10);
+        let notes: Vec<usize> = keys.iter().map(|k| k.note).collect();
+        assert_eq!(notes.iter().min(), Some(&0));
+        assert_eq!(notes.iter().max(), Some(&11));
+        // too many keys for 28 each, so they share the width
+        assert_eq!(key(&keys, 0).rect.width(), 100f32 / 7f32);
+    }
+    #[test]
+    fn black_keys_take_clicks_on_top() {
+        let keys = keys();
+        let c_sharp = key(&keys, 13).rect;
+        assert_eq!(key_at(&keys, c_sharp.center()), Some(13));
+        // below the black key is the white one
+        assert_eq!(key_at(&keys, Pos2::new(c_sharp.center().x - 1f32, 90f32)), Some(12));
+        assert_eq!(key_at(&keys, Pos2::new(c_sharp.center().x + 1f32, 90f32)), Some(14));
+        assert_eq!(key_at(&keys, Pos2::new(0f32, 50f32)), None);
+    }
+}
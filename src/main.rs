@@ -3,10 +3,37 @@
 mod scales;
 mod fretboard;
 mod instruments;
+mod pitch;
+mod tuner;
+mod rng;
+mod quiz;
+mod trainer;
+mod positions;
+mod fingering;
+mod arpeggios;
+mod i18n;
+mod keyboard;
+mod staff;
+mod tab;
+mod musicxml;
+mod archive;
+mod song;
+mod canvas;
+#[cfg(not(target_arch = "wasm32"))]
+mod raster;
+#[cfg(not(target_arch = "wasm32"))]
+mod pdf;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
 use crate::fretboard::FretboardApp;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main(){
+    // a command on the command line runs without opening the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
     let _result = eframe::run_native(
         "guitar-scales",
         eframe::NativeOptions::default(),
@@ -0,0 +1,392 @@
+use std::fmt::Write;
+use crate::instruments::FretPos;
+use crate::scales::{ScaleType, LETTERS, LETTER_TONES, TOTAL_TONES};
+use crate::staff::Clef;
+
+// every beat gets a quarter, four to a bar of 4/4
+const BEATS_PER_BAR: usize = 4;
+
+// a sounding MIDI note with the letter and accidental it's spelled with
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Pitch {
+    pub midi: usize,
+    pub letter: usize,
+    pub accidental: i32,
+}
+// one part on two staves: standard notation above and a TAB staff below
+pub struct Score {
+    pub title: String,
+    pub part: String,
+    pub clef: Clef,
+    // sharps in the key signature, negative for flats
+    pub fifths: i32,
+    pub mode: ScaleType,
+    // the open strings in the instrument's order
+    pub tuning: Vec<Pitch>,
+    // each beat's notes, more than one being a chord
+    pub beats: Vec<Vec<(Pitch, FretPos)>>,
+}
+impl Score {
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+        xml.push_str("<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \"http://www.musicxml.org/dtds/partwise.dtd\">\n");
+        xml.push_str("<score-partwise version=\"4.0\">\n");
+        let _ = writeln!(xml, "  <work><work-title>{}</work-title></work>", escape(&self.title));
+        xml.push_str("  <part-list>\n");
+        let _ = writeln!(xml, "    <score-part id=\"P1\"><part-name>{}</part-name></score-part>", escape(&self.part));
+        xml.push_str("  </part-list>\n");
+        xml.push_str("  <part id=\"P1\">\n");
+        let bars: Vec<&[Vec<(Pitch, FretPos)>]> = match self.beats.is_empty() {
+            true => vec![&[]],
+            false => self.beats.chunks(BEATS_PER_BAR).collect(),
+        };
+        for (i, bar) in bars.iter().enumerate() {
+            let _ = writeln!(xml, "    <measure number=\"{}\">", i + 1);
+            if i == 0 {
+                self.write_attributes(&mut xml);
+            }
+            for (i, (pitch, _)) in bar.iter().flat_map(|beat| beat.iter().enumerate()) {
+                xml.push_str("      <note>\n");
+                write_chord(&mut xml, i);
+                write_pitch(&mut xml, pitch);
+                xml.push_str("        <duration>1</duration><voice>1</voice><type>quarter</type><staff>1</staff>\n");
+                xml.push_str("      </note>\n");
+            }
+            write_rests(&mut xml, bar.len(), 1, 1);
+            let _ = writeln!(xml, "      <backup><duration>{}</duration></backup>", BEATS_PER_BAR);
+            // the same notes again on the TAB staff, placed by string and fret
+            for (i, (pitch, at)) in bar.iter().flat_map(|beat| beat.iter().enumerate()) {
+                xml.push_str("      <note>\n");
+                write_chord(&mut xml, i);
+                write_pitch(&mut xml, pitch);
+                xml.push_str("        <duration>1</duration><voice>2</voice><type>quarter</type><staff>2</staff>\n");
+                let _ = writeln!(xml, "        <notations><technical><string>{}</string><fret>{}</fret></technical></notations>", self.string_number(at.string), at.fret);
+                xml.push_str("      </note>\n");
+            }
+            write_rests(&mut xml, bar.len(), 2, 2);
+            xml.push_str("    </measure>\n");
+        }
+        xml.push_str("  </part>\n");
+        xml.push_str("</score-partwise>\n");
+        xml
+    }
+    fn write_attributes(&self, xml: &mut String) {
+        xml.push_str("      <attributes>\n");
+        xml.push_str("        <divisions>1</divisions>\n");
+        let _ = writeln!(xml, "        <key><fifths>{}</fifths><mode>{}</mode></key>", self.fifths, mode_name(self.mode));
+        let _ = writeln!(xml, "        <time><beats>{}</beats><beat-type>4</beat-type></time>", BEATS_PER_BAR);
+        xml.push_str("        <staves>2</staves>\n");
+        let clef = match self.clef {
+            Clef::Treble => "<sign>G</sign><line>2</line>",
+            Clef::Treble8vb => "<sign>G</sign><line>2</line><clef-octave-change>-1</clef-octave-change>",
+            Clef::Bass => "<sign>F</sign><line>4</line>",
+        };
+        let _ = writeln!(xml, "        <clef number=\"1\">{}</clef>", clef);
+        xml.push_str("        <clef number=\"2\"><sign>TAB</sign><line>5</line></clef>\n");
+        xml.push_str("        <staff-details number=\"2\">\n");
+        let _ = writeln!(xml, "          <staff-lines>{}</staff-lines>", self.tuning.len());
+        // staff-tuning lines count up from the lowest string
+        let mut order: Vec<usize> = (0..self.tuning.len()).collect();
+        order.sort_by_key(|i| self.tuning[*i].midi);
+        for (line, string) in order.iter().enumerate() {
+            let open = &self.tuning[*string];
+            let _ = write!(xml, "          <staff-tuning line=\"{}\"><tuning-step>{}</tuning-step>", line + 1, LETTERS[open.letter]);
+            if open.accidental != 0 {
+                let _ = write!(xml, "<tuning-alter>{}</tuning-alter>", open.accidental);
+            }
+            let _ = writeln!(xml, "<tuning-octave>{}</tuning-octave></staff-tuning>", octave(open));
+        }
+        xml.push_str("        </staff-details>\n");
+        xml.push_str("      </attributes>\n");
+    }
+    // MusicXML numbers strings from the highest, 1 being the top string on a guitar
+    fn string_number(&self, string: usize) -> usize {
+        let midi = self.tuning.get(string).map_or(0, |p| p.midi);
+        1 + self.tuning.iter().enumerate().filter(|(i, p)| p.midi > midi || (p.midi == midi && *i < string)).count()
+    }
+}
+// notes after a beat's first sound with it rather than after it
+fn write_chord(xml: &mut String, i: usize) {
+    if i > 0 {
+        xml.push_str("        <chord/>\n");
+    }
+}
+fn write_pitch(xml: &mut String, pitch: &Pitch) {
+    let _ = write!(xml, "        <pitch><step>{}</step>", LETTERS[pitch.letter]);
+    if pitch.accidental != 0 {
+        let _ = write!(xml, "<alter>{}</alter>", pitch.accidental);
+    }
+    let _ = writeln!(xml, "<octave>{}</octave></pitch>", octave(pitch));
+}
+// fill a short last bar with quarter rests
+fn write_rests(xml: &mut String, beats: usize, voice: usize, staff: usize) {
+    for _ in beats..BEATS_PER_BAR {
+        let _ = writeln!(xml, "      <note><rest/><duration>1</duration><voice>{}</voice><type>quarter</type><staff>{}</staff></note>", voice, staff);
+    }
+}
+// the octave of the letter, so B♯3 and C4 share a key but not an octave
+fn octave(pitch: &Pitch) -> i32 {
+    (pitch.midi as i32 - pitch.accidental - LETTER_TONES[pitch.letter] as i32).div_euclid(TOTAL_TONES as i32) - 1
+}
+fn mode_name(mode: ScaleType) -> &'static str {
+    match mode {
+        ScaleType::Minor => "minor",
+        ScaleType::Major => "major",
+        ScaleType::Dorian => "dorian",
+        ScaleType::Phrygian => "phrygian",
+        ScaleType::Lydian => "lydian",
+        ScaleType::Mixolydian => "mixolydian",
+        ScaleType::Locrian => "locrian",
+    }
+}
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+    use crate::song::Song;
+
+    // just enough of a DOM to walk the output
+    struct Element {
+        name: String,
+        attributes: Vec<(String, String)>,
+        children: Vec<Element>,
+        text: String,
+    }
+    impl Element {
+        fn names(&self) -> Vec<&str> {
+            self.children.iter().map(|c| c.name.as_str()).collect()
+        }
+        fn all(&self, name: &str) -> Vec<&Element> {
+            self.children.iter().filter(|c| c.name == name).collect()
+        }
+        fn child(&self, name: &str) -> &Element {
+            self.children.iter().find(|c| c.name == name).unwrap_or_else(|| panic!("no <{}> in <{}>", name, self.name))
+        }
+        fn text_of(&self, name: &str) -> &str {
+            self.child(name).text.trim()
+        }
+        fn attr(&self, name: &str) -> Option<&str> {
+            self.attributes.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+        }
+    }
+    fn element(start: &quick_xml::events::BytesStart) -> Element {
+        Element {
+            name: String::from_utf8_lossy(start.name().as_ref()).to_string(),
+            attributes: start.attributes().flatten()
+                .map(|a| (String::from_utf8_lossy(a.key.as_ref()).to_string(), a.unescape_value().unwrap().to_string()))
+                .collect(),
+            children: vec![],
+            text: String::new(),
+        }
+    }
+    // parse strictly, failing on anything that isn't well-formed
+    fn parse(xml: &str) -> Element {
+        let mut reader = Reader::from_str(xml);
+        reader.config_mut().check_end_names = true;
+        let mut stack: Vec<Element> = vec![];
+        loop {
+            match reader.read_event().expect("well-formed XML") {
+                Event::Start(start) => stack.push(element(&start)),
+                Event::Empty(start) => stack.last_mut().expect("inside the root").children.push(element(&start)),
+                Event::Text(text) => if let Some(e) = stack.last_mut() {
+                    e.text.push_str(&text.unescape().unwrap());
+                },
+                Event::End(_) => {
+                    let done = stack.pop().unwrap();
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(done),
+                        None => return done,
+                    }
+                },
+                Event::Eof => panic!("unclosed elements"),
+                _ => {},
+            }
+        }
+    }
+    fn pitch(midi: usize, letter: usize, accidental: i32) -> Pitch {
+        Pitch { midi, letter, accidental }
+    }
+    // five notes on a guitar, so the second bar is filled out with rests
+    fn score() -> Score {
+        let at = |string, fret| FretPos { string, fret };
+        Score {
+            title: "A <minor> & more".to_string(),
+            part: "Guitar".to_string(),
+            clef: Clef::Treble8vb,
+            fifths: 0,
+            mode: ScaleType::Minor,
+            tuning: vec![pitch(40, 2, 0), pitch(45, 5, 0), pitch(50, 1, 0), pitch(55, 4, 0), pitch(59, 6, 0), pitch(64, 2, 0)],
+            beats: vec![
+                vec![(pitch(45, 5, 0), at(0, 5))],
+                vec![(pitch(48, 0, 0), at(1, 3))],
+                vec![(pitch(50, 1, 0), at(1, 5))],
+                vec![(pitch(52, 2, 0), at(2, 2))],
+                vec![(pitch(60, 6, 1), at(4, 1))],
+            ],
+        }
+    }
+
+    #[test]
+    fn writes_a_partwise_score_with_one_part() {
+        let root = parse(&score().to_xml());
+        assert_eq!(root.name, "score-partwise");
+        assert_eq!(root.attr("version"), Some("4.0"));
+        assert_eq!(root.names(), ["work", "part-list", "part"]);
+        assert_eq!(root.child("work").text_of("work-title"), "A <minor> & more");
+        let part = root.child("part-list").child("score-part");
+        assert_eq!(part.attr("id"), Some("P1"));
+        assert_eq!(part.text_of("part-name"), "Guitar");
+        assert_eq!(root.child("part").attr("id"), Some("P1"));
+    }
+    #[test]
+    fn first_bar_sets_up_notation_and_tab_staves() {
+        let root = parse(&score().to_xml());
+        let attributes = root.child("part").child("measure").child("attributes");
+        // the order the schema's sequence asks for
+        assert_eq!(attributes.names(), ["divisions", "key", "time", "staves", "clef", "clef", "staff-details"]);
+        assert_eq!(attributes.text_of("divisions"), "1");
+        assert_eq!(attributes.child("key").names(), ["fifths", "mode"]);
+        assert_eq!(attributes.child("key").text_of("mode"), "minor");
+        assert_eq!(attributes.text_of("staves"), "2");
+        let clefs = attributes.all("clef");
+        assert_eq!((clefs[0].attr("number"), clefs[0].text_of("sign"), clefs[0].text_of("clef-octave-change")), (Some("1"), "G", "-1"));
+        assert_eq!((clefs[1].attr("number"), clefs[1].text_of("sign")), (Some("2"), "TAB"));
+        let details = attributes.child("staff-details");
+        assert_eq!(details.attr("number"), Some("2"));
+        assert_eq!(details.text_of("staff-lines"), "6");
+        let tuning: Vec<String> = details.all("staff-tuning").iter()
+            .map(|t| format!("{}{}{}", t.attr("line").unwrap(), t.text_of("tuning-step"), t.text_of("tuning-octave")))
+            .collect();
+        assert_eq!(tuning, ["1E2", "2A2", "3D3", "4G3", "5B3", "6E4"]);
+    }
+    #[test]
+    fn bars_hold_four_beats_on_each_staff() {
+        let root = parse(&score().to_xml());
+        let measures = root.child("part").all("measure");
+        assert_eq!(measures.iter().map(|m| m.attr("number").unwrap()).collect::<Vec<_>>(), ["1", "2"]);
+        for measure in measures {
+            let names: Vec<&str> = measure.names().into_iter().filter(|n| *n != "attributes").collect();
+            assert_eq!(names, ["note", "note", "note", "note", "backup", "note", "note", "note", "note"]);
+            assert_eq!(measure.child("backup").text_of("duration"), "4");
+            for staff in ["1", "2"] {
+                let beats: usize = measure.all("note").iter()
+                    .filter(|n| n.text_of("staff") == staff)
+                    .map(|n| n.text_of("duration").parse::<usize>().unwrap())
+                    .sum();
+                assert_eq!(beats, 4);
+            }
+        }
+    }
+    #[test]
+    fn notes_carry_pitch_then_string_and_fret() {
+        let root = parse(&score().to_xml());
+        let measures = root.child("part").all("measure");
+        let notes: Vec<&Element> = measures.iter().flat_map(|m| m.all("note")).filter(|n| n.children.iter().any(|c| c.name == "pitch")).collect();
+        let notation: Vec<&&Element> = notes.iter().filter(|n| n.text_of("staff") == "1").collect();
+        let tab: Vec<&&Element> = notes.iter().filter(|n| n.text_of("staff") == "2").collect();
+        assert_eq!(notation[0].names(), ["pitch", "duration", "voice", "type", "staff"]);
+        assert_eq!(tab[0].names(), ["pitch", "duration", "voice", "type", "staff", "notations"]);
+        let last = notation[4].child("pitch");
+        // B♯3 is written on B in octave 3, not C4
+        assert_eq!((last.text_of("step"), last.text_of("alter"), last.text_of("octave")), ("B", "1", "3"));
+        // strings count down from the highest, so the low E is string 6
+        let places: Vec<(&str, &str)> = tab.iter().map(|n| {
+            let technical = n.child("notations").child("technical");
+            (technical.text_of("string"), technical.text_of("fret"))
+        }).collect();
+        assert_eq!(places, [("6", "5"), ("5", "3"), ("5", "5"), ("4", "2"), ("2", "1")]);
+    }
+    #[test]
+    fn reads_back_as_the_same_notes() {
+        let song = Song::from_bytes("export.musicxml", score().to_xml().as_bytes()).ok().unwrap();
+        assert_eq!(song.tracks[0].bars, vec![vec![45, 48, 50, 52], vec![60]]);
+    }
+    #[test]
+    fn an_empty_score_is_one_bar_of_rests() {
+        let root = parse(&Score { beats: vec![], ..score() }.to_xml());
+        let measures = root.child("part").all("measure");
+        assert_eq!(measures.len(), 1);
+        assert!(measures[0].all("note").iter().all(|n| n.children.iter().any(|c| c.name == "rest")));
+    }
+    #[test]
+    fn chords_share_their_beat() {
+        let at = |string, fret| FretPos { string, fret };
+        // an open A minor, then a lone A
+        let chord = vec![(pitch(45, 5, 0), at(1, 0)), (pitch(52, 2, 0), at(2, 2)), (pitch(57, 5, 0), at(3, 2)), (pitch(60, 0, 0), at(4, 1))];
+        let root = parse(&Score { beats: vec![chord, vec![(pitch(45, 5, 0), at(1, 0))]], ..score() }.to_xml());
+        let measure = root.child("part").child("measure");
+        for staff in ["1", "2"] {
+            let notes: Vec<&Element> = measure.all("note").into_iter().filter(|n| n.text_of("staff") == staff).collect();
+            let chorded: Vec<bool> = notes.iter().map(|n| n.children.iter().any(|c| c.name == "chord")).collect();
+            assert_eq!(chorded, [false, true, true, true, false, false, false]);
+            // <chord/> comes before the pitch, and only the first of a chord's notes counts toward the bar
+            assert_eq!(notes[1].names()[..2], ["chord", "pitch"]);
+            let beats: usize = notes.iter().zip(chorded.iter()).filter(|(_, c)| !**c)
+                .map(|(n, _)| n.text_of("duration").parse::<usize>().unwrap())
+                .sum();
+            assert_eq!(beats, 4);
+        }
+    }
+    // with the musicxml-schema feature, validate with xmllint against the vendored schema,
+    // or the one MUSICXML_XSD names
+    #[cfg(feature = "musicxml-schema")]
+    fn validate(name: &str, xml: &str) -> Result<(), String> {
+        let schema = std::env::var("MUSICXML_XSD").unwrap_or_else(|_| format!("{}/schema/musicxml-4.0-subset.xsd", env!("CARGO_MANIFEST_DIR")));
+        let path = std::env::temp_dir().join(format!("fretboard-{}-{}.musicxml", name, std::process::id()));
+        std::fs::write(&path, xml).unwrap();
+        let output = std::process::Command::new("xmllint").args(["--noout", "--nonet", "--schema", &schema]).arg(&path).output();
+        let _ = std::fs::remove_file(&path);
+        match output.expect("xmllint on the PATH") {
+            output if output.status.success() => Ok(()),
+            output => Err(String::from_utf8_lossy(&output.stderr).to_string()),
+        }
+    }
+    #[cfg(feature = "musicxml-schema")]
+    #[test]
+    fn exports_are_valid_musicxml() {
+        let at = |string, fret| FretPos { string, fret };
+        let chords = Score {
+            beats: vec![
+                vec![(pitch(45, 5, 0), at(1, 0)), (pitch(52, 2, 0), at(2, 2)), (pitch(57, 5, 0), at(3, 2)), (pitch(60, 0, 0), at(4, 1))],
+                vec![(pitch(43, 4, 0), at(0, 3))],
+            ],
+            ..score()
+        };
+        // E♭ major on the cello, in the bass clef on four strings
+        let cello = Score {
+            title: "E♭ major".to_string(),
+            part: "Cello".to_string(),
+            clef: Clef::Bass,
+            fifths: -3,
+            mode: ScaleType::Major,
+            tuning: vec![pitch(36, 0, 0), pitch(43, 4, 0), pitch(50, 1, 0), pitch(57, 5, 0)],
+            beats: vec![
+                vec![(pitch(39, 2, -1), at(0, 3))],
+                vec![(pitch(41, 3, 0), at(0, 5))],
+                vec![(pitch(43, 4, 0), at(1, 0)), (pitch(51, 2, -1), at(2, 1))],
+                vec![(pitch(44, 5, -1), at(1, 1))],
+                vec![(pitch(46, 6, -1), at(1, 3))],
+            ],
+        };
+        let empty = Score { beats: vec![], ..score() };
+        for (name, score) in [("guitar", score()), ("chords", chords), ("cello", cello), ("empty", empty)] {
+            if let Err(e) = validate(name, &score.to_xml()) {
+                panic!("the {} score isn't valid: {}", name, e);
+            }
+        }
+    }
+    #[cfg(feature = "musicxml-schema")]
+    #[test]
+    fn the_schema_catches_misplaced_elements() {
+        // <chord/> belongs before the pitch, and the staff after the type
+        let xml = score().to_xml();
+        assert_eq!(validate("valid", &xml), Ok(()));
+        assert!(validate("chord", &xml.replacen("<pitch>", "<pitch><chord/>", 1)).is_err());
+        assert!(validate("staff", &xml.replacen("<type>quarter</type><staff>1</staff>", "<staff>1</staff><type>quarter</type>", 1)).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt::Write;
+use crate::raster::Raster;
+
+// an A4 sheet in PDF points, 72 to the inch
+pub const PAGE_WIDTH: f32 = 595f32;
+pub const PAGE_HEIGHT: f32 = 842f32;
+
+// a document of pages that are each one picture filling the sheet, so it prints as drawn
+#[derive(Default)]
+pub struct Pdf {
+    // each page's width and height in pixels and its RGB rows, compressed
+    pages: Vec<(usize, usize, Vec<u8>)>,
+}
+impl Pdf {
+    pub fn add_page(&mut self, page: &Raster) {
+        let data = miniz_oxide::deflate::compress_to_vec_zlib(&page.rgb(), 6);
+        self.pages.push((page.width(), page.height(), data));
+    }
+    // the catalog and page tree come first, then a page, its contents and its picture for each page
+    pub fn finish(self) -> Vec<u8> {
+        let mut pdf: Vec<u8> = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = vec![];
+        let mut object = |pdf: &mut Vec<u8>, header: String, stream: Option<&[u8]>| {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n{}\n", offsets.len(), header).as_bytes());
+            if let Some(stream) = stream {
+                pdf.extend_from_slice(b"stream\n");
+                pdf.extend_from_slice(stream);
+                pdf.extend_from_slice(b"\nendstream\n");
+            }
+            pdf.extend_from_slice(b"endobj\n");
+        };
+        let page_ids = (0..self.pages.len()).map(|i| format!("{} 0 R", 3 + 3 * i)).collect::<Vec<_>>().join(" ");
+        object(&mut pdf, "<< /Type /Catalog /Pages 2 0 R >>".to_string(), None);
+        object(&mut pdf, format!("<< /Type /Pages /Kids [{}] /Count {} >>", page_ids, self.pages.len()), None);
+        for (i, (width, height, data)) in self.pages.iter().enumerate() {
+            let id = 3 + 3 * i;
+            let contents = format!("q {} 0 0 {} 0 0 cm /Page Do Q", PAGE_WIDTH, PAGE_HEIGHT);
+            object(&mut pdf, format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R /Resources << /XObject << /Page {} 0 R >> >> >>",
+                PAGE_WIDTH, PAGE_HEIGHT, id + 1, id + 2,
+            ), None);
+            object(&mut pdf, format!("<< /Length {} >>", contents.len()), Some(contents.as_bytes()));
+            object(&mut pdf, format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>",
+                width, height, data.len(),
+            ), Some(data));
+        }
+        // the cross-reference table says where each object starts
+        let start = pdf.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+        for offset in offsets.iter() {
+            let _ = writeln!(xref, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(xref, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", offsets.len() + 1, start);
+        pdf.extend_from_slice(xref.as_bytes());
+        pdf
+    }
+}
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use egui::Color32;
+
+    fn find(bytes: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+        bytes[from..].windows(needle.len()).position(|w| w == needle).map(|i| from + i)
+    }
+    // check the cross-reference table points at each object in turn and return the page count
+    pub fn pages(pdf: &[u8]) -> usize {
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        let startxref = pdf.windows(10).rposition(|w| w == b"startxref\n").expect("startxref");
+        let tail = std::str::from_utf8(&pdf[startxref..]).unwrap();
+        let start: usize = tail.lines().nth(1).unwrap().parse().unwrap();
+        let xref = std::str::from_utf8(&pdf[start..]).expect("the table is text");
+        let mut lines = xref.lines();
+        assert_eq!(lines.next(), Some("xref"));
+        let size: usize = lines.next().unwrap().strip_prefix("0 ").unwrap().parse().unwrap();
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        for id in 1..size {
+            let entry = lines.next().unwrap();
+            assert_eq!(entry.len(), 19, "entries are 20 bytes with the newline");
+            assert!(entry.ends_with(" 00000 n "));
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", id).as_bytes()), "object {} is not at {}", id, offset);
+        }
+        assert_eq!(lines.next(), Some("trailer"));
+        assert_eq!(lines.next(), Some(format!("<< /Size {} /Root 1 0 R >>", size).as_str()));
+        // pages, then one page, contents and picture each
+        let count = find(pdf, b"/Count ", 0).map(|i| &pdf[i + 7..]).unwrap();
+        let count: usize = std::str::from_utf8(&count[..count.iter().position(|b| *b == b' ').unwrap()]).unwrap().parse().unwrap();
+        assert_eq!(size, 3 + 3 * count);
+        count
+    }
+
+    #[test]
+    fn pages_hold_their_pictures() {
+        let mut pdf = Pdf::default();
+        for (width, height) in [(4, 3), (2, 5)] {
+            pdf.add_page(&Raster::new(width, height, Color32::from_rgb(10, 20, 30), vec![]));
+        }
+        let pdf = pdf.finish();
+        assert_eq!(pages(&pdf), 2);
+        let image = find(&pdf, b"/Width 2 /Height 5", 0).expect("the second picture");
+        let data = find(&pdf, b"stream\n", image).unwrap() + 7;
+        let end = find(&pdf, b"\nendstream", data).unwrap();
+        let rgb = miniz_oxide::inflate::decompress_to_vec_zlib(&pdf[data..end]).unwrap();
+        assert_eq!(rgb, [10, 20, 30].repeat(10));
+    }
+    #[test]
+    fn an_empty_document_still_has_a_table() {
+        assert_eq!(pages(&Pdf::default().finish()), 0);
+    }
+}
@@ -0,0 +1,319 @@
+use std::io::Cursor;
+
+use crate::scales::TOTAL_TONES;
+
+pub const A4_HZ: f32 = 440.0;
+pub const A4_MIDI: f32 = 69.0;
+pub const FRAME_SIZE: usize = 2048;
+
+pub struct PitchDetector {
+    pub sample_rate: u32,
+    pub threshold: f32,
+    pub min_hz: f32,
+    pub max_hz: f32,
+    pub min_level: f32,
+}
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Detection {
+    pub frequency: f32,
+    pub midi: usize,
+    pub cents: f32,
+}
+impl Detection {
+    pub fn from_frequency(frequency: f32) -> Detection {
+        let exact = frequency_to_midi(frequency);
+        let midi = exact.round();
+        Detection {
+            frequency,
+            midi: midi.max(0.0) as usize,
+            cents: (exact - midi) * 100.0,
+        }
+    }
+    pub fn pitch_class(&self) -> usize {
+        self.midi % TOTAL_TONES
+    }
+}
+impl PitchDetector {
+    pub fn new(sample_rate: u32) -> PitchDetector {
+        PitchDetector {
+            sample_rate,
+            threshold: 0.15,
+            min_hz: 55.0,
+            max_hz: 1400.0,
+            min_level: 0.01,
+        }
+    }
+    // YIN: difference function, cumulative mean normalization, absolute threshold
+    pub fn frequency(&self, frame: &[f32]) -> Option<f32> {
+        if rms(frame) < self.min_level {
+            return None;
+        }
+        let sr = self.sample_rate as f32;
+        let window = frame.len() / 2;
+        let tau_min = ((sr / self.max_hz) as usize).max(2);
+        let tau_max = ((sr / self.min_hz) as usize).min(window);
+        if tau_min + 2 >= tau_max {
+            return None;
+        }
+        let mut cmnd = vec![1f32; tau_max];
+        let mut running_sum = 0f32;
+        for tau in 1..tau_max {
+            let diff: f32 = (0..window)
+                .map(|j| {
+                    let d = frame[j] - frame[j + tau];
+                    d * d
+                })
+                .sum();
+            running_sum += diff;
+            cmnd[tau] = match running_sum > 0.0 {
+                true => diff * tau as f32 / running_sum,
+                false => 1.0,
+            };
+        }
+        let mut tau = tau_min;
+        while tau < tau_max {
+            if cmnd[tau] < self.threshold {
+                // walk down into the dip before accepting it
+                while tau + 1 < tau_max && cmnd[tau + 1] < cmnd[tau] {
+                    tau += 1;
+                }
+                break;
+            }
+            tau += 1;
+        }
+        if tau >= tau_max {
+            return None;
+        }
+        Some(sr / parabolic_peak(&cmnd, tau))
+    }
+    pub fn detect(&self, frame: &[f32]) -> Option<Detection> {
+        self.frequency(frame).map(Detection::from_frequency)
+    }
+}
+fn parabolic_peak(values: &[f32], i: usize) -> f32 {
+    if i < 1 || i + 1 >= values.len() {
+        return i as f32;
+    }
+    let (a, b, c) = (values[i - 1], values[i], values[i + 1]);
+    let denominator = a - 2.0 * b + c;
+    match denominator.abs() > f32::EPSILON {
+        true => i as f32 + 0.5 * (a - c) / denominator,
+        false => i as f32,
+    }
+}
+fn rms(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt()
+}
+pub fn frequency_to_midi(hz: f32) -> f32 {
+    A4_MIDI + 12.0 * (hz / A4_HZ).log2()
+}
+pub fn midi_to_frequency(midi: f32) -> f32 {
+    A4_HZ * 2f32.powf((midi - A4_MIDI) / 12.0)
+}
+
+pub struct Recording {
+    pub name: String,
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+impl Recording {
+    pub fn from_wav(name: &str, bytes: &[u8]) -> Result<Recording, String> {
+        let reader = hound::WavReader::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+        let spec = reader.spec();
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .into_samples::<f32>()
+                .collect::<Result<_, _>>()
+                .map_err(|e| e.to_string())?,
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .into_samples::<i32>()
+                    .map(|s| s.map(|s| s as f32 / scale))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| e.to_string())?
+            }
+        };
+        // mix down to mono
+        let channels = spec.channels.max(1) as usize;
+        let samples = interleaved
+            .chunks(channels)
+            .map(|c| c.iter().sum::<f32>() / channels as f32)
+            .collect();
+        Ok(Recording {
+            name: name.to_string(),
+            samples,
+            sample_rate: spec.sample_rate,
+        })
+    }
+    pub fn duration(&self) -> f32 {
+        self.samples.len() as f32 / self.sample_rate as f32
+    }
+    pub fn frame_at(&self, seconds: f32) -> &[f32] {
+        let start = ((seconds * self.sample_rate as f32) as usize).min(self.samples.len());
+        let end = (start + FRAME_SIZE).min(self.samples.len());
+        &self.samples[start..end]
+    }
+}
+
+#[cfg(feature = "microphone")]
+pub struct Microphone {
+    _stream: cpal::Stream,
+    buffer: std::sync::Arc<std::sync::Mutex<Vec<f32>>>,
+    pub sample_rate: u32,
+}
+#[cfg(feature = "microphone")]
+impl Microphone {
+    pub fn open() -> Result<Microphone, String> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+        use std::sync::{Arc, Mutex};
+
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or("no input device found")?;
+        let config = device.default_input_config().map_err(|e| e.to_string())?;
+        let sample_rate = config.sample_rate().0;
+        let channels = config.channels().max(1) as usize;
+        let buffer = Arc::new(Mutex::new(Vec::with_capacity(FRAME_SIZE * 2)));
+        let writer = buffer.clone();
+        let stream = device
+            .build_input_stream(
+                &config.into(),
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    if let Ok(mut buffer) = writer.lock() {
+                        buffer.extend(data.chunks(channels).map(|c| c.iter().sum::<f32>() / channels as f32));
+                        // only the most recent frame is ever analysed
+                        let excess = buffer.len().saturating_sub(FRAME_SIZE);
+                        buffer.drain(..excess);
+                    }
+                },
+                |e| log::error!("microphone: {e}"),
+                None,
+            )
+            .map_err(|e| e.to_string())?;
+        stream.play().map_err(|e| e.to_string())?;
+        Ok(Microphone {
+            _stream: stream,
+            buffer,
+            sample_rate,
+        })
+    }
+    pub fn frame(&self) -> Vec<f32> {
+        self.buffer.lock().map(|b| b.clone()).unwrap_or_default()
+    }
+}
+
+pub struct Listener {
+    pub recording: Option<Recording>,
+    pub position: f32,
+    pub playing: bool,
+    pub heard: Option<Detection>,
+    pub wav_path: String,
+    #[cfg(feature = "microphone")]
+    pub microphone: Option<Microphone>,
+}
+impl Default for Listener {
+    fn default() -> Self {
+        Self {
+            recording: None,
+            position: 0.0,
+            playing: false,
+            heard: None,
+            wav_path: String::new(),
+            #[cfg(feature = "microphone")]
+            microphone: None,
+        }
+    }
+}
+impl Listener {
+    pub fn load(&mut self, recording: Recording) {
+        self.recording = Some(recording);
+        self.position = 0.0;
+        self.playing = true;
+        self.heard = None;
+    }
+    pub fn is_active(&self) -> bool {
+        #[cfg(feature = "microphone")]
+        if self.microphone.is_some() {
+            return true;
+        }
+        self.playing
+    }
+    // advance playback by dt seconds and re-run detection on the current frame
+    pub fn update(&mut self, dt: f32) {
+        #[cfg(feature = "microphone")]
+        if let Some(mic) = &self.microphone {
+            self.heard = PitchDetector::new(mic.sample_rate).detect(&mic.frame());
+            return;
+        }
+        let Some(recording) = &self.recording else {
+            return;
+        };
+        if self.playing {
+            self.position += dt;
+            if self.position >= recording.duration() {
+                self.position = 0.0;
+                self.playing = false;
+            }
+        }
+        self.heard = PitchDetector::new(recording.sample_rate).detect(recording.frame_at(self.position));
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a second of a sine wave as a WAV file
+    fn wav(frequency: f32, amplitude: f32, spec: hound::WavSpec) -> Vec<u8> {
+        let mut cursor = Cursor::new(vec![]);
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+        for i in 0..spec.sample_rate {
+            let sample = amplitude * (2.0 * std::f32::consts::PI * frequency * i as f32 / spec.sample_rate as f32).sin();
+            for _ in 0..spec.channels {
+                match spec.sample_format {
+                    hound::SampleFormat::Int => writer.write_sample((sample * i16::MAX as f32) as i16).unwrap(),
+                    hound::SampleFormat::Float => writer.write_sample(sample).unwrap(),
+                }
+            }
+        }
+        writer.finalize().unwrap();
+        cursor.into_inner()
+    }
+    fn mono_16_bit() -> hound::WavSpec {
+        hound::WavSpec { channels: 1, sample_rate: 44100, bits_per_sample: 16, sample_format: hound::SampleFormat::Int }
+    }
+    fn detect(bytes: &[u8]) -> Option<Detection> {
+        let recording = Recording::from_wav("test.wav", bytes).unwrap();
+        PitchDetector::new(recording.sample_rate).detect(recording.frame_at(0.25))
+    }
+    fn assert_near(frequency: f32, midi: usize, bytes: &[u8]) {
+        let detection = detect(bytes).unwrap_or_else(|| panic!("nothing heard at {} Hz", frequency));
+        let cents = 1200.0 * (detection.frequency / frequency).log2();
+        assert!(cents.abs() < 3.0, "{} Hz heard as {} Hz", frequency, detection.frequency);
+        assert_eq!(detection.midi, midi);
+    }
+
+    #[test]
+    fn hears_the_low_e_string() {
+        assert_near(82.41, 40, &wav(82.41, 0.5, mono_16_bit()));
+    }
+    #[test]
+    fn hears_concert_a() {
+        assert_near(440.0, 69, &wav(440.0, 0.5, mono_16_bit()));
+        let stereo_float = hound::WavSpec { channels: 2, sample_rate: 48000, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+        assert_near(440.0, 69, &wav(440.0, 0.5, stereo_float));
+    }
+    #[test]
+    fn silence_is_not_a_note() {
+        assert_eq!(detect(&wav(440.0, 0.0, mono_16_bit())), None);
+        // below the level the detector listens for
+        assert_eq!(detect(&wav(440.0, 0.005, mono_16_bit())), None);
+    }
+    #[test]
+    fn other_files_are_errors() {
+        assert!(Recording::from_wav("test.wav", b"RIFF\0\0\0\0WAVE").is_err());
+    }
+}
@@ -0,0 +1,332 @@
+use crate::fingering::Finger;
+use crate::i18n::{tr, trf};
+use crate::instruments::FretPos;
+use crate::scales::{Scale, ScaleSize, TOTAL_TONES};
+
+pub const BOXES: usize = 5;
+pub const PATTERNS_3NPS: usize = 7;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Position {
+    All,
+    Box(usize),
+    ThreeNps(usize),
+    Window { from: usize, to: usize },
+}
+impl Position {
+    pub fn name(&self) -> String {
+        match self {
+            Position::All => tr("position.all"),
+            Position::Box(i) => trf("position.box", &[&(i + 1)]),
+            Position::ThreeNps(i) => trf("position.three_nps", &[&(i + 1)]),
+            Position::Window { from, to } => trf("position.frets", &[from, to]),
+        }
+    }
+    // windows stay between the nut and the last fret
+    pub fn step(&self, forward: bool, frets: usize) -> Position {
+        match (self, forward) {
+            (Position::Box(i), true) => Position::Box((i + 1) % BOXES),
+            (Position::Box(i), false) => Position::Box((i + BOXES - 1) % BOXES),
+            (Position::ThreeNps(i), true) => Position::ThreeNps((i + 1) % PATTERNS_3NPS),
+            (Position::ThreeNps(i), false) => Position::ThreeNps((i + PATTERNS_3NPS - 1) % PATTERNS_3NPS),
+            (Position::Window { from, to }, true) if *to < frets => Position::Window { from: from + 1, to: to + 1 },
+            (Position::Window { from, to }, false) if *from > 0 => Position::Window { from: from - 1, to: to - 1 },
+            (Position::Window { .. }, _) => *self,
+            (Position::All, _) => Position::Box(0),
+        }
+    }
+}
+// the notes to draw for a position, plus the neighbouring boxes drawn faintly
+pub struct Shape {
+    pub notes: Vec<FretPos>,
+    pub ghost: Vec<FretPos>,
+    pub fingers: Vec<(FretPos, Finger)>,
+}
+impl Shape {
+    pub fn finger(&self, at: FretPos) -> Option<Finger> {
+        self.fingers.iter().find(|(p, _)| *p == at).map(|(_, f)| *f)
+    }
+    pub fn contains(&self, at: FretPos) -> bool {
+        self.notes.contains(&at)
+    }
+    pub fn is_ghost(&self, at: FretPos) -> bool {
+        !self.contains(at) && self.ghost.contains(&at)
+    }
+}
+pub fn shape(position: Position, scale: &Scale, strings: &[usize], frets: usize) -> Option<Shape> {
+    match position {
+        Position::All => None,
+        Position::Window { from, to } => Some(Shape {
+            notes: scale_notes_between(scale, strings, from, to.min(frets)),
+            ghost: vec![],
+            fingers: vec![],
+        }),
+        Position::Box(i) => Some(Shape {
+            notes: box_notes(scale, strings, i),
+            ghost: [(i + BOXES - 1) % BOXES, (i + 1) % BOXES]
+                .iter()
+                .flat_map(|n| box_notes(scale, strings, *n))
+                .collect(),
+            fingers: vec![],
+        }),
+        Position::ThreeNps(_) if scale.notes().len() != PATTERNS_3NPS => None,
+        Position::ThreeNps(i) => {
+            let fingers = three_notes_per_string(scale, strings, i);
+            Some(Shape {
+                notes: fingers.iter().map(|(at, _)| *at).collect(),
+                ghost: [(i + PATTERNS_3NPS - 1) % PATTERNS_3NPS, (i + 1) % PATTERNS_3NPS]
+                    .iter()
+                    .flat_map(|n| three_notes_per_string(scale, strings, *n))
+                    .map(|(at, _)| at)
+                    .collect(),
+                fingers,
+            })
+        },
+    }
+}
+fn scale_notes_between(scale: &Scale, strings: &[usize], from: usize, to: usize) -> Vec<FretPos> {
+    (0..strings.len())
+        .flat_map(|string| (from..=to).map(move |fret| FretPos { string, fret }))
+        .filter(|at| scale.is_note_in_scale(at.note(strings) as i16))
+        .collect()
+}
+// strings from lowest to highest pitch, so lefty tunings get the same boxes
+pub fn strings_by_pitch(strings: &[usize]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..strings.len()).collect();
+    order.sort_by_key(|i| (strings[*i], *i));
+    order
+}
+fn pentatonic(scale: &Scale) -> Scale {
+    Scale {
+        siz: ScaleSize::Pentatonic,
+        ..*scale
+    }
+}
+// the i-th pentatonic box: start on the i-th pentatonic note (counting from the root)
+// on the lowest string and take the next two scale notes on each string going up
+pub fn pentatonic_box(scale: &Scale, strings: &[usize], index: usize) -> Vec<FretPos> {
+    let order = strings_by_pitch(strings);
+    let Some(&lowest) = order.first() else {
+        return vec![];
+    };
+    let penta = pentatonic(scale);
+    let root_fret = (scale.key % TOTAL_TONES + TOTAL_TONES - strings[lowest] % TOTAL_TONES) % TOTAL_TONES;
+    let starts: Vec<usize> = (root_fret..root_fret + TOTAL_TONES)
+        .filter(|fret| penta.is_note_in_scale((strings[lowest] + fret) as i16))
+        .map(|fret| fret % TOTAL_TONES)
+        .collect();
+    let Some(&start) = starts.get(index % starts.len().max(1)) else {
+        return vec![];
+    };
+    let mut notes = vec![];
+    let mut pitch = strings[lowest] + start;
+    for string in order {
+        let open = strings[string];
+        pitch = pitch.max(open);
+        for _ in 0..2 {
+            while !penta.is_note_in_scale(pitch as i16) {
+                pitch += 1;
+            }
+            notes.push(FretPos { string, fret: pitch - open });
+            pitch += 1;
+        }
+    }
+    notes
+}
+// the box for any scale size: the pentatonic box with the remaining scale notes
+// filled in on whichever string keeps them inside the box's fret span (CAGED style)
+pub fn box_notes(scale: &Scale, strings: &[usize], index: usize) -> Vec<FretPos> {
+    let skeleton = pentatonic_box(scale, strings, index);
+    let (Some(lo), Some(hi)) = (skeleton.iter().map(|at| at.fret).min(), skeleton.iter().map(|at| at.fret).max()) else {
+        return vec![];
+    };
+    let lowest_pitch = skeleton.iter().map(|at| at.note(strings)).min().unwrap_or(0);
+    let highest_pitch = skeleton.iter().map(|at| at.note(strings)).max().unwrap_or(0);
+    let range_of = |string: usize| {
+        let frets = skeleton.iter().filter(|at| at.string == string).map(|at| at.fret);
+        (frets.clone().min().unwrap_or(lo), frets.max().unwrap_or(hi))
+    };
+    let mut notes = vec![];
+    for pitch in lowest_pitch..=highest_pitch {
+        if !scale.is_note_in_scale(pitch as i16) {
+            continue;
+        }
+        let candidates = strings_by_pitch(strings)
+            .into_iter()
+            .filter(|s| pitch >= strings[*s] && (lo..=hi).contains(&(pitch - strings[*s])))
+            .map(|s| {
+                let fret = pitch - strings[s];
+                let (a, b) = range_of(s);
+                let distance = a.saturating_sub(fret) + fret.saturating_sub(b);
+                (distance, FretPos { string: s, fret })
+            });
+        if let Some((_, at)) = candidates.min_by_key(|(distance, _)| *distance) {
+            notes.push(at);
+        }
+    }
+    notes
+}
+// the i-th three-notes-per-string fingering: start on scale degree i on the lowest string
+// and walk up the scale three notes at a time; because each string simply continues
+// from the last pitch, odd string intervals (G to B, open tunings) shift the shape for us;
+// near the nut a string takes a fourth note when the next one is tuned above it (a fifth apart)
+pub fn three_notes_per_string(scale: &Scale, strings: &[usize], index: usize) -> Vec<(FretPos, Finger)> {
+    let order = strings_by_pitch(strings);
+    let degrees = scale.notes();
+    let (Some(&lowest), Some(degree)) = (order.first(), degrees.get(index)) else {
+        return vec![];
+    };
+    let open = strings[lowest];
+    let start = ((scale.key + degree) % TOTAL_TONES + TOTAL_TONES - open % TOTAL_TONES) % TOTAL_TONES;
+    let mut pitch = open + start;
+    let mut notes = vec![];
+    for (n, &string) in order.iter().enumerate() {
+        let open = strings[string];
+        let next_open = order.get(n + 1).map(|s| strings[*s]);
+        let mut frets = vec![];
+        loop {
+            while !scale.is_note_in_scale(pitch as i16) {
+                pitch += 1;
+            }
+            if frets.len() >= 3 && next_open.is_none_or(|next| pitch >= next) {
+                break;
+            }
+            frets.push(pitch - open);
+            pitch += 1;
+        }
+        notes.extend(frets.iter().zip(three_finger_pattern(&frets)).map(|(fret, finger)| (FretPos { string, fret: *fret }, finger)));
+    }
+    notes
+}
+// index finger on the first note and pinky on the last; the middle note takes
+// whichever of 2 or 3 sits closer; open strings are played with no finger (0)
+// and the rest one finger per fret, slid up so the last note lands on the pinky
+fn three_finger_pattern(frets: &[usize]) -> Vec<Finger> {
+    let finger = |number, stretch| Finger { number, stretch };
+    match frets {
+        [0, rest @ ..] => {
+            let shift = rest.last().map_or(0, |f| f.saturating_sub(4));
+            std::iter::once(Finger::open()).chain(rest.iter().map(|f| finger(f.saturating_sub(shift).max(1), false))).collect()
+        },
+        [a, b, c] => vec![
+            finger(1, false),
+            finger(match b - a <= c - b { true => 2, false => 3 }, false),
+            finger(4, c - a > 3),
+        ],
+        _ => (1..=frets.len()).map(|n| finger(n, false)).collect(),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruments::Instrument;
+    use crate::scales::{Naming, Numbering, ScaleType};
+
+    fn tuning(name: &str) -> Vec<usize> {
+        Instrument::guitar().tunings.into_iter().find(|t| t.name == name).unwrap().strings
+    }
+    fn major(key: usize, key_letter: usize) -> Scale {
+        Scale { typ: ScaleType::Major, siz: ScaleSize::Diatonic, key, key_letter, numbering: Numbering::Simple, naming: Naming::English }
+    }
+    // (string, fret, finger label) for one pattern
+    fn pattern(scale: &Scale, strings: &[usize], index: usize) -> Vec<(usize, usize, String)> {
+        three_notes_per_string(scale, strings, index).iter().map(|(at, f)| (at.string, at.fret, f.label())).collect()
+    }
+    fn on_string(notes: &[(usize, usize, String)], string: usize) -> Vec<(usize, &str)> {
+        notes.iter().filter(|n| n.0 == string).map(|n| (n.1, n.2.as_str())).collect()
+    }
+
+    fn minor_pentatonic(key: usize, key_letter: usize) -> Scale {
+        Scale { typ: ScaleType::Minor, siz: ScaleSize::Pentatonic, ..major(key, key_letter) }
+    }
+    fn frets(notes: &[FretPos]) -> Vec<(usize, usize)> {
+        notes.iter().map(|at| (at.string, at.fret)).collect()
+    }
+
+    #[test]
+    fn a_minor_pentatonic_boxes() {
+        let scale = minor_pentatonic(9, 5);
+        let strings = tuning("Standard");
+        let boxes = [
+            [(0, 5), (0, 8), (1, 5), (1, 7), (2, 5), (2, 7), (3, 5), (3, 7), (4, 5), (4, 8), (5, 5), (5, 8)],
+            [(0, 8), (0, 10), (1, 7), (1, 10), (2, 7), (2, 10), (3, 7), (3, 9), (4, 8), (4, 10), (5, 8), (5, 10)],
+            [(0, 10), (0, 12), (1, 10), (1, 12), (2, 10), (2, 12), (3, 9), (3, 12), (4, 10), (4, 13), (5, 10), (5, 12)],
+            // the fourth box starts on the open E rather than at the 12th fret
+            [(0, 0), (0, 3), (1, 0), (1, 3), (2, 0), (2, 2), (3, 0), (3, 2), (4, 1), (4, 3), (5, 0), (5, 3)],
+            [(0, 3), (0, 5), (1, 3), (1, 5), (2, 2), (2, 5), (3, 2), (3, 5), (4, 3), (4, 5), (5, 3), (5, 5)],
+        ];
+        for (index, expected) in boxes.iter().enumerate() {
+            assert_eq!(frets(&pentatonic_box(&scale, &strings, index)), expected, "box {}", index + 1);
+            // at pentatonic size the full box is just the skeleton
+            assert_eq!(frets(&box_notes(&scale, &strings, index)), expected, "box {}", index + 1);
+        }
+        assert_eq!(pentatonic_box(&scale, &strings, BOXES), pentatonic_box(&scale, &strings, 0));
+    }
+    #[test]
+    fn lefty_boxes_mirror_the_strings() {
+        let last = tuning("Standard").len() - 1;
+        for scale in [minor_pentatonic(9, 5), major(7, 4)] {
+            for index in 0..BOXES {
+                let mirrored: Vec<_> = frets(&box_notes(&scale, &tuning("Standard"), index)).into_iter().map(|(s, fret)| (last - s, fret)).collect();
+                assert_eq!(frets(&box_notes(&scale, &tuning("Standard (lefty)"), index)), mirrored);
+            }
+        }
+    }
+    #[test]
+    fn windows_step_within_the_fretboard() {
+        let window = Position::Window { from: 0, to: 4 };
+        assert_eq!(window.step(false, 12), window);
+        assert_eq!(window.step(true, 12), Position::Window { from: 1, to: 5 });
+        let last = Position::Window { from: 8, to: 12 };
+        assert_eq!(last.step(true, 12), last);
+        assert_eq!(last.step(false, 12), Position::Window { from: 7, to: 11 });
+        assert_eq!(Position::Box(4).step(true, 12), Position::Box(0));
+    }
+    #[test]
+    fn standard_shifts_a_fret_for_the_major_third() {
+        let notes = pattern(&major(7, 4), &tuning("Standard"), 0);
+        assert_eq!(on_string(&notes, 0), [(3, "1"), (5, "2"), (7, "4+")]);
+        assert_eq!(on_string(&notes, 1), [(3, "1"), (5, "2"), (7, "4+")]);
+        assert_eq!(on_string(&notes, 2), [(4, "1"), (5, "2"), (7, "4")]);
+        assert_eq!(on_string(&notes, 3), [(4, "1"), (5, "2"), (7, "4")]);
+        // G to B is a third, so the B string starts a fret higher
+        assert_eq!(on_string(&notes, 4), [(5, "1"), (7, "3"), (8, "4")]);
+        assert_eq!(on_string(&notes, 5), [(5, "1"), (7, "3"), (8, "4")]);
+    }
+    #[test]
+    fn every_pattern_walks_the_scale_without_gaps() {
+        let scale = major(7, 4);
+        for name in ["Standard", "Standard (lefty)", "DADGAD", "OPEN G"] {
+            let strings = tuning(name);
+            for index in 0..PATTERNS_3NPS {
+                let pitches: Vec<usize> = three_notes_per_string(&scale, &strings, index).iter().map(|(at, _)| at.note(&strings)).collect();
+                let expected: Vec<usize> = (pitches[0]..=*pitches.last().unwrap()).filter(|p| scale.is_note_in_scale(*p as i16)).collect();
+                assert_eq!(pitches, expected, "{name} pattern {}", index + 1);
+            }
+        }
+    }
+    #[test]
+    fn open_tunings_use_open_strings_and_take_a_fourth_note_before_a_fifth() {
+        let notes = pattern(&major(2, 1), &tuning("DADGAD"), 0);
+        // D E F♯ G on the low D: the A string is a fifth up
+        assert_eq!(on_string(&notes, 0), [(0, "0"), (2, "1"), (4, "3"), (5, "4")]);
+        assert_eq!(on_string(&notes, 1), [(0, "0"), (2, "2"), (4, "4")]);
+        assert_eq!(on_string(&notes, 4), [(4, "1"), (5, "2"), (7, "4")]);
+
+        let notes = pattern(&major(7, 4), &tuning("OPEN G"), 4);
+        assert_eq!(on_string(&notes, 0), [(0, "0"), (2, "2"), (4, "4")]);
+        // G A B C on the G string: the next D is a fifth up
+        assert_eq!(on_string(&notes, 1), [(0, "0"), (2, "1"), (4, "3"), (5, "4")]);
+        assert_eq!(on_string(&notes, 2), [(0, "0"), (2, "2"), (4, "4")]);
+        assert_eq!(on_string(&notes, 4), [(1, "1"), (3, "2"), (5, "4+")]);
+    }
+    #[test]
+    fn lefty_patterns_mirror_the_strings() {
+        let scale = major(7, 4);
+        let last = tuning("Standard").len() - 1;
+        for index in 0..PATTERNS_3NPS {
+            let mirrored: Vec<_> = pattern(&scale, &tuning("Standard"), index).into_iter().map(|(s, fret, f)| (last - s, fret, f)).collect();
+            assert_eq!(pattern(&scale, &tuning("Standard (lefty)"), index), mirrored);
+        }
+    }
+}
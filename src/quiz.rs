@@ -0,0 +1,311 @@
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::arpeggios::{diatonic_chord, Arpeggio, ChordQuality};
+use crate::instruments::FretPos;
+use crate::rng::Rng;
+use crate::scales::{Scale, TOTAL_TONES};
+
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+pub enum QuizKind {
+    Intervals,
+    NoteNames,
+    FindAll,
+    ScaleDegrees,
+    Chords,
+}
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+impl Difficulty {
+    pub fn max_fret(&self, frets: usize) -> usize {
+        match self {
+            Difficulty::Easy => frets.min(5),
+            Difficulty::Medium => frets.min(12),
+            Difficulty::Hard => frets,
+        }
+    }
+    pub fn choices(&self) -> usize {
+        match self {
+            Difficulty::Easy => 3,
+            Difficulty::Medium => 4,
+            Difficulty::Hard => 6,
+        }
+    }
+    // the harder level also asks about notes outside the current scale
+    pub fn scale_only(&self) -> bool {
+        *self != Difficulty::Hard
+    }
+}
+#[derive(Debug, PartialEq, Clone)]
+pub enum Question {
+    Interval { from: FretPos, to: FretPos },
+    NoteName { at: FretPos },
+    FindAll { note: usize, found: Vec<FretPos>, total: usize },
+    ScaleDegree { at: FretPos },
+    Chord { notes: Vec<FretPos>, ask: ChordAsk },
+}
+// what a chord question wants named
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ChordAsk {
+    Quality,
+    Root,
+}
+#[derive(Debug, PartialEq, Clone)]
+pub struct Feedback {
+    pub correct: bool,
+    // the question answered, which says how the answer reads
+    pub question: Question,
+    pub answer: usize,
+}
+pub struct Quiz {
+    pub kind: QuizKind,
+    pub difficulty: Difficulty,
+    pub question: Option<Question>,
+    pub options: Vec<usize>,
+    pub answer: usize,
+    pub score: usize,
+    pub asked: usize,
+    pub streak: usize,
+    pub best_streak: usize,
+    pub feedback: Option<Feedback>,
+    rng: Rng,
+}
+impl Default for Quiz {
+    fn default() -> Self {
+        Self {
+            kind: QuizKind::NoteNames,
+            difficulty: Difficulty::Easy,
+            question: None,
+            options: vec![],
+            answer: 0,
+            score: 0,
+            asked: 0,
+            streak: 0,
+            best_streak: 0,
+            feedback: None,
+            rng: Rng::new(0x5eed),
+        }
+    }
+}
+impl Quiz {
+    pub fn is_running(&self) -> bool {
+        self.question.is_some()
+    }
+    pub fn start(&mut self, seed: u64, strings: &[usize], frets: usize, scale: &Scale) {
+        self.rng.seed(seed);
+        self.score = 0;
+        self.asked = 0;
+        self.streak = 0;
+        self.feedback = None;
+        self.next(strings, frets, scale);
+    }
+    pub fn stop(&mut self) {
+        self.question = None;
+        self.options.clear();
+    }
+    pub fn next(&mut self, strings: &[usize], frets: usize, scale: &Scale) {
+        let max_fret = self.difficulty.max_fret(frets);
+        let positions: Vec<FretPos> = (0..strings.len())
+            .flat_map(|string| (0..=max_fret).map(move |fret| FretPos { string, fret }))
+            .filter(|p| !self.difficulty.scale_only() || scale.is_note_in_scale(p.note(strings) as i16))
+            .collect();
+        let pitch_classes: Vec<usize> = match self.difficulty.scale_only() {
+            true => scale.notes().iter().map(|n| (n + scale.key) % TOTAL_TONES).collect(),
+            false => (0..TOTAL_TONES).collect(),
+        };
+        let degrees: Vec<usize> = match self.difficulty.scale_only() {
+            true => scale.notes(),
+            false => (0..TOTAL_TONES).collect(),
+        };
+        let Some(at) = self.rng.pick(&positions) else {
+            self.stop();
+            return;
+        };
+        let note = at.note(strings);
+        let (question, answer, pool) = match self.kind {
+            QuizKind::NoteNames => (Question::NoteName { at }, note % TOTAL_TONES, pitch_classes),
+            QuizKind::ScaleDegrees => (Question::ScaleDegree { at }, degree_of(scale, note), degrees),
+            QuizKind::FindAll => {
+                let target = note % TOTAL_TONES;
+                let total = (0..strings.len())
+                    .flat_map(|string| (0..=max_fret).map(move |fret| FretPos { string, fret }))
+                    .filter(|p| p.note(strings) % TOTAL_TONES == target)
+                    .count();
+                (Question::FindAll { note: target, found: vec![], total }, target, vec![])
+            },
+            QuizKind::Chords => {
+                let chords = self.chords(scale);
+                let mut shapes: Vec<(usize, ChordQuality, Vec<FretPos>)> = chords.iter().flat_map(|(root, quality)| {
+                    (0..=max_fret).map(|start_fret| {
+                        let arpeggio = Arpeggio { root: *root, quality: *quality, start_fret, ..Arpeggio::default() };
+                        (*root, *quality, arpeggio.chord(strings, max_fret))
+                    })
+                })
+                // within the frets asked about, and holding every note of the chord
+                .filter(|(_, quality, notes)| {
+                    let mut classes: Vec<usize> = notes.iter().map(|at| at.note(strings) % TOTAL_TONES).collect();
+                    classes.sort();
+                    classes.dedup();
+                    notes.iter().all(|at| at.fret <= max_fret) && classes.len() == quality.intervals().len()
+                })
+                .collect();
+                shapes.dedup();
+                if shapes.is_empty() {
+                    self.stop();
+                    return;
+                }
+                let (root, quality, notes) = shapes.swap_remove(self.rng.below(shapes.len()));
+                match self.rng.below(2) {
+                    0 => {
+                        let qualities: Vec<usize> = ChordQuality::iter().enumerate()
+                            .filter(|(_, q)| self.difficulty != Difficulty::Easy || q.intervals().len() == 3)
+                            .map(|(i, _)| i)
+                            .collect();
+                        let answer = ChordQuality::iter().position(|q| q == quality).unwrap_or(0);
+                        (Question::Chord { notes, ask: ChordAsk::Quality }, answer, qualities)
+                    },
+                    _ => {
+                        let mut roots: Vec<usize> = chords.iter().map(|(root, _)| *root).collect();
+                        roots.sort();
+                        roots.dedup();
+                        (Question::Chord { notes, ask: ChordAsk::Root }, root, roots)
+                    },
+                }
+            },
+            QuizKind::Intervals => {
+                let above = |from: usize| {
+                    positions
+                        .iter()
+                        .copied()
+                        .filter(move |p| (from + 1..=from + TOTAL_TONES).contains(&p.note(strings)))
+                };
+                let starts: Vec<FretPos> = positions
+                    .iter()
+                    .copied()
+                    .filter(|p| above(p.note(strings)).next().is_some())
+                    .collect();
+                let Some(from) = self.rng.pick(&starts) else {
+                    self.stop();
+                    return;
+                };
+                let targets: Vec<FretPos> = above(from.note(strings)).collect();
+                let to = self.rng.pick(&targets).unwrap_or(from);
+                (Question::Interval { from, to }, to.note(strings) - from.note(strings), (1..=TOTAL_TONES).collect())
+            },
+        };
+        self.question = Some(question);
+        self.answer = answer;
+        self.options = self.choose_options(answer, pool);
+    }
+    // the chords asked about by root and quality: the scale's triads, adding its seventh chords
+    // on the medium level, and any chord on any root on the hard one
+    fn chords(&self, scale: &Scale) -> Vec<(usize, ChordQuality)> {
+        let diatonic = scale.typ.diatonic();
+        let on_degrees = |notes: usize| (0..diatonic.len()).filter_map(|degree| {
+            diatonic_chord(&diatonic, degree, notes).map(|quality| ((scale.key + diatonic[degree]) % TOTAL_TONES, quality))
+        }).collect::<Vec<_>>();
+        match self.difficulty {
+            Difficulty::Easy => on_degrees(3),
+            Difficulty::Medium => [on_degrees(3), on_degrees(4)].concat(),
+            Difficulty::Hard => (0..TOTAL_TONES).flat_map(|root| ChordQuality::iter().map(move |quality| (root, quality))).collect(),
+        }
+    }
+    fn choose_options(&mut self, answer: usize, mut pool: Vec<usize>) -> Vec<usize> {
+        pool.retain(|n| *n != answer);
+        self.rng.shuffle(&mut pool);
+        pool.truncate(self.difficulty.choices() - 1);
+        pool.push(answer);
+        self.rng.shuffle(&mut pool);
+        pool
+    }
+    pub fn answer(&mut self, value: usize, strings: &[usize], frets: usize, scale: &Scale) {
+        let correct = value == self.answer;
+        self.record(correct);
+        self.next(strings, frets, scale);
+    }
+    // only used by FindAll: each click either finds another position or counts as a miss
+    pub fn click(&mut self, at: FretPos, strings: &[usize], frets: usize, scale: &Scale) {
+        let Some(Question::FindAll { note, found, total }) = &mut self.question else {
+            return;
+        };
+        let is_match = at.note(strings) % TOTAL_TONES == *note && at.fret <= self.difficulty.max_fret(frets);
+        if !is_match {
+            self.record(false);
+            return;
+        }
+        if !found.contains(&at) {
+            found.push(at);
+        }
+        if found.len() >= *total {
+            self.record(true);
+            self.next(strings, frets, scale);
+        }
+    }
+    fn record(&mut self, correct: bool) {
+        self.asked += 1;
+        match correct {
+            true => {
+                self.score += 1;
+                self.streak += 1;
+                self.best_streak = self.best_streak.max(self.streak);
+            },
+            false => self.streak = 0,
+        }
+        if let Some(question) = &self.question {
+            self.feedback = Some(Feedback {
+                correct,
+                question: question.clone(),
+                answer: self.answer,
+            });
+        }
+    }
+}
+fn degree_of(scale: &Scale, note: usize) -> usize {
+    (note % TOTAL_TONES + TOTAL_TONES - scale.key % TOTAL_TONES) % TOTAL_TONES
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scales::{Naming, Numbering, ScaleSize, ScaleType};
+
+    const GUITAR: [usize; 6] = [4, 9, 14, 19, 23, 28];
+
+    #[test]
+    fn chord_questions_show_the_chord_they_ask_about() {
+        // A minor
+        let scale = Scale { typ: ScaleType::Minor, siz: ScaleSize::Pentatonic, key: 9, key_letter: 5, numbering: Numbering::Simple, naming: Naming::English };
+        for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+            let mut quiz = Quiz { kind: QuizKind::Chords, difficulty, ..Quiz::default() };
+            quiz.start(7, &GUITAR, 12, &scale);
+            for _ in 0..50 {
+                let Some(Question::Chord { notes, ask }) = quiz.question.clone() else {
+                    panic!("no chord question on {:?}", difficulty);
+                };
+                assert!(quiz.options.contains(&quiz.answer));
+                assert_eq!(quiz.options.len(), difficulty.choices());
+                assert!(notes.iter().all(|at| at.fret <= difficulty.max_fret(12)));
+                let root = notes[0].note(&GUITAR) % TOTAL_TONES;
+                let mut intervals: Vec<usize> = notes.iter().map(|at| (at.note(&GUITAR) + TOTAL_TONES - root) % TOTAL_TONES).collect();
+                intervals.sort();
+                intervals.dedup();
+                match ask {
+                    ChordAsk::Root => assert_eq!(quiz.answer, root),
+                    ChordAsk::Quality => {
+                        let quality = ChordQuality::iter().nth(quiz.answer).unwrap();
+                        assert_eq!(intervals, quality.intervals());
+                        if difficulty != Difficulty::Hard {
+                            assert!(scale.typ.diatonic().contains(&((root + TOTAL_TONES - scale.key) % TOTAL_TONES)));
+                        }
+                    },
+                }
+                let answer = quiz.answer;
+                quiz.answer(answer, &GUITAR, 12, &scale);
+            }
+            assert_eq!((quiz.score, quiz.asked), (50, 50));
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use egui::{Align, Align2, Color32, FontId, Pos2, Rect, Stroke, Vec2};
+use crate::canvas::Canvas;
+
+// a picture drawn in memory without a window or GPU, for PNG files and printed pages
+pub struct Raster {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color32>,
+    // the first font that has a character draws it
+    fonts: Vec<FontArc>,
+    // drawing coordinates are scaled and then offset into pixels
+    scale: f32,
+    offset: Vec2,
+    clip_rect: Rect,
+}
+impl Raster {
+    pub fn new(width: usize, height: usize, background: Color32, fonts: Vec<FontArc>) -> Raster {
+        Raster {
+            width,
+            height,
+            pixels: vec![background; width * height],
+            fonts,
+            scale: 1f32,
+            offset: Vec2::ZERO,
+            clip_rect: Rect::EVERYTHING,
+        }
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    // draw what's in area into the pixels of target, as large as fits without stretching and centred
+    pub fn fit(&mut self, area: Rect, target: Rect) {
+        self.scale = (target.width() / area.width()).min(target.height() / area.height());
+        self.offset = target.center().to_vec2() - area.center().to_vec2() * self.scale;
+    }
+    fn to_pixels(&self, pos: Pos2) -> Pos2 {
+        pos * self.scale + self.offset
+    }
+    // the pixels a shape may touch, inside the picture and the clip
+    fn bounds(&self, rect: Rect) -> (usize, usize, usize, usize) {
+        let rect = rect.intersect(self.clip_rect).intersect(Rect::from_min_size(Pos2::ZERO, Vec2::new(self.width as f32, self.height as f32)));
+        match rect.is_positive() {
+            true => (rect.left().floor() as usize, rect.top().floor() as usize, rect.right().ceil() as usize, rect.bottom().ceil() as usize),
+            false => (0, 0, 0, 0),
+        }
+    }
+    // cover each pixel in rect by how much of it is inside the shape, given a pixel's centre
+    fn cover(&mut self, rect: Rect, color: Color32, coverage: impl Fn(Pos2) -> f32) {
+        if color == Color32::TRANSPARENT {
+            return;
+        }
+        let (left, top, right, bottom) = self.bounds(rect.expand(1f32));
+        for y in top..bottom {
+            for x in left..right {
+                let amount = coverage(Pos2::new(x as f32 + 0.5, y as f32 + 0.5)).clamp(0f32, 1f32);
+                self.blend(x, y, color, amount);
+            }
+        }
+    }
+    // colours are premultiplied, so laying one over another is a sum
+    fn blend(&mut self, x: usize, y: usize, color: Color32, amount: f32) {
+        if amount <= 0f32 {
+            return;
+        }
+        let pixel = &mut self.pixels[y * self.width + x];
+        let source = color.to_array().map(|c| c as f32 * amount);
+        let keep = 1f32 - source[3] / 255f32;
+        let target = pixel.to_array();
+        let mixed: [u8; 4] = std::array::from_fn(|i| (source[i] + target[i] as f32 * keep).round().min(255f32) as u8);
+        *pixel = Color32::from_rgba_premultiplied(mixed[0], mixed[1], mixed[2], mixed[3]);
+    }
+    // each character in the first font that has it
+    fn font_for(&self, c: char) -> Option<&FontArc> {
+        self.fonts.iter().find(|f| f.glyph_id(c).0 != 0).or(self.fonts.first())
+    }
+    pub fn png(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data: Vec<u8> = self.pixels.iter().flat_map(|p| p.to_srgba_unmultiplied()).collect();
+        encoder.write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .map_err(|e| e.to_string())?;
+        Ok(bytes)
+    }
+    // the pixels as rows of red, green and blue bytes, for pages where nothing shows through
+    pub fn rgb(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|p| {
+            let [r, g, b, _] = p.to_srgba_unmultiplied();
+            [r, g, b]
+        }).collect()
+    }
+}
+impl Canvas for Raster {
+    fn line(&mut self, from: Pos2, to: Pos2, stroke: Stroke) {
+        let (from, to) = (self.to_pixels(from), self.to_pixels(to));
+        let width = stroke.width * self.scale;
+        let length = from.distance(to);
+        if length == 0f32 {
+            return;
+        }
+        let along = (to - from) / length;
+        // hairlines fade instead of thinning below a pixel
+        let (half, strength) = (width.max(1f32) / 2f32, width.min(1f32));
+        self.cover(Rect::from_two_pos(from, to).expand(half), stroke.color, |p| {
+            let t = (p - from).dot(along);
+            let across = (p - from).dot(along.rot90()).abs();
+            (half - across + 0.5).clamp(0f32, 1f32) * (t + 0.5).min(length - t + 0.5).clamp(0f32, 1f32) * strength
+        });
+    }
+    fn circle(&mut self, center: Pos2, radius: f32, fill: Color32, stroke: Stroke) {
+        let center = self.to_pixels(center);
+        let radius = radius * self.scale;
+        let bounds = Rect::from_center_size(center, Vec2::splat(2f32 * radius));
+        self.cover(bounds, fill, |p| radius - p.distance(center) + 0.5);
+        if !stroke.is_empty() {
+            let half = stroke.width * self.scale / 2f32;
+            self.cover(bounds.expand(half), stroke.color, |p| half - (p.distance(center) - radius).abs() + 0.5);
+        }
+    }
+    fn rect(&mut self, rect: Rect, radius: f32, fill: Color32, stroke: Stroke) {
+        let rect = Rect::from_two_pos(self.to_pixels(rect.min), self.to_pixels(rect.max));
+        let radius = (radius * self.scale).min(rect.width().min(rect.height()) / 2f32);
+        // how far outside the rounded rect a point is, negative inside it
+        let distance = |p: Pos2| {
+            let q = (p - rect.center()).abs() - (rect.size() / 2f32 - Vec2::splat(radius));
+            q.max(Vec2::ZERO).length() + q.x.max(q.y).min(0f32) - radius
+        };
+        self.cover(rect, fill, |p| 0.5 - distance(p));
+        if !stroke.is_empty() {
+            let half = stroke.width * self.scale / 2f32;
+            self.cover(rect.expand(half), stroke.color, |p| half - distance(p).abs() + 0.5);
+        }
+    }
+    fn text(&mut self, pos: Pos2, anchor: Align2, text: &str, font: FontId, color: Color32) {
+        let Some(first) = self.fonts.first() else {
+            return;
+        };
+        let size = PxScale::from(font.size * self.scale);
+        let metrics = first.as_scaled(size);
+        let (ascent, height) = (metrics.ascent(), metrics.ascent() - metrics.descent() + metrics.line_gap());
+        let mut glyphs = vec![];
+        let mut x = 0f32;
+        for c in text.chars() {
+            if let Some(font) = self.font_for(c) {
+                let scaled = font.as_scaled(size);
+                let id = scaled.glyph_id(c);
+                glyphs.push((font.clone(), id.with_scale_and_position(size, ab_glyph::point(x, 0f32))));
+                x += scaled.h_advance(id);
+            }
+        }
+        let pos = self.to_pixels(pos);
+        let left = match anchor.x() {
+            Align::Min => pos.x,
+            Align::Center => pos.x - x / 2f32,
+            Align::Max => pos.x - x,
+        };
+        let top = match anchor.y() {
+            Align::Min => pos.y,
+            Align::Center => pos.y - height / 2f32,
+            Align::Max => pos.y - height,
+        };
+        for (font, mut glyph) in glyphs {
+            glyph.position = ab_glyph::point(left + glyph.position.x, top + ascent);
+            if let Some(outline) = font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                let mut coverage = vec![];
+                outline.draw(|x, y, amount| coverage.push((x, y, amount)));
+                for (x, y, amount) in coverage {
+                    let (x, y) = (bounds.min.x as i32 + x as i32, bounds.min.y as i32 + y as i32);
+                    let inside = x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height
+                        && self.clip_rect.contains(Pos2::new(x as f32 + 0.5, y as f32 + 0.5));
+                    if inside {
+                        self.blend(x as usize, y as usize, color, amount);
+                    }
+                }
+            }
+        }
+    }
+    fn clip(&mut self, rect: Option<Rect>) {
+        self.clip_rect = rect.map_or(Rect::EVERYTHING, |r| Rect::from_two_pos(self.to_pixels(r.min), self.to_pixels(r.max)));
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+        let mut reader = png::Decoder::new(bytes).read_info().expect("a PNG header");
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).expect("a PNG image");
+        (info, pixels)
+    }
+
+    #[test]
+    fn png_decodes_at_its_size() {
+        let mut raster = Raster::new(40, 30, Color32::WHITE, vec![]);
+        raster.rect(Rect::from_min_size(Pos2::new(10f32, 10f32), Vec2::new(10f32, 10f32)), 0f32, Color32::RED, Stroke::NONE);
+        let (info, pixels) = decode(&raster.png().unwrap());
+        assert_eq!((info.width, info.height), (40, 30));
+        assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Rgba, png::BitDepth::Eight));
+        let at = |x: usize, y: usize| &pixels[(y * 40 + x) * 4..][..4];
+        assert_eq!(at(0, 0), [255, 255, 255, 255]);
+        assert_eq!(at(15, 15), [255, 0, 0, 255]);
+    }
+    #[test]
+    fn fit_scales_into_the_pixels() {
+        let mut raster = Raster::new(200, 100, Color32::TRANSPARENT, vec![]);
+        // a 20 by 10 area drawn ten times larger and centred
+        raster.fit(Rect::from_min_size(Pos2::ZERO, Vec2::new(20f32, 10f32)), Rect::from_min_size(Pos2::ZERO, Vec2::new(200f32, 100f32)));
+        raster.rect(Rect::from_min_size(Pos2::new(1f32, 1f32), Vec2::new(2f32, 2f32)), 0f32, Color32::BLUE, Stroke::NONE);
+        let (info, pixels) = decode(&raster.png().unwrap());
+        assert_eq!((info.width, info.height), (200, 100));
+        let alpha = |x: usize, y: usize| pixels[(y * 200 + x) * 4 + 3];
+        assert_eq!(alpha(20, 20), 255);
+        assert_eq!(alpha(5, 5), 0);
+        assert_eq!(alpha(35, 35), 0);
+    }
+}
@@ -0,0 +1,36 @@
+// xorshift64*, plenty for picking quiz questions without pulling in a crate
+pub struct Rng(u64);
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+    pub fn seed(&mut self, seed: u64) {
+        self.0 ^= seed.max(1);
+        if self.0 == 0 {
+            self.0 = 1;
+        }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+    pub fn below(&mut self, n: usize) -> usize {
+        match n {
+            0 => 0,
+            _ => (self.next_u64() % n as u64) as usize,
+        }
+    }
+    pub fn pick<T: Copy>(&mut self, items: &[T]) -> Option<T> {
+        match items.is_empty() {
+            true => None,
+            false => Some(items[self.below(items.len())]),
+        }
+    }
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
@@ -1,9 +1,11 @@
 #![macro_use]
 
 use egui::Color32;
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use crate::fretboard::{NoteMarker,NoteColors};
+use crate::i18n::label;
 
 #[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
 pub enum ScaleSize {
@@ -17,11 +19,24 @@ pub enum ScaleSize {
 pub enum ScaleType {
     Minor,
     Major,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+}
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+pub enum Numbering {
+    Simple,
+    Extended,
 }
 pub struct Scale {
     pub typ: ScaleType,
     pub siz: ScaleSize,
     pub key: usize,
+    pub key_letter: usize,
+    pub numbering: Numbering,
+    pub naming: Naming,
 }
 pub struct Bubble {
     pub color: Color32,
@@ -54,36 +69,232 @@ pub enum NoteType {
     NotInScale,
 }
 pub const TOTAL_TONES:usize = 12;
-const NOTE_LETTERS_SHARP: [&'static str; TOTAL_TONES] = ["C","C♯","D","D♯","E","F","F♯","G","G♯","A","A♯","B"];
-const NOTE_LETTERS_FLAT: [&'static str; TOTAL_TONES] = ["C","D♭","D","E♭","E","F","G♭","G","A♭","A","B♭","B"];
-const NOTE_NUMBERS: [&'static str; TOTAL_TONES] = ["R","b2","2","b3","3","4","b5","5","b6","6","b7","7"];
+const MAJOR_TONES: [usize; 7] = [0,2,4,5,7,9,11];
+// simple intervals, then the compound ones up to two octaves (9ths, 11ths, 13ths)
+const INTERVAL_NAMES: [&str; 2 * TOTAL_TONES + 1] = [
+    "P1","m2","M2","m3","M3","P4","TT","P5","m6","M6","m7","M7","P8",
+    "m9","M9","m10","M10","P11","A11","P12","m13","M13","m14","M14","P15",
+];
+pub const LETTERS: [&str; 7] = ["C","D","E","F","G","A","B"];
+const GERMAN: [&str; 7] = ["C","D","E","F","G","A","H"];
+const SOLFEGE: [&str; 7] = ["Do","Re","Mi","Fa","Sol","La","Si"];
+const MOVABLE_DO: [&str; 7] = ["Do","Re","Mi","Fa","Sol","La","Ti"];
+const SARGAM: [&str; 7] = ["Sa","Re","Ga","Ma","Pa","Dha","Ni"];
+const IROHA: [&str; 7] = ["Ha","Ni","Ho","He","To","I","Ro"];
+pub const LETTER_TONES: [usize; 7] = MAJOR_TONES;
+// how many letters above the tonic a chromatic note is spelled: b2 2 b3 3 4 b5 5 b6 6 b7 7
+const DEGREE_STEPS: [usize; TOTAL_TONES] = [0,1,1,2,2,3,4,4,5,5,6,6];
+// letters in the order sharps are added to a key signature: F C G D A E B
+const SHARP_ORDER: [usize; 7] = [3,0,4,1,5,2,6];
 const BLANK:&str = "";
-pub fn note_letter(i:usize, prefer_flats:bool) -> String {
-    if i >= TOTAL_TONES {
-        return String::from(BLANK);
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+pub enum Naming {
+    English,
+    German,
+    FixedDo,
+    MovableDo,
+    Sargam,
+    Iroha,
+}
+fn accidentals(accidental:i32) -> String {
+    match accidental >= 0 {
+        true => "♯".repeat(accidental as usize),
+        false => "♭".repeat(-accidental as usize),
+    }
+}
+impl Naming {
+    // movable systems name degrees, so a note on its own gets the fixed equivalent
+    fn absolute(&self) -> Naming {
+        match self {
+            Naming::MovableDo => Naming::FixedDo,
+            Naming::Sargam => Naming::English,
+            naming => *naming,
+        }
+    }
+    fn is_movable(&self) -> bool {
+        *self != self.absolute()
+    }
+    // a note from its letter (0 = C ... 6 = B) and accidental (negative for flats)
+    fn name(&self, letter:usize, accidental:i32) -> String {
+        let letter = letter % 7;
+        match self.absolute() {
+            Naming::German => match (letter, accidental) {
+                (6, -1) => String::from("B"),
+                (2 | 5, a) if a < 0 => format!("{}s{}", GERMAN[letter], "es".repeat(-a as usize - 1)),
+                (_, a) if a >= 0 => format!("{}{}", GERMAN[letter], "is".repeat(a as usize)),
+                (_, a) => format!("{}{}", GERMAN[letter], "es".repeat(-a as usize)),
+            },
+            Naming::FixedDo => format!("{}{}", SOLFEGE[letter], accidentals(accidental)),
+            // 嬰 (ei) raises and 変 (hen) lowers, written before the note
+            Naming::Iroha => match accidental {
+                0 => String::from(IROHA[letter]),
+                1 => format!("Ei-{}", IROHA[letter].to_lowercase()),
+                -1 => format!("Hen-{}", IROHA[letter].to_lowercase()),
+                a if a > 0 => format!("Jūei-{}", IROHA[letter].to_lowercase()),
+                _ => format!("Jūhen-{}", IROHA[letter].to_lowercase()),
+            },
+            _ => format!("{}{}", LETTERS[letter], accidentals(accidental)),
+        }
+    }
+    // a degree above the tonic (0 = tonic ... 6 = seventh) and its accidental against the major scale
+    fn degree_name(&self, step:usize, accidental:i32) -> String {
+        match (self, step, accidental) {
+            (Naming::MovableDo, _, 0) => String::from(MOVABLE_DO[step]),
+            (Naming::MovableDo, 0, 1) => String::from("Di"),
+            (Naming::MovableDo, 1, 1) => String::from("Ri"),
+            (Naming::MovableDo, 3, 1) => String::from("Fi"),
+            (Naming::MovableDo, 4, 1) => String::from("Si"),
+            (Naming::MovableDo, 5, 1) => String::from("Li"),
+            (Naming::MovableDo, 1, -1) => String::from("Ra"),
+            (Naming::MovableDo, 2, -1) => String::from("Me"),
+            (Naming::MovableDo, 4, -1) => String::from("Se"),
+            (Naming::MovableDo, 5, -1) => String::from("Le"),
+            (Naming::MovableDo, 6, -1) => String::from("Te"),
+            (Naming::MovableDo, _, a) => format!("{}{}", MOVABLE_DO[step], accidentals(a)),
+            // komal (lowered) swaras in lower case; Pa never moves, so a ♭5 is tivra Ma
+            (_, _, 0) => String::from(SARGAM[step]),
+            (_, 3, 1) | (_, 4, -1) => String::from("Ma'"),
+            (_, _, -1) => SARGAM[step].to_lowercase(),
+            (_, _, a) => format!("{}{}", SARGAM[step], accidentals(a)),
+        }
+    }
+}
+// a pitch class as a natural, else a single sharp or flat
+pub fn chromatic_spelling(i:usize, prefer_flats:bool) -> (usize, i32) {
+    let letter_of = |n:usize| LETTER_TONES.iter().position(|t| *t == n % TOTAL_TONES);
+    match (letter_of(i), prefer_flats) {
+        (Some(letter), _) => (letter, 0),
+        (None, true) => (letter_of(i + 1).unwrap_or(0), -1),
+        (None, false) => (letter_of(i + TOTAL_TONES - 1).unwrap_or(0), 1),
     }
-    match prefer_flats {
-        true => String::from(NOTE_LETTERS_FLAT[i]),
-        false => String::from(NOTE_LETTERS_SHARP[i]),
+}
+// how far a pitch class is from the natural of the given letter (0 = C ... 6 = B), if it can be spelled with it
+fn accidental(letter:usize, n:usize) -> Option<i32> {
+    match (n % TOTAL_TONES + TOTAL_TONES - LETTER_TONES[letter % 7]) % TOTAL_TONES {
+        0 => Some(0),
+        1 => Some(1),
+        2 => Some(2),
+        10 => Some(-2),
+        11 => Some(-1),
+        _ => None,
+    }
+}
+// spell a pitch class with the given letter, using double sharps/flats if needed
+pub fn spell(letter:usize, n:usize, naming:Naming) -> Option<String> {
+    accidental(letter, n).map(|a| naming.absolute().name(letter, a))
+}
+// the usual letter for a key: the natural one, else the neighbour the key signature prefers
+pub fn key_letter(typ:ScaleType, key:usize) -> usize {
+    let letter_of = |n:usize| LETTER_TONES.iter().position(|t| *t == n % TOTAL_TONES);
+    match (letter_of(key), prefers_flats(typ, key % TOTAL_TONES)) {
+        (Some(letter), _) => letter,
+        (None, true) => letter_of(key + 1).unwrap_or(0),
+        (None, false) => letter_of(key + TOTAL_TONES - 1).unwrap_or(0),
+    }
+}
+// every spelling of every key whose scale needs no double sharps or flats,
+// e.g. both F♯ and G♭ major, as (key, key_letter) ordered by pitch
+pub fn keys(typ:ScaleType) -> Vec<(usize, usize)> {
+    (0..TOTAL_TONES)
+        .flat_map(|key| (0..7).map(move |letter| (key, letter)))
+        .filter(|(key, letter)| {
+            let scale = Scale { typ, siz: ScaleSize::Diatonic, key: *key, key_letter: *letter, numbering: Numbering::Simple, naming: Naming::English };
+            scale.notes().iter().all(|n| accidental(letter + scale.degree_step(*n), key + n).is_some_and(|a| a.abs() <= 1))
+        })
+        .collect()
+}
+// the mode and key whose diatonic scale holds the most of the notes, each counted as often as it's played.
+// Relative modes hold the same notes, so ties go to the tonic played most (ending on it counts once more),
+// then to minor and major before the other modes
+pub fn best_fit(notes:&[usize]) -> Option<(ScaleType, usize)> {
+    let last = notes.last()? % TOTAL_TONES;
+    let mut best = None;
+    let mut best_score = (0, 0);
+    for typ in ScaleType::iter() {
+        let tones = typ.diatonic();
+        for key in 0..TOTAL_TONES {
+            let fits = notes.iter().filter(|n| tones.contains(&((*n + TOTAL_TONES - key) % TOTAL_TONES))).count();
+            let tonic = notes.iter().filter(|n| *n % TOTAL_TONES == key).count() + usize::from(last == key);
+            if best.is_none() || (fits, tonic) > best_score {
+                best = Some((typ, key));
+                best_score = (fits, tonic);
+            }
+        }
+    }
+    best
+}
+pub fn interval_name(semitones:usize) -> String {
+    match INTERVAL_NAMES.get(semitones) {
+        Some(name) => String::from(*name),
+        None => String::from(BLANK),
+    }
+}
+// wider than two octaves still reads as a 9th/11th/13th
+pub fn compound_interval_name(semitones:usize) -> String {
+    match semitones > 2 * TOTAL_TONES {
+        true => interval_name(TOTAL_TONES + (semitones - 1) % TOTAL_TONES + 1),
+        false => interval_name(semitones),
+    }
+}
+pub fn interval_type(semitones:usize) -> NoteType {
+    match semitones % TOTAL_TONES {
+        0 => NoteType::Root,
+        3 | 4 | 7 => NoteType::Triad,
+        _ => NoteType::InDiatonic,
+    }
+}
+impl ScaleType {
+    // how far up the major scale the mode starts
+    fn mode_offset(&self) -> usize {
+        match self {
+            ScaleType::Major => 0,
+            ScaleType::Dorian => 1,
+            ScaleType::Phrygian => 2,
+            ScaleType::Lydian => 3,
+            ScaleType::Mixolydian => 4,
+            ScaleType::Minor => 5,
+            ScaleType::Locrian => 6,
+        }
+    }
+    pub fn diatonic(&self) -> Vec<usize> {
+        let start = self.mode_offset();
+        (0..7).map(|i| (MAJOR_TONES[(start + i) % 7] + TOTAL_TONES - MAJOR_TONES[start]) % TOTAL_TONES).collect()
+    }
+    fn third(&self) -> usize {
+        self.diatonic()[2]
+    }
+    // minor-third modes drop the 2nd and 6th for their pentatonic and add a ♭5 blue note,
+    // major-third modes drop the 4th and 7th and add a ♭3
+    fn pentatonic(&self) -> Vec<usize> {
+        let dropped = match self.third() {
+            3 => [1, 5],
+            _ => [3, 6],
+        };
+        self.diatonic().into_iter().enumerate().filter(|(i, _)| !dropped.contains(i)).map(|(_, n)| n).collect()
+    }
+    fn blue_note(&self) -> usize {
+        match self.third() {
+            3 => 6,
+            _ => 3,
+        }
     }
 }
 impl Scale {
     pub fn notes(&self) -> Vec<usize>{
-        return match self.typ {
-            ScaleType::Minor => match self.siz {
-                ScaleSize::Diatonic => vec!(0,2,3,5,7,8,10),
-                ScaleSize::Blues => vec!(0,3,5,6,7,10),
-                ScaleSize::Pentatonic => vec!(0,3,5,7,10),
-                ScaleSize::TriadsOnly => vec!(0,3,7),
-                ScaleSize::RootOnly => vec!(0),
-            },
-            ScaleType::Major => match self.siz {
-                ScaleSize::Diatonic => vec!(0,2,4,5,7,9,11),
-                ScaleSize::Blues => vec!(0,2,3,4,7,9),
-                ScaleSize::Pentatonic => vec!(0,2,4,7,9),
-                ScaleSize::TriadsOnly => vec!(0,4,7),
-                ScaleSize::RootOnly => vec!(0),
+        let diatonic = self.typ.diatonic();
+        match self.siz {
+            ScaleSize::Diatonic => diatonic,
+            ScaleSize::Blues => {
+                let mut notes = self.typ.pentatonic();
+                if !notes.contains(&self.typ.blue_note()) {
+                    notes.push(self.typ.blue_note());
+                    notes.sort();
+                }
+                notes
             },
+            ScaleSize::Pentatonic => self.typ.pentatonic(),
+            ScaleSize::TriadsOnly => vec!(0, diatonic[2], diatonic[4]),
+            ScaleSize::RootOnly => vec!(0),
         }
     }
     pub fn is_note_in_scale(&self, mut n:i16) -> bool {
@@ -97,15 +308,143 @@ impl Scale {
         // returns true if any element matches n
         self.notes().iter().any(|note| *note == n as usize)
     }
+    // one letter per scale degree counted from the tonic's letter, so F♯ major has an E♯
     pub fn get_note_letter(&self, n:usize) -> String {
-        note_letter(n % TOTAL_TONES, self.prefers_flats() || self.is_blue_note(self.normalize(n)))
+        if self.naming.is_movable() {
+            return self.get_note_number(self.normalize(n));
+        }
+        let (letter, accidental) = self.spelling(n);
+        self.naming.name(letter, accidental)
+    }
+    // the letter (0 = C ... 6 = B) and accidental a note is written with in this key
+    pub fn spelling(&self, n:usize) -> (usize, i32) {
+        let letter = (self.key_letter + self.degree_step(self.normalize(n))) % 7;
+        match accidental(letter, n) {
+            Some(accidental) => (letter, accidental),
+            None => chromatic_spelling(n % TOTAL_TONES, self.prefers_flats()),
+        }
+    }
+    // the tonic by its own name, even in a movable system where it would just be Do or Sa
+    pub fn key_name(&self) -> String {
+        spell(self.key_letter, self.key, self.naming).unwrap_or_default()
     }
-    pub fn get_note_number(&self, n:usize) -> String {
-        String::from(NOTE_NUMBERS[n % TOTAL_TONES])
+    // letters above the tonic: the scale's own degree if it has one (so Lydian has a ♯4),
+    // anything else, like the blue ♭3/♭5, by its usual chromatic name
+    fn degree_step(&self, note_0_to_11:usize) -> usize {
+        let n = note_0_to_11 % TOTAL_TONES;
+        match self.typ.diatonic().iter().position(|d| *d == n) {
+            Some(step) => step,
+            None => DEGREE_STEPS[n],
+        }
+    }
+    // the degree number with accidentals measured against the major scale: ♭3, ♯4, ♭7;
+    // extended numbering names the 2nd, 4th and 6th as 9, 11 and 13
+    pub fn get_note_number(&self, note_0_to_11:usize) -> String {
+        let n = note_0_to_11 % TOTAL_TONES;
+        if self.naming.is_movable() {
+            let step = self.degree_step(n);
+            return self.naming.degree_name(step, (n as i32 - MAJOR_TONES[step] as i32 + 6).rem_euclid(12) - 6);
+        }
+        if n == 0 {
+            return String::from("R");
+        }
+        // over a major third, extended numbering reads the blue notes as ♯9 and ♯11
+        let step = match (self.numbering, n) {
+            (Numbering::Extended, 3) if self.typ.third() == 4 => 1,
+            (Numbering::Extended, 6) if self.typ.third() == 4 && !self.typ.diatonic().contains(&6) => 3,
+            _ => self.degree_step(n),
+        };
+        let accidental = match (n + TOTAL_TONES - MAJOR_TONES[step]) % TOTAL_TONES {
+            0 => "",
+            1 => "♯",
+            2 => "♯♯",
+            10 => "♭♭",
+            _ => "♭",
+        };
+        let number = match (self.numbering, step) {
+            (Numbering::Extended, 1 | 3 | 5) => step + 8,
+            _ => step + 1,
+        };
+        format!("{}{}", accidental, number)
     }
     pub fn prefers_flats(&self) -> bool {
         prefers_flats(self.typ, self.key)
     }
+    // the major key sharing this key signature, spelled to match: E minor -> G, D♯ minor -> F♯
+    pub fn relative_major(&self) -> Scale {
+        let offset = self.typ.mode_offset();
+        Scale {
+            typ: ScaleType::Major,
+            key: (self.key + TOTAL_TONES - MAJOR_TONES[offset]) % TOTAL_TONES,
+            key_letter: (self.key_letter + 7 - offset) % 7,
+            ..*self
+        }
+    }
+    pub fn relative_minor(&self) -> Scale {
+        let major = self.relative_major();
+        Scale {
+            typ: ScaleType::Minor,
+            key: (major.key + MAJOR_TONES[5]) % TOTAL_TONES,
+            key_letter: (major.key_letter + 5) % 7,
+            ..*self
+        }
+    }
+    // one step round the circle of fifths: clockwise is up a fifth (one more sharp),
+    // anticlockwise up a fourth; past six sharps or flats it switches to the enharmonic key
+    pub fn neighbour(&self, clockwise:bool) -> Scale {
+        let (semitones, letters) = match clockwise {
+            true => (7, 4),
+            false => (5, 3),
+        };
+        let mut scale = Scale {
+            key: (self.key + semitones) % TOTAL_TONES,
+            key_letter: (self.key_letter + letters) % 7,
+            ..*self
+        };
+        if !keys(scale.typ).contains(&(scale.key, scale.key_letter)) {
+            scale.key_letter = key_letter(scale.typ, scale.key);
+        }
+        scale
+    }
+    // the sharps or flats of the key signature, in the order they are written
+    pub fn key_signature(&self) -> Vec<String> {
+        self.signature().iter().map(|(letter, a)| self.naming.absolute().name(*letter, *a)).collect()
+    }
+    // the same as letters and accidentals
+    pub fn signature(&self) -> Vec<(usize, i32)> {
+        let major = self.relative_major();
+        let notes: Vec<(usize, i32)> = (0..7)
+            .map(|step| {
+                let letter = (major.key_letter + step) % 7;
+                (letter, accidental(letter, major.key + MAJOR_TONES[step]).unwrap_or(0))
+            })
+            .collect();
+        let sharps = SHARP_ORDER.iter().filter_map(|l| notes.iter().find(|(letter, a)| letter == l && *a > 0));
+        let flats = SHARP_ORDER.iter().rev().filter_map(|l| notes.iter().find(|(letter, a)| letter == l && *a < 0));
+        sharps.chain(flats).copied().collect()
+    }
+    // the note dropped and the note added when modulating to a neighbouring key
+    pub fn modulation(&self, clockwise:bool) -> Option<(usize, usize)> {
+        let pitch_classes = |scale:&Scale| -> Vec<usize> {
+            scale.typ.diatonic().iter().map(|n| (n + scale.key) % TOTAL_TONES).collect()
+        };
+        let here = pitch_classes(self);
+        let there = pitch_classes(&self.neighbour(clockwise));
+        let leaving = here.iter().find(|n| !there.contains(n))?;
+        let arriving = there.iter().find(|n| !here.contains(n))?;
+        Some((*leaving, *arriving))
+    }
+    // pitch classes only in this scale, and only in the other one, in scale order
+    pub fn differences(&self, other:&Scale) -> (Vec<usize>, Vec<usize>) {
+        let pitch_classes = |scale:&Scale| -> Vec<usize> {
+            scale.notes().iter().map(|n| (n + scale.key) % TOTAL_TONES).collect()
+        };
+        let (a, b) = (pitch_classes(self), pitch_classes(other));
+        (
+            a.iter().filter(|n| !b.contains(n)).copied().collect(),
+            b.iter().filter(|n| !a.contains(n)).copied().collect(),
+        )
+    }
     pub fn color_lookup(note_colors:NoteColors, dark_mode:bool, typ:NoteType, octave:usize) -> (Color32, Color32) {
         match note_colors {
             NoteColors::ByTone => match dark_mode {
@@ -148,36 +487,15 @@ impl Scale {
             },
         }
     }
-    pub fn is_blue_note(&self, note_0_to_11:usize) -> bool{
-        match self.typ {
-            ScaleType::Minor => match note_0_to_11 {
-                6 => true,
-                _ => false,
-            },
-            ScaleType::Major => match note_0_to_11 {
-                3 => true,
-                _ => false,
-            }
-        }
-    }
     pub fn get_note_type(&self, note_0_to_11:usize) -> NoteType {
-        match self.typ {
-            ScaleType::Minor => match note_0_to_11 {
-                0 => NoteType::Root,
-                6 => NoteType::Blue,
-                3|7 => NoteType::Triad,
-                2|8 => NoteType::InDiatonic,
-                5|10 => NoteType::InPentatonic,
-                _ => NoteType::NotInScale,
-            },
-            ScaleType::Major => match note_0_to_11 {
-                0 => NoteType::Root,
-                3 => NoteType::Blue,
-                4|7 => NoteType::Triad,
-                2|9 => NoteType::InPentatonic,
-                5|11 => NoteType::InDiatonic,
-                _ => NoteType::NotInScale,
-            }
+        let diatonic = self.typ.diatonic();
+        match note_0_to_11 {
+            0 => NoteType::Root,
+            n if n == diatonic[2] || n == diatonic[4] => NoteType::Triad,
+            n if n == self.typ.blue_note() && !diatonic.contains(&n) => NoteType::Blue,
+            n if self.typ.pentatonic().contains(&n) => NoteType::InPentatonic,
+            n if diatonic.contains(&n) => NoteType::InDiatonic,
+            _ => NoteType::NotInScale,
         }
     }
     fn normalize(&self, note_as_int:usize) -> usize{
@@ -204,51 +522,29 @@ impl Scale {
                 }
             },
             true => match note_marker {
-                NoteMarker::AllNotes | NoteMarker::Letters => bubble_letter,
+                NoteMarker::AllNotes | NoteMarker::Letters | NoteMarker::Fingering => bubble_letter,
                 NoteMarker::Numbers => bubble_number,
                 NoteMarker::Debug => bubble_debug,
             },
         };
     }
     pub fn get_bubble_from(&self, note_colors:NoteColors, dark_mode:bool, note:NoteType, marker:NoteMarker) -> Bubble {
-        let note_0_to_11:usize =  match self.typ {
-            ScaleType::Minor => match note {
-                NoteType::Root => 0,
-                NoteType::Triad => 3,
-                NoteType::InPentatonic => 5,
-                NoteType::Blue => 6,
-                NoteType::InDiatonic => 8,
-                _ => 12,
-            },
-            ScaleType::Major => match note {
-                NoteType::Root => 0,
-                NoteType::Triad => 4,
-                NoteType::InPentatonic => 2,
-                NoteType::Blue => 3,
-                NoteType::InDiatonic => 5,
-                _ => 0,
-            },
-        };
+        // the first interval of the scale that is drawn with this note type
+        let note_0_to_11 = (0..TOTAL_TONES).find(|n| self.get_note_type(*n) == note).unwrap_or(0);
         let n = (note_0_to_11 as i8 + self.key as i8) as usize % TOTAL_TONES;
         let colors = Scale::color_lookup(note_colors, dark_mode, note, 0usize);
         Bubble::new(colors, match marker {
-            NoteMarker::AllNotes | NoteMarker::Letters => self.get_note_letter(n),
-            NoteMarker::Numbers => self.get_note_number(n),
+            NoteMarker::AllNotes | NoteMarker::Letters | NoteMarker::Fingering => self.get_note_letter(n),
+            NoteMarker::Numbers => self.get_note_number(note_0_to_11),
             NoteMarker::Debug => note_0_to_11.to_string(),
         })
     }
-    pub fn scale_name(&self) -> &str {
-        &scale_name(self.siz)
+    pub fn scale_name(&self) -> String {
+        scale_name(self.siz)
     }
 }
-pub fn scale_name(s:ScaleSize) -> &'static str {
-    match s {
-        ScaleSize::Blues => "Blues scale",
-        ScaleSize::Pentatonic => "Pentatonic scale",
-        ScaleSize::Diatonic => "Natural scale",
-        ScaleSize::TriadsOnly => "triads",
-        ScaleSize::RootOnly => "roots",
-    }
+pub fn scale_name(s:ScaleSize) -> String {
+    label(&s)
 }
 pub fn prefers_flats(t:ScaleType, k:usize) -> bool {
     match t {
@@ -260,5 +556,181 @@ pub fn prefers_flats(t:ScaleType, k:usize) -> bool {
             0|2|3|5|7|9|10 => true,
             _ => false,
         },
+        // modes follow the key signature of their relative major
+        mode => prefers_flats(ScaleType::Major, (k + TOTAL_TONES - MAJOR_TONES[mode.mode_offset()]) % TOTAL_TONES),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scale(typ: ScaleType, key: usize, key_letter: usize) -> Scale {
+        Scale { typ, siz: ScaleSize::Diatonic, key, key_letter, numbering: Numbering::Simple, naming: Naming::English }
+    }
+    fn letters(scale: &Scale) -> Vec<String> {
+        scale.notes().iter().map(|n| scale.get_note_letter(scale.key + n)).collect()
+    }
+
+    #[test]
+    fn sharp_keys_use_every_letter_once() {
+        // F♯ major has an E♯, not an F, and C♯ major a B♯
+        assert_eq!(letters(&scale(ScaleType::Major, 6, 3)), ["F♯", "G♯", "A♯", "B", "C♯", "D♯", "E♯"]);
+        assert_eq!(letters(&scale(ScaleType::Major, 1, 0)), ["C♯", "D♯", "E♯", "F♯", "G♯", "A♯", "B♯"]);
+        assert_eq!(scale(ScaleType::Major, 6, 3).spelling(5), (2, 1));
+    }
+    #[test]
+    fn flat_keys_use_every_letter_once() {
+        // G♭ major has a C♭, not a B
+        assert_eq!(letters(&scale(ScaleType::Major, 6, 4)), ["G♭", "A♭", "B♭", "C♭", "D♭", "E♭", "F"]);
+        assert_eq!(letters(&scale(ScaleType::Minor, 3, 2)), ["E♭", "F", "G♭", "A♭", "B♭", "C♭", "D♭"]);
+        assert_eq!(scale(ScaleType::Major, 6, 4).spelling(11), (0, -1));
+    }
+    #[test]
+    fn enharmonic_keys_are_both_offered() {
+        let major = keys(ScaleType::Major);
+        assert!(major.contains(&(6, 3)) && major.contains(&(6, 4)));
+        // but not ones that would need double sharps, like D♯ major
+        assert!(!major.contains(&(3, 1)));
+        assert_eq!(key_letter(ScaleType::Major, 10), 6);
+        assert_eq!(key_letter(ScaleType::Minor, 10), 6);
+        assert_eq!(key_letter(ScaleType::Minor, 8), 4);
+    }
+    fn numbers(scale: &Scale, notes: &[usize]) -> Vec<String> {
+        notes.iter().map(|n| scale.get_note_number(*n)).collect()
+    }
+    #[test]
+    fn degrees_are_numbered_against_major() {
+        assert_eq!(numbers(&scale(ScaleType::Major, 0, 0), &[0, 2, 4, 5, 7, 9, 11]), ["R", "2", "3", "4", "5", "6", "7"]);
+        assert_eq!(numbers(&scale(ScaleType::Minor, 9, 5), &[0, 2, 3, 5, 7, 8, 10]), ["R", "2", "♭3", "4", "5", "♭6", "♭7"]);
+        // Lydian raises the fourth rather than lowering the fifth
+        assert_eq!(scale(ScaleType::Lydian, 5, 3).get_note_number(6), "♯4");
+        assert_eq!(scale(ScaleType::Locrian, 11, 6).get_note_number(6), "♭5");
+        assert_eq!(scale(ScaleType::Mixolydian, 7, 4).get_note_number(10), "♭7");
+        // notes are taken as semitones above the key, any octave
+        assert_eq!(scale(ScaleType::Major, 0, 0).get_note_number(16), "3");
+    }
+    #[test]
+    fn blue_notes_are_flat_fifths_and_thirds() {
+        let blues = Scale { siz: ScaleSize::Blues, ..scale(ScaleType::Minor, 9, 5) };
+        assert_eq!(numbers(&blues, &[0, 3, 5, 6, 7, 10]), ["R", "♭3", "4", "♭5", "5", "♭7"]);
+        let blues = Scale { siz: ScaleSize::Blues, ..scale(ScaleType::Major, 0, 0) };
+        assert_eq!(numbers(&blues, &[3, 6]), ["♭3", "♭5"]);
+    }
+    #[test]
+    fn extended_numbering_names_the_tensions() {
+        let extended = |typ, key, key_letter| Scale { numbering: Numbering::Extended, ..scale(typ, key, key_letter) };
+        let major = extended(ScaleType::Major, 0, 0);
+        assert_eq!(numbers(&major, &[0, 2, 4, 5, 7, 9, 11]), ["R", "9", "3", "11", "5", "13", "7"]);
+        // over a major third the blue notes read as a sharp ninth and a sharp eleventh
+        assert_eq!(numbers(&major, &[3, 6]), ["♯9", "♯11"]);
+        assert_eq!(extended(ScaleType::Lydian, 5, 3).get_note_number(6), "♯11");
+        // over a minor third they stay a flat third and a flat fifth
+        let minor = extended(ScaleType::Minor, 9, 5);
+        assert_eq!(numbers(&minor, &[3, 6, 8, 9]), ["♭3", "♭5", "♭13", "13"]);
+    }
+    #[test]
+    fn german_names() {
+        let german = |letter, accidental| Naming::German.name(letter, accidental);
+        assert_eq!([german(6, 0), german(6, -1), german(2, -1), german(5, -1), german(3, 1)], ["H", "B", "Es", "As", "Fis"]);
+        assert_eq!([german(1, -1), german(0, -1), german(5, -2), german(6, -2), german(3, 2)], ["Des", "Ces", "Ases", "Heses", "Fisis"]);
+        let e_flat_minor = Scale { naming: Naming::German, ..scale(ScaleType::Minor, 3, 2) };
+        assert_eq!(letters(&e_flat_minor), ["Es", "F", "Ges", "As", "B", "Ces", "Des"]);
+        assert_eq!(e_flat_minor.key_name(), "Es");
+    }
+    #[test]
+    fn movable_do_names_degrees_chromatically() {
+        let solfege = |typ, key, key_letter| Scale { naming: Naming::MovableDo, ..scale(typ, key, key_letter) };
+        let degrees = |scale: &Scale| numbers(scale, &scale.notes());
+        assert_eq!(degrees(&solfege(ScaleType::Major, 7, 4)), ["Do", "Re", "Mi", "Fa", "Sol", "La", "Ti"]);
+        assert_eq!(degrees(&solfege(ScaleType::Lydian, 5, 3)), ["Do", "Re", "Mi", "Fi", "Sol", "La", "Ti"]);
+        assert_eq!(degrees(&solfege(ScaleType::Minor, 9, 5)), ["Do", "Re", "Me", "Fa", "Sol", "Le", "Te"]);
+        assert_eq!(degrees(&solfege(ScaleType::Locrian, 11, 6)), ["Do", "Ra", "Me", "Fa", "Se", "Le", "Te"]);
+        // notes are named by degree, but the key keeps its fixed do name
+        let d_major = solfege(ScaleType::Major, 2, 1);
+        assert_eq!(d_major.get_note_letter(6), "Mi");
+        assert_eq!(d_major.key_name(), "Re");
+    }
+    #[test]
+    fn sargam_names_degrees() {
+        let sargam = |typ, key, key_letter| Scale { naming: Naming::Sargam, ..scale(typ, key, key_letter) };
+        let degrees = |scale: &Scale| numbers(scale, &scale.notes());
+        assert_eq!(degrees(&sargam(ScaleType::Major, 0, 0)), ["Sa", "Re", "Ga", "Ma", "Pa", "Dha", "Ni"]);
+        // komal swaras are lower case
+        assert_eq!(degrees(&sargam(ScaleType::Phrygian, 4, 2)), ["Sa", "re", "ga", "Ma", "Pa", "dha", "ni"]);
+        // tivra Ma, whether it comes as a ♯4 or a ♭5
+        assert_eq!(sargam(ScaleType::Lydian, 5, 3).get_note_number(6), "Ma'");
+        assert_eq!(sargam(ScaleType::Locrian, 11, 6).get_note_number(6), "Ma'");
+        assert_eq!(sargam(ScaleType::Major, 2, 1).get_note_letter(6), "Ga");
+        assert_eq!(sargam(ScaleType::Major, 2, 1).key_name(), "D");
+    }
+    #[test]
+    fn iroha_names() {
+        let iroha = |typ, key, key_letter| Scale { naming: Naming::Iroha, ..scale(typ, key, key_letter) };
+        assert_eq!(letters(&iroha(ScaleType::Minor, 9, 5)), ["I", "Ro", "Ha", "Ni", "Ho", "He", "To"]);
+        assert_eq!(letters(&iroha(ScaleType::Major, 2, 1)), ["Ni", "Ho", "Ei-he", "To", "I", "Ro", "Ei-ha"]);
+        assert_eq!(iroha(ScaleType::Major, 10, 6).key_name(), "Hen-ro");
+        assert_eq!([Naming::Iroha.name(3, 2), Naming::Iroha.name(6, -2)], ["Jūei-he", "Jūhen-ro"]);
+    }
+    fn key(scale: &Scale) -> (ScaleType, usize, usize) {
+        (scale.typ, scale.key, scale.key_letter)
+    }
+    #[test]
+    fn relative_keys_share_a_signature() {
+        assert_eq!(key(&scale(ScaleType::Major, 0, 0).relative_minor()), (ScaleType::Minor, 9, 5));
+        assert_eq!(key(&scale(ScaleType::Minor, 9, 5).relative_major()), (ScaleType::Major, 0, 0));
+        assert_eq!(key(&scale(ScaleType::Major, 3, 2).relative_minor()), (ScaleType::Minor, 0, 0));
+        assert_eq!(key(&scale(ScaleType::Minor, 0, 0).relative_major()), (ScaleType::Major, 3, 2));
+        // spelled to match: D♯ minor goes with F♯, not G♭
+        assert_eq!(key(&scale(ScaleType::Minor, 3, 1).relative_major()), (ScaleType::Major, 6, 3));
+        assert_eq!(key(&scale(ScaleType::Dorian, 2, 1).relative_major()), (ScaleType::Major, 0, 0));
+        assert_eq!(key(&scale(ScaleType::Dorian, 2, 1).relative_minor()), (ScaleType::Minor, 9, 5));
+    }
+    #[test]
+    fn signatures_count_sharps_and_flats_in_order() {
+        assert!(scale(ScaleType::Major, 0, 0).signature().is_empty());
+        assert!(scale(ScaleType::Minor, 9, 5).key_signature().is_empty());
+        let f_sharp = scale(ScaleType::Major, 6, 3);
+        assert_eq!(f_sharp.key_signature(), ["F♯", "C♯", "G♯", "D♯", "A♯", "E♯"]);
+        assert!(f_sharp.signature().iter().all(|(_, a)| *a == 1));
+        let g_flat = scale(ScaleType::Major, 6, 4);
+        assert_eq!(g_flat.key_signature(), ["B♭", "E♭", "A♭", "D♭", "G♭", "C♭"]);
+        assert!(g_flat.signature().iter().all(|(_, a)| *a == -1));
+        // a minor key has its relative major's signature
+        assert_eq!(scale(ScaleType::Minor, 0, 0).key_signature(), ["B♭", "E♭", "A♭"]);
+        assert_eq!(scale(ScaleType::Mixolydian, 9, 5).signature(), [(3, 1), (0, 1)]);
+    }
+    #[test]
+    fn neighbours_round_the_circle_of_fifths() {
+        let c = scale(ScaleType::Major, 0, 0);
+        assert_eq!(key(&c.neighbour(true)), (ScaleType::Major, 7, 4));
+        assert_eq!(key(&c.neighbour(false)), (ScaleType::Major, 5, 3));
+        assert_eq!(key(&scale(ScaleType::Minor, 9, 5).neighbour(true)), (ScaleType::Minor, 4, 2));
+        // G♯ major would need an F𝄪, so seven sharps go on to A♭; seven flats back round to E
+        assert_eq!(key(&scale(ScaleType::Major, 1, 0).neighbour(true)), (ScaleType::Major, 8, 5));
+        assert_eq!(key(&scale(ScaleType::Major, 11, 0).neighbour(false)), (ScaleType::Major, 4, 2));
+        // twelve steps either way come home
+        let mut around = [Scale { ..c }, Scale { ..c }];
+        for _ in 0..TOTAL_TONES {
+            around = [around[0].neighbour(true), around[1].neighbour(false)];
+        }
+        assert_eq!(around.map(|s| key(&s)), [key(&c), key(&c)]);
+    }
+    #[test]
+    fn modulating_changes_one_note() {
+        let c = scale(ScaleType::Major, 0, 0);
+        // to G, F becomes F♯; to F, B becomes B♭
+        assert_eq!(c.modulation(true), Some((5, 6)));
+        assert_eq!(c.modulation(false), Some((11, 10)));
+        assert_eq!(c.differences(&c.neighbour(true)), (vec![5], vec![6]));
+        assert_eq!(c.differences(&c.neighbour(false)), (vec![11], vec![10]));
+        assert_eq!(scale(ScaleType::Minor, 9, 5).modulation(true), Some((5, 6)));
+        assert_eq!(c.differences(&scale(ScaleType::Minor, 9, 5)), (vec![], vec![]));
+    }
+    #[test]
+    fn differences_follow_the_scale_sizes() {
+        let c = scale(ScaleType::Major, 0, 0);
+        let a_minor_pentatonic = Scale { siz: ScaleSize::Pentatonic, ..scale(ScaleType::Minor, 9, 5) };
+        assert_eq!(c.differences(&a_minor_pentatonic), (vec![5, 11], vec![]));
+        assert_eq!(a_minor_pentatonic.differences(&scale(ScaleType::Major, 2, 1)), (vec![0], vec![6, 11, 1]));
     }
 }
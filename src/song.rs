@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use crate::archive;
+use crate::i18n::tr;
+use crate::scales::{LETTERS, LETTER_TONES, TOTAL_TONES};
+
+// the GPIF score inside Guitar Pro 6 and 7 files
+const GPIF: &str = "score.gpif";
+
+pub struct Song {
+    pub name: String,
+    pub tracks: Vec<Track>,
+}
+pub struct Track {
+    pub name: String,
+    // the MIDI notes struck in each bar; tied-over notes only count where they start
+    pub bars: Vec<Vec<usize>>,
+}
+// a loaded song and the passage of it shown on the fretboard
+#[derive(Default)]
+pub struct SongImport {
+    pub song: Option<Song>,
+    pub path: String,
+    pub track: usize,
+    // bars counted from 1, both ends included
+    pub from: usize,
+    pub to: usize,
+    // how often each pitch class is played in the passage
+    pub counts: [usize; TOTAL_TONES],
+}
+impl SongImport {
+    pub fn load(&mut self, song: Song) {
+        self.track = 0;
+        self.from = 1;
+        self.to = song.tracks.first().map_or(1, |t| t.bars.len());
+        self.song = Some(song);
+        self.refresh();
+    }
+    pub fn bars(&self) -> usize {
+        self.song.as_ref().and_then(|s| s.tracks.get(self.track)).map_or(0, |t| t.bars.len())
+    }
+    // the notes of the chosen track and bars, in order
+    pub fn notes(&self) -> Vec<usize> {
+        match self.song.as_ref().and_then(|s| s.tracks.get(self.track)) {
+            Some(track) => track.bars.iter()
+                .skip(self.from.saturating_sub(1))
+                .take((self.to + 1).saturating_sub(self.from))
+                .flatten()
+                .copied()
+                .collect(),
+            None => vec![],
+        }
+    }
+    pub fn refresh(&mut self) {
+        let bars = self.bars().max(1);
+        self.to = self.to.clamp(1, bars);
+        self.from = self.from.clamp(1, self.to);
+        self.counts = [0; TOTAL_TONES];
+        for note in self.notes() {
+            self.counts[note % TOTAL_TONES] += 1;
+        }
+    }
+}
+impl Song {
+    // Guitar Pro 6 (.gpx) and 7 (.gp), and MusicXML either plain (.musicxml, .xml) or compressed (.mxl)
+    pub fn from_bytes(name: &str, bytes: &[u8]) -> Result<Song, String> {
+        let files = match (archive::is_zip(bytes), archive::is_gpx(bytes)) {
+            (true, _) => archive::unzip(bytes)?,
+            (_, true) => archive::unpack_gpx(bytes)?,
+            // older Guitar Pro files (.gp3 to .gp5) are binary and end up here too
+            _ if !looks_like_xml(bytes) => return Err(tr("song.error.format")),
+            _ => vec![(name.to_string(), bytes.to_vec())],
+        };
+        // a compressed MusicXML file names its score in META-INF/container.xml
+        let container = files.iter().find(|(n, _)| n == "META-INF/container.xml")
+            .and_then(|(_, data)| parse(data).ok())
+            .and_then(|root| root.find("rootfile").and_then(|r| r.attr("full-path")).map(|p| p.to_string()));
+        let score = files.iter()
+            .find(|(n, _)| n.ends_with(GPIF) || Some(n) == container.as_ref())
+            .or_else(|| files.iter().find(|(n, _)| !n.starts_with("META-INF") && (n.ends_with(".xml") || n.ends_with(".musicxml"))))
+            .or(files.first())
+            .ok_or_else(|| tr("song.error.format"))?;
+        let root = parse(&score.1)?;
+        let tracks = match root.name.as_str() {
+            "GPIF" => read_gpif(&root),
+            "score-partwise" => read_musicxml(&root),
+            _ => return Err(tr("song.error.format")),
+        };
+        match tracks.iter().any(|t| t.bars.iter().any(|b| !b.is_empty())) {
+            true => Ok(Song { name: name.to_string(), tracks }),
+            false => Err(tr("song.error.no_notes")),
+        }
+    }
+}
+fn looks_like_xml(bytes: &[u8]) -> bool {
+    String::from_utf8_lossy(bytes).trim_start_matches('\u{feff}').trim_start().starts_with('<')
+}
+// both formats are read into a small element tree first
+struct Node {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+    text: String,
+}
+impl Node {
+    fn from(start: &BytesStart) -> Node {
+        Node {
+            name: String::from_utf8_lossy(start.local_name().as_ref()).to_string(),
+            attributes: start.attributes().flatten().map(|a| {
+                let key = String::from_utf8_lossy(a.key.local_name().as_ref()).to_string();
+                (key, a.unescape_value().map(|v| v.to_string()).unwrap_or_default())
+            }).collect(),
+            children: vec![],
+            text: String::new(),
+        }
+    }
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+    fn child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|c| c.name == name)
+    }
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+    // the first element with this name anywhere below, depth first
+    fn find(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find_map(|c| match c.name == name {
+            true => Some(c),
+            false => c.find(name),
+        })
+    }
+    fn text_of(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|c| c.text.trim())
+    }
+    fn number_of(&self, name: &str) -> Option<f32> {
+        self.text_of(name).and_then(|t| t.parse().ok())
+    }
+}
+fn parse(bytes: &[u8]) -> Result<Node, String> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut reader = Reader::from_str(&text);
+    let mut stack: Vec<Node> = vec![];
+    loop {
+        let event = reader.read_event().map_err(|e| format!("{}: {}", tr("song.error.damaged"), e))?;
+        match event {
+            Event::Start(start) => stack.push(Node::from(&start)),
+            Event::Empty(start) => {
+                let node = Node::from(&start);
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => return Ok(node),
+                }
+            },
+            Event::Text(text) => if let Some(node) = stack.last_mut() {
+                node.text.push_str(&text.unescape().unwrap_or_default());
+            },
+            Event::CData(data) => if let Some(node) = stack.last_mut() {
+                node.text.push_str(&String::from_utf8_lossy(&data));
+            },
+            Event::End(_) => {
+                let node = stack.pop().ok_or_else(|| tr("song.error.damaged"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => return Ok(node),
+                }
+            },
+            Event::Eof => return Err(tr("song.error.damaged")),
+            _ => {},
+        }
+    }
+}
+// one track per part, one bar per measure; pitches are written, so transposing parts are moved to concert pitch,
+// and a TAB staff repeating the notation staff's notes is left out
+fn read_musicxml(root: &Node) -> Vec<Track> {
+    let names: HashMap<&str, &str> = root.child("part-list").map_or(vec![], |list| {
+        list.children("score-part").filter_map(|p| Some((p.attr("id")?, p.text_of("part-name").unwrap_or_default()))).collect()
+    }).into_iter().collect();
+    root.children("part").map(|part| {
+        let id = part.attr("id").unwrap_or_default();
+        let mut transpose = 0;
+        let mut tab_staves: Vec<String> = vec![];
+        let bars = part.children("measure").map(|measure| {
+            for attributes in measure.children("attributes") {
+                if let Some(t) = attributes.child("transpose") {
+                    transpose = t.number_of("chromatic").unwrap_or(0f32) as i32 + 12 * t.number_of("octave-change").unwrap_or(0f32) as i32;
+                }
+                for clef in attributes.children("clef") {
+                    let staff = clef.attr("number").unwrap_or("1").to_string();
+                    tab_staves.retain(|s| *s != staff);
+                    if clef.text_of("sign") == Some("TAB") {
+                        tab_staves.push(staff);
+                    }
+                }
+            }
+            measure.children("note")
+                .filter(|note| !tab_staves.iter().any(|s| Some(s.as_str()) == note.text_of("staff").or(Some("1"))))
+                .filter(|note| !note.children("tie").any(|t| t.attr("type") == Some("stop")))
+                .filter_map(|note| {
+                    let pitch = note.child("pitch")?;
+                    let letter = LETTERS.iter().position(|l| Some(*l) == pitch.text_of("step"))?;
+                    let alter = pitch.number_of("alter").unwrap_or(0f32).round() as i32;
+                    let octave = pitch.number_of("octave")? as i32;
+                    let midi = (octave + 1) * TOTAL_TONES as i32 + LETTER_TONES[letter] as i32 + alter + transpose;
+                    usize::try_from(midi).ok()
+                })
+                .collect()
+        }).collect();
+        Track { name: names.get(id).unwrap_or(&id).to_string(), bars }
+    }).collect()
+}
+// GPIF keeps each kind of object in its own list, linked by ids: master bars list a bar per track,
+// bars list voices, voices list beats and beats list notes
+fn read_gpif(root: &Node) -> Vec<Track> {
+    let by_id = |list: &str, item: &'static str| -> HashMap<String, &Node> {
+        root.child(list).map_or(HashMap::new(), |l| {
+            l.children(item).filter_map(|n| Some((n.attr("id")?.to_string(), n))).collect()
+        })
+    };
+    let ids = |text: Option<&str>| -> Vec<String> {
+        text.unwrap_or_default().split_whitespace().filter(|id| *id != "-1").map(|id| id.to_string()).collect()
+    };
+    let bars = by_id("Bars", "Bar");
+    let voices = by_id("Voices", "Voice");
+    let beats = by_id("Beats", "Beat");
+    let notes = by_id("Notes", "Note");
+    let master_bars: Vec<Vec<String>> = root.child("MasterBars")
+        .map_or(vec![], |m| m.children("MasterBar").map(|b| ids(b.text_of("Bars"))).collect());
+    let tracks = root.child("Tracks").map_or(vec![], |t| t.children("Track").collect());
+    tracks.iter().enumerate().map(|(index, track)| {
+        // the open strings' MIDI notes from low to high, as the String property counts them
+        let tuning: Vec<usize> = find_property(track, "Tuning")
+            .and_then(|p| p.text_of("Pitches"))
+            .map_or(vec![], |p| p.split_whitespace().filter_map(|n| n.parse().ok()).collect());
+        let track_bars = master_bars.iter().map(|master| {
+            let bar = master.get(index).and_then(|id| bars.get(id));
+            bar.map_or(vec![], |bar| {
+                ids(bar.text_of("Voices")).iter()
+                    .filter_map(|id| voices.get(id))
+                    .flat_map(|voice| ids(voice.text_of("Beats")))
+                    .filter_map(|id| beats.get(&id))
+                    .flat_map(|beat| ids(beat.text_of("Notes")))
+                    .filter_map(|id| notes.get(&id))
+                    .filter(|note| note.child("Tie").and_then(|t| t.attr("destination")) != Some("true"))
+                    .filter_map(|note| gpif_note(note, &tuning))
+                    .collect()
+            })
+        }).collect();
+        Track { name: track.text_of("Name").unwrap_or_default().to_string(), bars: track_bars }
+    }).collect()
+}
+fn find_property<'a>(node: &'a Node, name: &str) -> Option<&'a Node> {
+    match node.name == "Property" && node.attr("name") == Some(name) {
+        true => Some(node),
+        false => node.children.iter().find_map(|c| find_property(c, name)),
+    }
+}
+// string and fret on the track's tuning, else the MIDI number some notes carry instead
+fn gpif_note(note: &Node, tuning: &[usize]) -> Option<usize> {
+    let properties = note.child("Properties")?;
+    let value = |name: &str, field: &str| find_property(properties, name).and_then(|p| p.number_of(field)).map(|n| n as usize);
+    match (value("String", "String"), value("Fret", "Fret")) {
+        (Some(string), Some(fret)) if string < tuning.len() => Some(tuning[string] + fret),
+        _ => value("Midi", "Number"),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::tests::{gpx, zip};
+
+    // two bars: A2 from string and fret and C4 from its MIDI number, then a tied-over E4 and G4
+    const GPIF_SCORE: &str = r#"<GPIF>
+        <Tracks><Track id="0"><Name>Lead</Name><Properties>
+            <Property name="Tuning"><Pitches>40 45 50 55 59 64</Pitches></Property>
+        </Properties></Track></Tracks>
+        <MasterBars><MasterBar><Bars>0</Bars></MasterBar><MasterBar><Bars>1</Bars></MasterBar></MasterBars>
+        <Bars><Bar id="0"><Voices>0 -1 -1 -1</Voices></Bar><Bar id="1"><Voices>1 -1 -1 -1</Voices></Bar></Bars>
+        <Voices><Voice id="0"><Beats>0 1</Beats></Voice><Voice id="1"><Beats>2</Beats></Voice></Voices>
+        <Beats><Beat id="0"><Notes>0</Notes></Beat><Beat id="1"><Notes>1</Notes></Beat><Beat id="2"><Notes>2 3</Notes></Beat></Beats>
+        <Notes>
+            <Note id="0"><Properties><Property name="String"><String>0</String></Property><Property name="Fret"><Fret>5</Fret></Property></Properties></Note>
+            <Note id="1"><Properties><Property name="Midi"><Number>60</Number></Property></Properties></Note>
+            <Note id="2"><Tie origin="false" destination="true"/><Properties><Property name="String"><String>5</String></Property><Property name="Fret"><Fret>0</Fret></Property></Properties></Note>
+            <Note id="3"><Properties><Property name="String"><String>5</String></Property><Property name="Fret"><Fret>3</Fret></Property></Properties></Note>
+        </Notes>
+    </GPIF>"#;
+    // a B♭ clarinet part written a tone up, and a guitar part whose TAB staff repeats its notes
+    const MUSICXML_SCORE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <score-partwise version="4.0">
+        <part-list><score-part id="P1"><part-name>Clarinet</part-name></score-part><score-part id="P2"><part-name>Guitar</part-name></score-part></part-list>
+        <part id="P1"><measure number="1">
+            <attributes><transpose><diatonic>-1</diatonic><chromatic>-2</chromatic></transpose></attributes>
+            <note><pitch><step>D</step><octave>4</octave></pitch><tie type="start"/></note>
+            <note><pitch><step>D</step><octave>4</octave></pitch><tie type="stop"/></note>
+            <note><rest/></note>
+        </measure></part>
+        <part id="P2"><measure number="1">
+            <attributes><staves>2</staves><clef number="1"><sign>G</sign></clef><clef number="2"><sign>TAB</sign></clef></attributes>
+            <note><pitch><step>F</step><alter>1</alter><octave>3</octave></pitch><staff>1</staff></note>
+            <note><pitch><step>F</step><alter>1</alter><octave>3</octave></pitch><staff>2</staff></note>
+        </measure><measure number="2">
+            <note><pitch><step>B</step><alter>-1</alter><octave>2</octave></pitch><staff>1</staff></note>
+        </measure></part>
+        </score-partwise>"#;
+
+    #[test]
+    fn reads_gpif_from_gp_and_gpx() {
+        let gp = zip(&[("Content/score.gpif", GPIF_SCORE.as_bytes(), true), ("Content/BinaryStylesheet", &[0; 10], false)]);
+        let gpx = gpx(&[("misc.xml", b"<x/>"), ("score.gpif", GPIF_SCORE.as_bytes())]);
+        for bytes in [gp, gpx] {
+            let song = Song::from_bytes("song", &bytes).unwrap();
+            assert_eq!(song.tracks.len(), 1);
+            assert_eq!(song.tracks[0].name, "Lead");
+            assert_eq!(song.tracks[0].bars, vec![vec![45, 60], vec![67]]);
+        }
+    }
+    #[test]
+    fn reads_musicxml_plain_and_compressed() {
+        let container = br#"<container><rootfiles><rootfile full-path="score/song.musicxml"/></rootfiles></container>"#;
+        let mxl = zip(&[("META-INF/container.xml", container, true), ("score/song.musicxml", MUSICXML_SCORE.as_bytes(), true)]);
+        for bytes in [MUSICXML_SCORE.as_bytes().to_vec(), mxl] {
+            let song = Song::from_bytes("song.musicxml", &bytes).unwrap();
+            let names: Vec<&str> = song.tracks.iter().map(|t| t.name.as_str()).collect();
+            assert_eq!(names, ["Clarinet", "Guitar"]);
+            // the clarinet's written D4 sounds C4, held over the bar line as one note
+            assert_eq!(song.tracks[0].bars, vec![vec![60]]);
+            assert_eq!(song.tracks[1].bars, vec![vec![54], vec![46]]);
+        }
+    }
+    #[test]
+    fn chooses_bars_of_a_track() {
+        let mut import = SongImport::default();
+        import.load(Song::from_bytes("song", MUSICXML_SCORE.as_bytes()).unwrap());
+        import.track = 1;
+        import.refresh();
+        assert_eq!((import.from, import.to), (1, 1));
+        import.to = 5;
+        import.refresh();
+        assert_eq!(import.to, 2);
+        assert_eq!(import.notes(), vec![54, 46]);
+        assert_eq!(import.counts[6] + import.counts[10], 2);
+    }
+    #[test]
+    fn damaged_or_unknown_files_are_errors() {
+        let format = tr("song.error.format");
+        assert_eq!(Song::from_bytes("old.gp5", b"\x18FICHIER GUITAR PRO v5.00").err(), Some(format.clone()));
+        assert_eq!(Song::from_bytes("page.html", b"<html><body/></html>").err(), Some(format));
+        let cut = &MUSICXML_SCORE[..MUSICXML_SCORE.len() / 2];
+        assert!(Song::from_bytes("cut.musicxml", cut.as_bytes()).err().is_some_and(|e| e.starts_with(&tr("song.error.damaged"))));
+        let unbalanced = "<score-partwise><part></measure></score-partwise>";
+        assert!(Song::from_bytes("bad.musicxml", unbalanced.as_bytes()).is_err());
+        let empty = "<score-partwise><part id=\"P1\"><measure/></part></score-partwise>";
+        assert_eq!(Song::from_bytes("empty.musicxml", empty.as_bytes()).err(), Some(tr("song.error.no_notes")));
+        let gp = zip(&[("Content/score.gpif", GPIF_SCORE.as_bytes(), true)]);
+        assert!(Song::from_bytes("cut.gp", &gp[..gp.len() - 30]).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use crate::scales::{LETTER_TONES, TOTAL_TONES};
+
+// staff positions count half-spaces (one letter) up from the bottom line: lines are 0, 2, 4, 6 and 8
+pub const TOP_LINE: i32 = 8;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Clef {
+    Treble,
+    // guitar music: written an octave above where it sounds
+    Treble8vb,
+    Bass,
+}
+impl Clef {
+    // the bottom line as octave * 7 + letter: E4 for treble, G2 for bass
+    fn bottom_line(&self) -> i32 {
+        match self {
+            Clef::Treble | Clef::Treble8vb => 4 * 7 + 2,
+            Clef::Bass => 2 * 7 + 4,
+        }
+    }
+    fn written_offset(&self) -> i32 {
+        match self {
+            Clef::Treble8vb => TOTAL_TONES as i32,
+            _ => 0,
+        }
+    }
+    // where a sounding MIDI note spelled with this letter and accidental sits on the staff
+    pub fn position(&self, midi: usize, letter: usize, accidental: i32) -> i32 {
+        let written = midi as i32 + self.written_offset();
+        let octave = (written - accidental - LETTER_TONES[letter] as i32).div_euclid(TOTAL_TONES as i32) - 1;
+        octave * 7 + letter as i32 - self.bottom_line()
+    }
+    // key signature accidentals go at their engraved heights: F♯ on the top line, B♭ on the middle
+    pub fn signature_position(&self, letter: usize, accidental: i32) -> i32 {
+        let lowest = match (self, accidental > 0) {
+            (Clef::Bass, true) => 1,
+            (Clef::Bass, false) => -1,
+            (_, true) => 3,
+            (_, false) => 1,
+        };
+        lowest + (letter as i32 - self.bottom_line() - lowest).rem_euclid(7)
+    }
+    // the octave of a tonic that puts it on or just around the bottom of the staff
+    pub fn tonic_midi(&self, key: usize, key_letter: usize) -> usize {
+        (1..8)
+            .map(|octave| (octave + 1) * TOTAL_TONES + key % TOTAL_TONES)
+            .find(|midi| (-3..=3).contains(&self.position(*midi, key_letter, key_accidental(key, key_letter))))
+            .unwrap_or(60)
+    }
+}
+fn key_accidental(key: usize, letter: usize) -> i32 {
+    (key as i32 - LETTER_TONES[letter] as i32 + 6).rem_euclid(TOTAL_TONES as i32) - 6
+}
+// the short lines a note above or below the staff needs
+pub fn ledger_lines(position: i32) -> Vec<i32> {
+    match position {
+        p if p < 0 => (p..=-2).filter(|l| l % 2 == 0).collect(),
+        p if p > TOP_LINE => (TOP_LINE + 2..=p).filter(|l| l % 2 == 0).collect(),
+        _ => vec![],
+    }
+}
+pub fn accidental_symbol(accidental: i32) -> String {
+    match accidental {
+        0 => String::from("♮"),
+        2 => String::from("x"),
+        a if a > 0 => "♯".repeat(a as usize),
+        a => "♭".repeat(-a as usize),
+    }
+}
+// clef outlines in staff spaces, x to the right and y up from the line the clef names (G or F)
+pub const TREBLE_CLEF: [(f32, f32); 30] = [
+    (0.2, 0.0), (0.0, 0.3), (-0.35, 0.1), (-0.3, -0.4), (0.15, -0.7), (0.7, -0.5), (0.95, 0.1), (0.7, 0.8),
+    (0.1, 1.05), (-0.6, 0.8), (-0.95, 0.1), (-0.85, -0.7), (-0.3, -1.1), (0.4, -1.1), (0.95, -0.6), (1.1, 0.3),
+    (0.8, 1.3), (0.2, 2.2), (-0.25, 3.0), (-0.35, 3.8), (-0.1, 4.5), (0.25, 4.3), (0.35, 3.5), (0.15, 2.5),
+    (0.0, 1.0), (0.0, -1.5), (0.05, -2.2), (-0.2, -2.6), (-0.55, -2.5), (-0.6, -2.1),
+];
+pub const BASS_CLEF: [(f32, f32); 9] = [
+    (0.0, 0.0), (0.1, 0.45), (0.5, 0.75), (1.0, 0.7), (1.35, 0.3), (1.35, -0.4), (1.0, -1.2), (0.3, -2.0), (-0.1, -2.3),
+];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scales::{Naming, Numbering, Scale, ScaleSize, ScaleType};
+
+    // the signature of a major key by its letters, as written on the staff
+    fn signature(clef: Clef, key: usize, key_letter: usize) -> Vec<i32> {
+        let scale = Scale { typ: ScaleType::Major, siz: ScaleSize::Diatonic, key, key_letter, numbering: Numbering::Simple, naming: Naming::English };
+        scale.signature().iter().map(|(letter, a)| clef.signature_position(*letter, *a)).collect()
+    }
+
+    #[test]
+    fn treble_positions() {
+        // E4 on the bottom line, F5 on the top, middle C on the first ledger line below
+        assert_eq!(Clef::Treble.position(64, 2, 0), 0);
+        assert_eq!(Clef::Treble.position(77, 3, 0), TOP_LINE);
+        assert_eq!(Clef::Treble.position(60, 0, 0), -2);
+        // the letter decides the position, not the pitch: B♯3 sits below C4, C♭5 above B4
+        assert_eq!(Clef::Treble.position(60, 6, 1), -3);
+        assert_eq!(Clef::Treble.position(71, 0, -1), 5);
+        assert_eq!(Clef::Treble.position(71, 6, 0), 4);
+    }
+    #[test]
+    fn bass_positions() {
+        // G2 on the bottom line, A3 on the top, middle C on the first ledger line above
+        assert_eq!(Clef::Bass.position(43, 4, 0), 0);
+        assert_eq!(Clef::Bass.position(57, 5, 0), TOP_LINE);
+        assert_eq!(Clef::Bass.position(60, 0, 0), 10);
+        // the cello's open C
+        assert_eq!(Clef::Bass.position(36, 0, 0), -4);
+    }
+    #[test]
+    fn guitar_music_is_written_an_octave_up() {
+        // the low E string is written E3, three ledger lines below
+        assert_eq!(Clef::Treble8vb.position(40, 2, 0), -7);
+        assert_eq!(ledger_lines(-7), [-6, -4, -2]);
+        assert_eq!(Clef::Treble8vb.position(64, 2, 0), Clef::Treble.position(76, 2, 0));
+        assert_eq!(Clef::Treble8vb.tonic_midi(4, 2), 52);
+        assert_eq!(Clef::Treble.tonic_midi(4, 2), 64);
+        assert_eq!(Clef::Bass.tonic_midi(7, 4), 43);
+    }
+    #[test]
+    fn signatures_sit_where_they_are_engraved() {
+        // F♯ C♯ G♯ D♯ A♯ E♯ (B♯) and B♭ E♭ A♭ D♭ G♭ C♭ (F♭)
+        assert_eq!(signature(Clef::Treble, 1, 0), [8, 5, 9, 6, 3, 7, 4]);
+        assert_eq!(signature(Clef::Treble, 11, 0), [4, 7, 3, 6, 2, 5, 1]);
+        assert_eq!(signature(Clef::Treble8vb, 6, 3), [8, 5, 9, 6, 3, 7]);
+        assert_eq!(signature(Clef::Bass, 1, 0), [6, 3, 7, 4, 1, 5, 2]);
+        assert_eq!(signature(Clef::Bass, 11, 0), [2, 5, 1, 4, 0, 3, -1]);
+        assert!(signature(Clef::Bass, 0, 0).is_empty());
+    }
+    #[test]
+    fn ledger_lines_above_and_below() {
+        assert!(ledger_lines(-1).is_empty() && ledger_lines(9).is_empty() && ledger_lines(4).is_empty());
+        assert_eq!(ledger_lines(-2), [-2]);
+        assert_eq!(ledger_lines(13), [10, 12]);
+        assert_eq!([accidental_symbol(0), accidental_symbol(1), accidental_symbol(-2), accidental_symbol(2)], ["♮", "♯", "♭♭", "x"]);
+    }
+}
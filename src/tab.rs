@@ -0,0 +1,238 @@
+use crate::instruments::FretPos;
+use crate::i18n::{tr, trf};
+use crate::scales::{chromatic_spelling, LETTERS, LETTER_TONES, TOTAL_TONES};
+
+// forum posts and chat windows wrap long lines, so longer runs are split into blocks
+const MAX_WIDTH: usize = 72;
+
+// string names in plain ASCII, flat for lowered tunings (Eb Ab Db Gb Bb eb),
+// with the top string in lower case when it repeats the bottom one: E A D G B e
+pub fn string_names(strings: &[usize]) -> Vec<String> {
+    let mut names: Vec<String> = strings.iter().map(|s| {
+        let (letter, accidental) = chromatic_spelling(s % TOTAL_TONES, true);
+        ascii_name(letter, accidental)
+    }).collect();
+    let lowest = (0..strings.len()).min_by_key(|i| strings[*i]);
+    let highest = (0..strings.len()).rev().max_by_key(|i| strings[*i]);
+    if let (Some(lowest), Some(highest)) = (lowest, highest) {
+        if lowest != highest && names[lowest] == names[highest] {
+            names[highest] = names[highest].to_lowercase();
+        }
+    }
+    names
+}
+fn ascii_name(letter: usize, accidental: i32) -> String {
+    let symbol = match accidental > 0 {
+        true => "#",
+        false => "b",
+    };
+    format!("{}{}", LETTERS[letter % 7], symbol.repeat(accidental.unsigned_abs() as usize))
+}
+// a set of positions played as a run: string by string from the lowest, each string up the neck
+pub fn ascending(notes: &[FretPos], strings: &[usize]) -> Vec<FretPos> {
+    let mut run: Vec<FretPos> = notes.iter().filter(|at| at.string < strings.len()).copied().collect();
+    run.sort_by_key(|at| (strings[at.string], at.fret));
+    run
+}
+// one column per beat, a chord's notes stacked in it, the highest string on top whichever order
+// the tuning lists them in (the lefty tunings are reversed)
+pub fn export(beats: &[Vec<FretPos>], strings: &[usize], names: &[String]) -> String {
+    let mut order: Vec<usize> = (0..strings.len()).collect();
+    order.sort_by_key(|i| std::cmp::Reverse(strings[*i]));
+    let name_width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    let columns: Vec<Vec<(usize, String)>> = beats.iter()
+        .map(|beat| beat.iter().filter(|at| at.string < strings.len()).map(|at| (at.string, at.fret.to_string())).collect::<Vec<_>>())
+        .filter(|column| !column.is_empty())
+        .collect();
+    let column_width = |column: &[(usize, String)]| column.iter().map(|(_, fret)| fret.len()).max().unwrap_or(1);
+    let mut blocks: Vec<&[Vec<(usize, String)>]> = vec![];
+    let mut start = 0;
+    let mut width = 0;
+    for (i, column) in columns.iter().enumerate() {
+        if width + column_width(column) + 1 > MAX_WIDTH - name_width - 3 && i > start {
+            blocks.push(&columns[start..i]);
+            start = i;
+            width = 0;
+        }
+        width += column_width(column) + 1;
+    }
+    blocks.push(&columns[start..]);
+    blocks.iter().map(|block| {
+        order.iter().map(|string| {
+            let cells: String = block.iter().map(|column| {
+                let width = column_width(column);
+                match column.iter().find(|(on, _)| on == string) {
+                    Some((_, fret)) => format!("{:-<width$}-", fret),
+                    None => "-".repeat(width + 1),
+                }
+            }).collect();
+            format!("{:<width$}|-{}|", names.get(*string).map_or("", |n| n.as_str()), cells, width = name_width)
+        }).collect::<Vec<String>>().join("\n")
+    }).collect::<Vec<String>>().join("\n\n")
+}
+// a note read from a pasted tab: its pitch on the current instrument, and where on the current
+// tuning that string plays it (None when the tab tunes the string below its open note)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TabNote {
+    pub note: usize,
+    pub at: Option<FretPos>,
+}
+// besides frets and dashes, tab lines carry hammer-ons (5h7), pull-offs (7p5), bends (7b9),
+// releases (b9r7), slides (5/7, 7\5), vibrato (~), dead notes (x), ghost notes ((5)) and repeats (*)
+const TECHNIQUES: &str = "hpbrs/\\~vx()*.^| ";
+// read a pasted tab onto the given strings, in the order it's played. A tab line has a bar and a dash
+// (anything else, like chord names or lyrics, is skipped); blocks of them are separated by other lines
+// and need one line per string, the highest string on top. Strings named in another tuning than the
+// current one are retuned by the nearest interval, so a drop D tab reads on standard tuning.
+pub fn import(text: &str, strings: &[usize]) -> Result<Vec<TabNote>, String> {
+    let mut order: Vec<usize> = (0..strings.len()).collect();
+    order.sort_by_key(|i| std::cmp::Reverse(strings[*i]));
+    let mut notes: Vec<(usize, usize, TabNote)> = vec![];
+    let mut block: Vec<(usize, &str)> = vec![];
+    let mut blocks = 0;
+    // a trailing blank line closes the last block
+    for (number, line) in text.lines().chain(std::iter::once("")).enumerate() {
+        if line.contains('|') && line.contains('-') {
+            block.push((number + 1, line));
+            continue;
+        }
+        if block.is_empty() {
+            continue;
+        }
+        if block.len() != strings.len() {
+            return Err(trf("tab.error.strings", &[&block[0].0, &block.len(), &strings.len()]));
+        }
+        // a chord is the notes in one column, so every line of a block has to line up
+        let width = |line: &str| line.split_once('|').map_or(0, |(_, body)| body.trim_end().chars().count());
+        let expected = width(block[0].1);
+        if let Some((number, line)) = block.iter().find(|(_, line)| width(line) != expected) {
+            return Err(trf("tab.error.ragged", &[number, &width(line), &expected]));
+        }
+        for ((number, line), string) in block.iter().zip(order.iter()) {
+            let open = strings[*string];
+            let (name, body) = line.split_once('|').unwrap_or_default();
+            let shift = match pitch_class(name.trim()) {
+                Ok(Some(class)) => (class as i32 - open as i32 + 6).rem_euclid(TOTAL_TONES as i32) - 6,
+                Ok(None) => 0,
+                Err(_) => return Err(trf("tab.error.name", &[number, &name.trim()])),
+            };
+            let body: Vec<char> = body.chars().collect();
+            // columns are counted from 1 at the start of the line for the error messages
+            let start = name.chars().count() + 2;
+            let mut column = 0;
+            while column < body.len() {
+                let c = body[column];
+                let digits = body[column..].iter().take_while(|d| d.is_ascii_digit()).count();
+                if digits > 2 {
+                    return Err(trf("tab.error.fret", &[number, &(start + column)]));
+                }
+                if digits > 0 {
+                    let fret: String = body[column..column + digits].iter().collect();
+                    let note = open as i32 + shift + fret.parse::<i32>().unwrap_or(0);
+                    if note < 0 {
+                        return Err(trf("tab.error.low", &[number, &fret]));
+                    }
+                    let at = (note as usize).checked_sub(open).map(|fret| FretPos { string: *string, fret });
+                    notes.push((blocks, column, TabNote { note: note as usize, at }));
+                    column += digits;
+                    continue;
+                }
+                if c != '-' && !TECHNIQUES.contains(c) {
+                    return Err(trf("tab.error.symbol", &[number, &(start + column), &c]));
+                }
+                column += 1;
+            }
+        }
+        block.clear();
+        blocks += 1;
+    }
+    if blocks == 0 {
+        return Err(tr("tab.error.empty"));
+    }
+    // notes in the same column are a chord, kept from the highest string down
+    notes.sort_by_key(|(block, column, _)| (*block, *column));
+    Ok(notes.into_iter().map(|(_, _, note)| note).collect())
+}
+// E, e, F#, Bb, eb... or an unnamed string; Err when the name isn't a note
+fn pitch_class(name: &str) -> Result<Option<usize>, ()> {
+    let mut chars = name.chars();
+    let letter = match chars.next() {
+        None => return Ok(None),
+        Some(c) => LETTERS.iter().position(|l| l.eq_ignore_ascii_case(&c.to_string())).ok_or(())?,
+    };
+    let accidental: i32 = chars.map(|c| match c {
+        '#' | '♯' => Ok(1),
+        'b' | '♭' => Ok(-1),
+        _ => Err(()),
+    }).sum::<Result<i32, ()>>()?;
+    Ok(Some((LETTER_TONES[letter] as i32 + accidental).rem_euclid(TOTAL_TONES as i32) as usize))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUITAR: [usize; 6] = [4, 9, 14, 19, 23, 28];
+
+    fn at(string: usize, fret: usize) -> FretPos {
+        FretPos { string, fret }
+    }
+    fn positions(notes: &[TabNote]) -> Vec<FretPos> {
+        notes.iter().map(|n| n.at.unwrap()).collect()
+    }
+
+    #[test]
+    fn names_strings_in_ascii() {
+        assert_eq!(string_names(&GUITAR), ["E", "A", "D", "G", "B", "e"]);
+        // half a step down
+        assert_eq!(string_names(&[3, 8, 13, 18, 22, 27]), ["Eb", "Ab", "Db", "Gb", "Bb", "eb"]);
+    }
+    #[test]
+    fn exported_runs_read_back() {
+        // long enough to be split into two blocks
+        let run: Vec<Vec<FretPos>> = (0..30).map(|i| vec![at(i % 6, 5 + i % 8)]).collect();
+        let text = export(&run, &GUITAR, &string_names(&GUITAR));
+        assert_eq!(text.split("\n\n").count(), 2);
+        assert!(text.lines().all(|line| line.is_empty() || line.chars().count() <= MAX_WIDTH));
+        let notes = import(&text, &GUITAR).unwrap();
+        assert_eq!(positions(&notes), run.concat());
+        assert!(notes.iter().all(|n| Some(n.note) == n.at.map(|at| at.note(&GUITAR))));
+    }
+    #[test]
+    fn exported_chords_read_back() {
+        // an open C, then a lone G: chords come back from the highest string down
+        let run = vec![vec![at(1, 3), at(2, 2), at(3, 0), at(4, 1), at(5, 0)], vec![at(0, 3)]];
+        let text = export(&run, &GUITAR, &string_names(&GUITAR));
+        assert_eq!(text, "e|-0---|\nB|-1---|\nG|-0---|\nD|-2---|\nA|-3---|\nE|---3-|");
+        let notes = import(&text, &GUITAR).unwrap();
+        assert_eq!(positions(&notes), [at(5, 0), at(4, 1), at(3, 0), at(2, 2), at(1, 3), at(0, 3)]);
+    }
+    #[test]
+    fn lefty_tunings_read_back() {
+        let lefty: Vec<usize> = GUITAR.iter().rev().copied().collect();
+        let run = vec![vec![at(5, 3)], vec![at(0, 1), at(2, 2)]];
+        let notes = import(&export(&run, &lefty, &string_names(&lefty)), &lefty).unwrap();
+        assert_eq!(positions(&notes), [at(5, 3), at(0, 1), at(2, 2)]);
+    }
+    #[test]
+    fn strings_in_another_tuning_are_retuned() {
+        // drop D read on standard tuning: the open low D is below the E string
+        let text = "e|-------|\nB|-------|\nG|-------|\nD|-------|\nA|-------|\nD|-0--2h4|";
+        let notes = import(text, &GUITAR).unwrap();
+        assert_eq!(notes[0], TabNote { note: 2, at: None });
+        assert_eq!(notes[1..].iter().map(|n| n.at).collect::<Vec<_>>(), [Some(at(0, 0)), Some(at(0, 2))]);
+    }
+    #[test]
+    fn malformed_tab_says_where() {
+        let errors = [
+            ("chords: Am\n\nlyrics", "No tab lines found: each string needs a line like e|--5--7--|"),
+            ("e|--0--|\nB|--1--|\nG|--0--|", "The tab block at line 1 has 3 strings, but the instrument has 6"),
+            ("e|--0--|\nB|--1--|\nG|--0--|\nD|--2----|\nA|--3--|\nE|-----|", "Line 4 is 8 characters long, but the first line of its block is 6"),
+            ("e|--0--|\nB|--1--|\nG|--0--|\nD|--2--|\nH|--3--|\nE|-----|", "Line 5: \"H\" isn't a string name"),
+            ("e|--0--|\nB|--1--|\nG|--0--|\nD|--2--|\nA|-123-|\nE|-----|", "Line 5, column 4: frets go up to two digits"),
+            ("e|--0--|\nB|--1--|\nG|--0--|\nD|--2--|\nA|--3--|\nE|--q--|", "Line 6, column 5: unexpected 'q'"),
+        ];
+        for (text, error) in errors {
+            assert_eq!(import(text, &GUITAR), Err(error.to_string()), "{}", text);
+        }
+    }
+}
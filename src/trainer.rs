@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
+
+use crate::instruments::{FretPos, Instrument};
+use crate::rng::Rng;
+use crate::scales::TOTAL_TONES;
+
+pub const STORAGE_KEY: &str = "trainer_history";
+
+#[derive(Debug, EnumIter, PartialEq, Clone, Copy)]
+pub enum Drill {
+    NameNote,
+    FindOnString,
+    Mixed,
+}
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Prompt {
+    NameNote { at: FretPos },
+    FindOnString { at: FretPos },
+}
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Feedback {
+    pub correct: bool,
+    pub at: FretPos,
+    pub seconds: f32,
+}
+// one SM-2 card per position; intervals are counted in questions asked rather than days
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Card {
+    pub ease: f32,
+    pub interval: u64,
+    pub repetitions: u32,
+    pub due: u64,
+    pub attempts: u32,
+    pub correct: u32,
+    pub total_seconds: f32,
+}
+impl Default for Card {
+    fn default() -> Self {
+        Self {
+            ease: 2.5,
+            interval: 0,
+            repetitions: 0,
+            due: 0,
+            attempts: 0,
+            correct: 0,
+            total_seconds: 0.0,
+        }
+    }
+}
+impl Card {
+    fn review(&mut self, correct: bool, seconds: f32, round: u64) {
+        self.attempts += 1;
+        self.total_seconds += seconds;
+        if correct {
+            self.correct += 1;
+        }
+        // SM-2 grade: a miss is 1, a hit is 3 to 5 depending on how quickly it came
+        let quality = match correct {
+            false => 1,
+            true if seconds < 3.0 => 5,
+            true if seconds < 6.0 => 4,
+            true => 3,
+        };
+        self.grade(quality, round);
+    }
+    // a grade of 3 to 5 was recalled and spaces the card out further, below that it starts over
+    fn grade(&mut self, quality: u32, round: u64) {
+        match quality >= 3 {
+            false => {
+                self.repetitions = 0;
+                self.interval = 1;
+            },
+            true => {
+                self.repetitions += 1;
+                self.interval = match self.repetitions {
+                    1 => 1,
+                    2 => 6,
+                    _ => (self.interval as f32 * self.ease).round() as u64,
+                };
+            },
+        }
+        let miss = 5.0 - quality.min(5) as f32;
+        self.ease = (self.ease + 0.1 - miss * (0.08 + miss * 0.02)).max(1.3);
+        self.due = round + self.interval;
+    }
+    pub fn accuracy(&self) -> f32 {
+        match self.attempts {
+            0 => 0.0,
+            n => self.correct as f32 / n as f32,
+        }
+    }
+    pub fn average_seconds(&self) -> f32 {
+        match self.attempts {
+            0 => 0.0,
+            n => self.total_seconds / n as f32,
+        }
+    }
+    // 0 (never right) to 1 (always right, and quickly)
+    pub fn mastery(&self) -> f32 {
+        let speed = match self.average_seconds() {
+            s if s < 3.0 => 1.0,
+            s if s < 6.0 => 0.8,
+            _ => 0.6,
+        };
+        self.accuracy() * speed
+    }
+}
+#[derive(Serialize, Deserialize, Default)]
+pub struct History {
+    pub round: u64,
+    pub cards: BTreeMap<String, Card>,
+}
+pub struct Trainer {
+    pub drill: Drill,
+    pub heat_map: bool,
+    pub history: History,
+    pub prompt: Option<Prompt>,
+    pub feedback: Option<Feedback>,
+    asked_at: f64,
+    rng: Rng,
+}
+impl Default for Trainer {
+    fn default() -> Self {
+        Self {
+            drill: Drill::Mixed,
+            heat_map: false,
+            history: History::default(),
+            prompt: None,
+            feedback: None,
+            asked_at: 0.0,
+            rng: Rng::new(0x7ea1),
+        }
+    }
+}
+pub fn card_key(instrument: &Instrument, at: FretPos) -> String {
+    format!("{}/{}/{}:{}", instrument.name, instrument.tuning().name, at.string, at.fret)
+}
+// string 1 is the highest sounding string, whichever way round the tuning is listed
+pub fn string_number(strings: &[usize], index: usize) -> usize {
+    1 + strings
+        .iter()
+        .enumerate()
+        .filter(|(i, s)| **s > strings[index] || (**s == strings[index] && *i > index))
+        .count()
+}
+impl Trainer {
+    pub fn card(&self, instrument: &Instrument, at: FretPos) -> Option<&Card> {
+        self.history.cards.get(&card_key(instrument, at))
+    }
+    pub fn start(&mut self, seed: u64, instrument: &Instrument, frets: usize, now: f64) {
+        self.rng.seed(seed);
+        self.feedback = None;
+        self.next(instrument, frets, now);
+    }
+    pub fn stop(&mut self) {
+        self.prompt = None;
+    }
+    // overdue positions come first, then ones never asked, then whatever is due soonest
+    pub fn next(&mut self, instrument: &Instrument, frets: usize, now: f64) {
+        let positions: Vec<FretPos> = (0..instrument.strings().len())
+            .flat_map(|string| (0..=frets).map(move |fret| FretPos { string, fret }))
+            .collect();
+        let round = self.history.round;
+        let due = |at: &FretPos| self.card(instrument, *at).map(|c| c.due);
+        let soonest = positions.iter().filter_map(due).min();
+        let overdue: Vec<FretPos> = positions
+            .iter()
+            .copied()
+            .filter(|at| due(at).is_some_and(|d| d <= round && Some(d) == soonest))
+            .collect();
+        let unseen: Vec<FretPos> = positions.iter().copied().filter(|at| due(at).is_none()).collect();
+        let upcoming: Vec<FretPos> = positions.iter().copied().filter(|at| due(at) == soonest).collect();
+        let pool = match (overdue.is_empty(), unseen.is_empty()) {
+            (false, _) => overdue,
+            (true, false) => unseen,
+            (true, true) => upcoming,
+        };
+        let Some(at) = self.rng.pick(&pool) else {
+            self.stop();
+            return;
+        };
+        let find = match self.drill {
+            Drill::NameNote => false,
+            Drill::FindOnString => true,
+            Drill::Mixed => self.rng.below(2) == 1,
+        };
+        self.prompt = Some(match find {
+            true => Prompt::FindOnString { at },
+            false => Prompt::NameNote { at },
+        });
+        self.asked_at = now;
+    }
+    pub fn answer_note(&mut self, pitch_class: usize, instrument: &Instrument, frets: usize, now: f64) {
+        let Some(Prompt::NameNote { at }) = self.prompt else {
+            return;
+        };
+        let correct = at.note(instrument.strings()) % TOTAL_TONES == pitch_class;
+        self.review(at, correct, instrument, frets, now);
+    }
+    pub fn click(&mut self, clicked: FretPos, instrument: &Instrument, frets: usize, now: f64) {
+        let Some(Prompt::FindOnString { at }) = self.prompt else {
+            return;
+        };
+        let strings = instrument.strings();
+        let correct = clicked.string == at.string && clicked.note(strings) % TOTAL_TONES == at.note(strings) % TOTAL_TONES;
+        self.review(at, correct, instrument, frets, now);
+    }
+    fn review(&mut self, at: FretPos, correct: bool, instrument: &Instrument, frets: usize, now: f64) {
+        let seconds = (now - self.asked_at) as f32;
+        let round = self.history.round;
+        self.history.cards.entry(card_key(instrument, at)).or_default().review(correct, seconds, round);
+        self.history.round += 1;
+        self.feedback = Some(Feedback { correct, at, seconds });
+        self.next(instrument, frets, now);
+    }
+    pub fn summary(&self, instrument: &Instrument) -> (usize, f32, f32) {
+        let prefix = format!("{}/{}/", instrument.name, instrument.tuning().name);
+        let cards: Vec<&Card> = self
+            .history
+            .cards
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, card)| card)
+            .collect();
+        let attempts: u32 = cards.iter().map(|c| c.attempts).sum();
+        let correct: u32 = cards.iter().map(|c| c.correct).sum();
+        let seconds: f32 = cards.iter().map(|c| c.total_seconds).sum();
+        match attempts {
+            0 => (cards.len(), 0.0, 0.0),
+            n => (cards.len(), correct as f32 / n as f32, seconds / n as f32),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a card answered correctly twice, so its next interval is 6 × ease
+    fn learned() -> Card {
+        let mut card = Card::default();
+        card.grade(5, 0);
+        card.grade(4, 1);
+        card
+    }
+
+    #[test]
+    fn ease_moves_by_the_sm2_formula() {
+        let changes = [(0, -0.8), (1, -0.54), (2, -0.32), (3, -0.14), (4, 0.0), (5, 0.1)];
+        for (quality, change) in changes {
+            let mut card = learned();
+            let ease = card.ease;
+            card.grade(quality, 10);
+            assert!((card.ease - (ease + change)).abs() < 1e-5, "grade {}: ease {} from {}", quality, card.ease, ease);
+        }
+        // and never below 1.3
+        let mut card = Card::default();
+        for round in 0..10 {
+            card.grade(0, round);
+        }
+        assert_eq!(card.ease, 1.3);
+    }
+    #[test]
+    fn intervals_grow_on_recall_and_reset_on_failure() {
+        let mut card = Card::default();
+        card.grade(4, 0);
+        assert_eq!((card.repetitions, card.interval, card.due), (1, 1, 1));
+        card.grade(4, 1);
+        assert_eq!((card.repetitions, card.interval, card.due), (2, 6, 7));
+        card.grade(4, 7);
+        assert_eq!((card.repetitions, card.interval, card.due), (3, 15, 22));
+        for quality in 0..=5 {
+            let mut card = learned();
+            let ease = card.ease;
+            card.grade(quality, 10);
+            let (repetitions, interval) = match quality >= 3 {
+                true => (3, (6.0 * ease).round() as u64),
+                false => (0, 1),
+            };
+            assert_eq!((card.repetitions, card.interval, card.due), (repetitions, interval, 10 + interval), "grade {}", quality);
+        }
+    }
+    #[test]
+    fn answers_are_graded_by_speed() {
+        let grades = [(false, 1.0, 1), (true, 8.0, 3), (true, 4.0, 4), (true, 1.0, 5)];
+        for (correct, seconds, quality) in grades {
+            let (mut reviewed, mut graded) = (learned(), learned());
+            reviewed.review(correct, seconds, 10);
+            graded.grade(quality, 10);
+            assert_eq!((reviewed.ease, reviewed.interval, reviewed.repetitions), (graded.ease, graded.interval, graded.repetitions));
+        }
+        let mut card = Card::default();
+        card.review(true, 2.0, 0);
+        card.review(false, 4.0, 1);
+        assert_eq!((card.attempts, card.correct, card.accuracy(), card.average_seconds()), (2, 1, 0.5, 3.0));
+    }
+}
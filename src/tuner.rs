@@ -0,0 +1,79 @@
+use crate::instruments::Instrument;
+use crate::pitch::{frequency_to_midi, midi_to_frequency};
+
+pub const IN_TUNE_CENTS: f32 = 5.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TunerReading {
+    pub string: usize,
+    pub target_midi: usize,
+    pub target_frequency: f32,
+    pub cents: f32,
+}
+impl TunerReading {
+    pub fn in_tune(&self) -> bool {
+        self.cents.abs() <= IN_TUNE_CENTS
+    }
+}
+pub fn target_midi(instrument: &Instrument) -> Vec<usize> {
+    instrument.strings().iter().map(|s| instrument.midi_note(*s)).collect()
+}
+// picks the open string closest to the heard frequency, measured in cents
+pub fn nearest_string(instrument: &Instrument, frequency: f32) -> Option<TunerReading> {
+    let heard = frequency_to_midi(frequency);
+    target_midi(instrument)
+        .into_iter()
+        .enumerate()
+        .map(|(string, target)| TunerReading {
+            string,
+            target_midi: target,
+            target_frequency: midi_to_frequency(target as f32),
+            cents: (heard - target as f32) * 100.0,
+        })
+        .min_by(|a, b| a.cents.abs().total_cmp(&b.cents.abs()))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_strings_read_in_tune_in_every_tuning() {
+        let mut tested = vec![];
+        for mut instrument in Instrument::all() {
+            for tune_index in 0..instrument.tunings.len() {
+                instrument.tune_index = tune_index;
+                for (string, midi) in target_midi(&instrument).into_iter().enumerate() {
+                    let reading = nearest_string(&instrument, midi_to_frequency(midi as f32)).unwrap();
+                    let name = format!("{} {} string {}", instrument.name, instrument.tuning().name, string + 1);
+                    assert_eq!(reading.string, string, "{name}");
+                    assert!(reading.cents.abs() < 0.01, "{name}: {} cents", reading.cents);
+                    assert!(reading.in_tune(), "{name}");
+                }
+                tested.push(instrument.tuning().name.clone());
+            }
+        }
+        assert!(tested.iter().any(|name| name == "OPEN C"));
+        assert!(tested.iter().any(|name| name == "DADGAD"));
+    }
+    #[test]
+    fn open_tunings_sound_at_concert_pitch() {
+        let guitar = |name: &str| {
+            let mut guitar = Instrument::guitar();
+            guitar.tune_index = guitar.tunings.iter().position(|t| t.name == name).unwrap();
+            target_midi(&guitar)
+        };
+        assert_eq!(guitar("Standard"), [40, 45, 50, 55, 59, 64]);
+        // C2 G2 C3 G3 C4 E4 and D2 A2 D3 G3 A3 D4
+        assert_eq!(guitar("OPEN C"), [36, 43, 48, 55, 60, 64]);
+        assert_eq!(guitar("DADGAD"), [38, 45, 50, 55, 57, 62]);
+    }
+    #[test]
+    fn a_sharp_string_reads_sharp() {
+        let guitar = Instrument::guitar();
+        // ten cents above the open A
+        let reading = nearest_string(&guitar, midi_to_frequency(45.1)).unwrap();
+        assert_eq!(reading.string, 1);
+        assert!((reading.cents - 10.0).abs() < 0.01);
+        assert!(!reading.in_tune());
+    }
+}